@@ -0,0 +1,60 @@
+/*!
+ * Baselines for the hot path of `--simulate`: scoring a round and drawing
+ * the opponent's move. Run with `cargo bench`.
+ */
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rand::{Rng, SeedableRng};
+use rock_paper_scissors::{calculate_winner, opponent::MoveWeights, GameRng, Move, Variant};
+
+/** How many items each iteration handles, so throughput reads per item. */
+const BATCH: u64 = 1_000;
+
+fn bench_calculate_winner(c: &mut Criterion) {
+    let moves = Variant::LizardSpock.moves();
+    let pairs : Vec<(Move, Move)> = moves.iter().flat_map(|a| moves.iter().map(move |b| (*a, *b))).collect();
+    let mut group = c.benchmark_group("calculate_winner");
+    group.throughput(Throughput::Elements(BATCH));
+    group.bench_function("every matchup", |b| {
+        b.iter(|| {
+            for (user, opponent) in pairs.iter().cycle().take(BATCH as usize) {
+                black_box(calculate_winner(black_box(*user), black_box(*opponent)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_sampling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sampling");
+    group.throughput(Throughput::Elements(BATCH));
+    group.bench_function("standard", |b| {
+        let mut rng = GameRng::seed_from_u64(7);
+        b.iter(|| {
+            for _ in 0..BATCH {
+                black_box(rng.gen::<Move>());
+            }
+        })
+    });
+    group.bench_function("lizard-spock", |b| {
+        let mut rng = GameRng::seed_from_u64(7);
+        b.iter(|| {
+            for _ in 0..BATCH {
+                black_box(rng.sample(Variant::LizardSpock));
+            }
+        })
+    });
+    group.bench_function("weighted", |b| {
+        let weights = MoveWeights::new(Variant::Classic, &[3.0, 1.0, 1.0]).expect("valid weights");
+        let mut rng = GameRng::seed_from_u64(7);
+        b.iter(|| {
+            for _ in 0..BATCH {
+                black_box(rng.sample(&weights));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_calculate_winner, bench_sampling);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+/*!
+ * Feeds arbitrary input to the move parsers: `Move::from_str` and the
+ * `TryFrom<u8>` and `TryFrom<char>` conversions. None of them may panic,
+ * everything they accept has to be one of the spellings the README
+ * documents, and a fixed table of known-good and known-bad spellings has to
+ * parse the way it says, whatever the fuzzer is trying.
+ */
+
+#![no_main]
+
+use std::{str::FromStr, sync::Once};
+
+use libfuzzer_sys::fuzz_target;
+use rock_paper_scissors::Move;
+
+/** Spellings that have to parse, and the move each one is. */
+const KNOWN_GOOD: &[(&str, Move)] = &[
+    ("rock", Move::Rock),
+    ("ROCK", Move::Rock),
+    ("r", Move::Rock),
+    ("0", Move::Rock),
+    ("✊", Move::Rock),
+    ("rocks", Move::Rock),
+    (" rock ", Move::Rock),
+    ("\"rock\"", Move::Rock),
+    ("'rock'", Move::Rock),
+    ("Paper", Move::Paper),
+    ("p", Move::Paper),
+    ("1", Move::Paper),
+    ("✋", Move::Paper),
+    ("papers", Move::Paper),
+    ("scissors", Move::Scissors),
+    ("s", Move::Scissors),
+    ("2", Move::Scissors),
+    ("✌️", Move::Scissors),
+    ("✌", Move::Scissors),
+    ("scissor", Move::Scissors),
+    ("lizard", Move::Lizard),
+    ("l", Move::Lizard),
+    ("3", Move::Lizard),
+    ("🦎", Move::Lizard),
+    ("lizards", Move::Lizard),
+    ("spock", Move::Spock),
+    ("sp", Move::Spock),
+    ("k", Move::Spock),
+    ("4", Move::Spock),
+    ("🖖", Move::Spock),
+    ("spocks", Move::Spock),
+];
+
+/** Spellings that have to be rejected. */
+const KNOWN_BAD: &[&str] = &[
+    "",
+    " ",
+    "5",
+    "256",
+    "-1",
+    "+2",
+    "02",
+    "rockss",
+    "scissorss",
+    "ps",
+    "rs",
+    "stone",
+    "roc",
+    "\"rock'",
+    "\"\"rock\"\"",
+    "\u{fe0f}",
+    "✊✋",
+    "r o c k",
+];
+
+static TABLE: Once = Once::new();
+
+/**
+ * Checks every entry of `KNOWN_GOOD` and `KNOWN_BAD`, once per fuzzing run.
+ */
+fn check_table() {
+    for (input, m) in KNOWN_GOOD {
+        assert_eq!(Move::from_str(input), Ok(*m), "{:?}", input);
+        // Keeps `is_spelling_of` honest about what the README allows.
+        assert!(is_spelling_of(input, *m), "{:?} isn't a spelling of {:?}", input, m);
+    }
+    for input in KNOWN_BAD {
+        assert!(Move::from_str(input).is_err(), "{:?} parsed", input);
+    }
+}
+
+/**
+ * Whether `input` is a documented spelling of `m`: one of its aliases, its
+ * number or its emoji, give or take surrounding whitespace, one pair of
+ * matching quotes, capitals and, for the full name, a plural or singular
+ * "s".
+ */
+fn is_spelling_of(input: &str, m: Move) -> bool {
+    let trimmed = input.trim_matches(|c: char| c.is_ascii_whitespace());
+    let word = ['"', '\'']
+        .iter()
+        .find_map(|quote| trimmed.strip_prefix(*quote).and_then(|rest| rest.strip_suffix(*quote)))
+        .unwrap_or(trimmed);
+    let lower = word.to_ascii_lowercase();
+    let name = m.name();
+    m.aliases().contains(&lower.as_str())
+        || lower.strip_suffix('s') == Some(name)
+        || name.strip_suffix('s') == Some(lower.as_str())
+        || word == u8::from(m).to_string()
+        || word.trim_end_matches('\u{fe0f}') == m.emoji().trim_end_matches('\u{fe0f}')
+}
+
+fuzz_target!(|data: &[u8]| {
+    TABLE.call_once(check_table);
+    for byte in data {
+        assert_eq!(Move::try_from(*byte).is_ok(), *byte < 5);
+    }
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(m) = Move::from_str(input) {
+        assert!(is_spelling_of(input, m), "{:?} parsed as {:?}", input, m);
+        assert_eq!(Move::from_str(&m.to_string()), Ok(m));
+    }
+    for c in input.chars() {
+        if let Ok(m) = Move::try_from(c) {
+            assert_eq!(Move::from_str(&c.to_string()), Ok(m), "{:?}", c);
+        }
+    }
+});
@@ -0,0 +1,175 @@
+/*!
+ * Commit-reveal, so two players who don't trust each other can still play.
+ * Each side first publishes a commitment, the SHA-256 of its move and a
+ * random salt, and only reveals the move and salt once both have committed.
+ * Each reveal is checked against the commitment published earlier, so a move
+ * swapped in after seeing the other side's is caught before scoring.
+ */
+
+use std::{error::Error, fmt, str::FromStr};
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::{Move, ParseMoveError};
+
+/**
+ * The commitment to `m` under `salt`: the hex SHA-256 of e.g. "rock:salt".
+ */
+pub fn commitment(m: Move, salt: &str) -> String {
+    let digest = Sha256::digest(format!("{}:{}", m.name(), salt));
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/**
+ * A fresh salt of 16 random bytes in hex. Without it there would be only a
+ * handful of possible commitments, and the other side could simply try them.
+ */
+pub fn new_salt<R: Rng + ?Sized>(rng: &mut R) -> String {
+    (0..16).map(|_| format!("{:02x}", rng.gen::<u8>())).collect()
+}
+
+/**
+ * What a player reveals once both sides have committed, written as
+ * "move:salt".
+ */
+#[derive(Debug, PartialEq)]
+pub struct Reveal {
+    pub chosen: Move,
+    pub salt: String
+}
+
+impl Reveal {
+    pub fn new(chosen: Move, salt: String) -> Reveal {
+        Reveal { chosen, salt }
+    }
+
+    /**
+     * The commitment to publish before revealing.
+     */
+    pub fn commitment(&self) -> String {
+        commitment(self.chosen, &self.salt)
+    }
+
+    /**
+     * The revealed move, provided it is the one `commitment` was made to.
+     * The commitment has to come from the other side's record of what was
+     * published, never from the player revealing.
+     */
+    pub fn verify(&self, commitment: &str) -> Result<Move, RevealError> {
+        if self.commitment() == commitment.trim().to_ascii_lowercase() {
+            Ok(self.chosen)
+        } else {
+            Err(RevealError::Mismatch)
+        }
+    }
+}
+
+impl fmt::Display for Reveal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.chosen.name(), self.salt)
+    }
+}
+
+impl FromStr for Reveal {
+    type Err = RevealError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts : Vec<&str> = s.trim().split(':').collect();
+        match parts.as_slice() {
+            [chosen, salt] if !salt.is_empty() => Ok(Reveal {
+                chosen: Move::from_str(chosen).map_err(RevealError::Move)?,
+                salt: salt.to_string(),
+            }),
+            _ => Err(RevealError::Malformed(s.to_string())),
+        }
+    }
+}
+
+/**
+ * Why a reveal can't be scored.
+ */
+#[derive(Debug, PartialEq)]
+pub enum RevealError {
+    /** Not of the form "move:salt". */
+    Malformed(String),
+    /** The move part isn't a move. */
+    Move(ParseMoveError),
+    /** The move and salt don't hash to the commitment. */
+    Mismatch
+}
+
+impl fmt::Display for RevealError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RevealError::Malformed(s) => write!(f, "Malformed reveal {:?}: expected move:salt", s),
+            RevealError::Move(err) => write!(f, "{}", err),
+            RevealError::Mismatch => write!(f, "The move doesn't match its commitment"),
+        }
+    }
+}
+
+impl Error for RevealError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use crate::GameRng;
+
+    #[test]
+    fn test_commitment_is_sha256_of_move_and_salt(){
+        // echo -n "rock:pepper" | sha256sum
+        assert_eq!(commitment(Move::Rock, "pepper"), "f07b4a1246fe5c7ca55a7583c0af31976d2345aa881a4abfa3d13cdb51402692")
+    }
+
+    #[test]
+    fn test_valid_reveal(){
+        let reveal = Reveal::new(Move::Paper, new_salt(&mut GameRng::seed_from_u64(7)));
+        let published = reveal.commitment();
+        let parsed : Reveal = reveal.to_string().parse().unwrap();
+        assert_eq!(parsed.verify(&published), Ok(Move::Paper))
+    }
+
+    #[test]
+    fn test_tampered_reveal_is_rejected(){
+        let honest = Reveal::new(Move::Rock, String::from("pepper"));
+        let published = honest.commitment();
+        let tampered = Reveal { chosen: Move::Paper, ..honest };
+        assert_eq!(tampered.verify(&published), Err(RevealError::Mismatch))
+    }
+
+    #[test]
+    fn test_changed_salt_is_rejected(){
+        let honest = Reveal::new(Move::Rock, String::from("pepper"));
+        let published = honest.commitment();
+        let tampered = Reveal { salt: String::from("salt"), ..honest };
+        assert_eq!(tampered.verify(&published), Err(RevealError::Mismatch))
+    }
+
+    #[test]
+    fn test_fresh_reveal_does_not_match_the_published_commitment(){
+        // Having seen the other side's move, the player commits again to
+        // the move that beats it. The new reveal is honest, but not about
+        // the commitment that was published.
+        let published = Reveal::new(Move::Rock, String::from("pepper")).commitment();
+        let fresh = Reveal::new(Move::Paper, String::from("salt"));
+        assert_eq!(fresh.verify(&fresh.commitment()), Ok(Move::Paper));
+        assert_eq!(fresh.verify(&published), Err(RevealError::Mismatch))
+    }
+
+    #[test]
+    fn test_malformed_reveal(){
+        assert_eq!("rock".parse::<Reveal>(), Err(RevealError::Malformed(String::from("rock"))));
+        assert_eq!("rock:pepper:abc".parse::<Reveal>(), Err(RevealError::Malformed(String::from("rock:pepper:abc"))));
+        assert!(matches!("stone:pepper".parse::<Reveal>(), Err(RevealError::Move(_))))
+    }
+
+    #[test]
+    fn test_salts_differ(){
+        let mut rng = GameRng::seed_from_u64(7);
+        let first = new_salt(&mut rng);
+        assert_eq!(first.len(), 32);
+        assert_ne!(first, new_salt(&mut rng))
+    }
+}
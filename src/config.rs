@@ -0,0 +1,135 @@
+/*!
+ * Defaults read from an `rps.toml` file, so favourite options don't have to
+ * be typed every time.
+ */
+
+use std::{fs, path::{Path, PathBuf}};
+
+use clap::ValueEnum;
+use directories::ProjectDirs;
+
+use crate::{lang::Lang, opponent::Strategy, Variant};
+
+/** The name of the config file, looked for in the current directory first. */
+pub const FILE_NAME: &str = "rps.toml";
+
+/**
+ * The settings a config file can hold. Anything left out keeps its built-in
+ * default.
+ */
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub variant: Option<Variant>,
+    pub ai: Option<Strategy>,
+    pub color: Option<bool>,
+    pub lang: Option<Lang>
+}
+
+impl Config {
+    /**
+     * Reads the settings from TOML such as `ai = "frequency"`. Problems are
+     * returned as warnings instead of errors: an unknown key or a bad value
+     * is skipped, and a file that isn't TOML at all gives an empty config.
+     */
+    pub fn parse(source: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut warnings = Vec::new();
+        let table = match source.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                warnings.push(format!("not valid TOML, ignoring it: {}", err.message()));
+                return (config, warnings);
+            }
+        };
+        for (key, value) in &table {
+            let accepted = match key.as_str() {
+                "variant" => value.as_str().and_then(parse_value).map(|v| config.variant = Some(v)),
+                "ai" => value.as_str().and_then(parse_value).map(|v| config.ai = Some(v)),
+                "lang" => value.as_str().and_then(parse_value).map(|v| config.lang = Some(v)),
+                "color" => value.as_bool().map(|v| config.color = Some(v)),
+                _ => {
+                    warnings.push(format!("unknown key {:?}", key));
+                    continue;
+                }
+            };
+            if accepted.is_none() {
+                warnings.push(format!("invalid {}: {}", key, value));
+            }
+        }
+        (config, warnings)
+    }
+
+    /**
+     * Reads the config at `path`, see `Config::parse`. An unreadable file is
+     * a warning too.
+     */
+    pub fn load(path: &Path) -> (Config, Vec<String>) {
+        match fs::read_to_string(path) {
+            Ok(source) => Config::parse(&source),
+            Err(err) => (Config::default(), vec![format!("could not be read: {}", err)]),
+        }
+    }
+}
+
+/**
+ * The name of a value as typed on the command line, in any case.
+ */
+fn parse_value<T: ValueEnum>(name: &str) -> Option<T> {
+    T::from_str(name, true).ok()
+}
+
+/**
+ * The config file to use: `rps.toml` in the current directory, otherwise in
+ * the config directory (e.g. `~/.config/rps/rps.toml` on Linux). `None` if
+ * neither exists.
+ */
+pub fn find() -> Option<PathBuf> {
+    let local = PathBuf::from(FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    ProjectDirs::from("", "", "rps")
+        .map(|dirs| dirs.config_dir().join(FILE_NAME))
+        .filter(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_every_setting(){
+        let (config, warnings) = Config::parse("variant = \"lizard-spock\"\nai = \"Frequency\"\ncolor = false\nlang = \"es\"\n");
+        assert_eq!(config, Config { variant: Some(Variant::LizardSpock), ai: Some(Strategy::Frequency), color: Some(false), lang: Some(Lang::Es) });
+        assert!(warnings.is_empty())
+    }
+
+    #[test]
+    fn test_unknown_key_warns(){
+        let (config, warnings) = Config::parse("ai = \"markov\"\ncolour = false\n");
+        assert_eq!(config, Config { ai: Some(Strategy::Markov), ..Config::default() });
+        assert_eq!(warnings, ["unknown key \"colour\""])
+    }
+
+    #[test]
+    fn test_invalid_value_warns(){
+        let (config, warnings) = Config::parse("ai = \"smart\"\ncolor = \"no\"\n");
+        assert_eq!(config, Config::default());
+        assert_eq!(warnings, ["invalid ai: \"smart\"", "invalid color: \"no\""])
+    }
+
+    #[test]
+    fn test_bad_syntax_warns(){
+        let (config, warnings) = Config::parse("ai = ");
+        assert_eq!(config, Config::default());
+        assert_eq!(warnings.len(), 1)
+    }
+
+    #[test]
+    fn test_missing_file_warns(){
+        let dir = tempfile::tempdir().unwrap();
+        let (config, warnings) = Config::load(&dir.path().join(FILE_NAME));
+        assert_eq!(config, Config::default());
+        assert_eq!(warnings.len(), 1)
+    }
+}
@@ -0,0 +1,138 @@
+/*!
+ * Translations of the words the game shows: move names and results.
+ */
+
+use clap::ValueEnum;
+
+use crate::{GameResult, Move, ParseMoveError, Variant};
+
+/**
+ * A language the game can be played in.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Lang {
+    /// English.
+    En,
+    /// Spanish.
+    Es
+}
+
+/**
+ * Every string that changes with the language.
+ */
+pub struct Strings {
+    /// Move names, in the order of `Move::all`.
+    pub moves: [&'static str; 5],
+    pub win: &'static str,
+    pub tie: &'static str,
+    pub lose: &'static str,
+    /// Introduces the opponent's move, as in "Opponent's move: Rock".
+    pub opponent_move: &'static str,
+    /// Shown when an interactive move didn't arrive within --timeout.
+    pub timed_out: &'static str,
+    /// Introduces the --practice suggestion, as in "You should have played Paper".
+    pub should_have_played: &'static str
+}
+
+const EN: Strings = Strings {
+    moves: ["Rock", "Paper", "Scissors", "Lizard", "Spock"],
+    win: "You win!",
+    tie: "Tie",
+    lose: "You lose!",
+    opponent_move: "Opponent's move",
+    timed_out: "Time's up! The opponent wins the round.",
+    should_have_played: "You should have played"
+};
+
+const ES: Strings = Strings {
+    moves: ["Piedra", "Papel", "Tijera", "Lagarto", "Spock"],
+    win: "¡Ganaste!",
+    tie: "Empate",
+    lose: "¡Perdiste!",
+    opponent_move: "Jugada del rival",
+    timed_out: "¡Se acabó el tiempo! El rival gana la ronda.",
+    should_have_played: "Deberías haber jugado"
+};
+
+impl Lang {
+    pub fn strings(&self) -> &'static Strings {
+        match self {
+            Lang::En => &EN,
+            Lang::Es => &ES,
+        }
+    }
+
+    pub fn move_name(&self, m: &Move) -> &'static str {
+        self.strings().moves[usize::from(u8::from(*m))]
+    }
+
+    pub fn result_message(&self, result: &GameResult) -> &'static str {
+        let strings = self.strings();
+        match result {
+            GameResult::UserWin => strings.win,
+            GameResult::Tie => strings.tie,
+            GameResult::OpponentWin => strings.lose,
+        }
+    }
+
+    /**
+     * Like `Variant::parse_move`, but also accepts the move names of this
+     * language in any case, e.g. "piedra" in Spanish.
+     */
+    pub fn parse_move(&self, variant: Variant, pattern: &str) -> Result<Move, ParseMoveError> {
+        let localized = variant.moves().iter().find(|m| self.move_name(m).to_lowercase() == pattern.to_lowercase());
+        match localized {
+            Some(m) => Ok(*m),
+            None => variant.parse_move(pattern),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_matches_display(){
+        for m in Move::all() {
+            assert_eq!(Lang::En.move_name(m), m.to_string())
+        }
+    }
+
+    #[test]
+    fn test_spanish_move_names(){
+        assert_eq!(Lang::Es.move_name(&Move::Rock), "Piedra");
+        assert_eq!(Lang::Es.move_name(&Move::Paper), "Papel");
+        assert_eq!(Lang::Es.move_name(&Move::Scissors), "Tijera")
+    }
+
+    #[test]
+    fn test_spanish_result_messages(){
+        assert_eq!(Lang::Es.result_message(&GameResult::UserWin), "¡Ganaste!");
+        assert_eq!(Lang::Es.result_message(&GameResult::Tie), "Empate");
+        assert_eq!(Lang::Es.result_message(&GameResult::OpponentWin), "¡Perdiste!")
+    }
+
+    #[test]
+    fn test_parse_spanish_move(){
+        assert_eq!(Lang::Es.parse_move(Variant::Classic, "piedra"), Ok(Move::Rock));
+        assert_eq!(Lang::Es.parse_move(Variant::Classic, "TIJERA"), Ok(Move::Scissors));
+        assert_eq!(Lang::Es.parse_move(Variant::LizardSpock, "Lagarto"), Ok(Move::Lizard))
+    }
+
+    #[test]
+    fn test_parse_spanish_falls_back_to_english(){
+        assert_eq!(Lang::Es.parse_move(Variant::Classic, "paper"), Ok(Move::Paper));
+        assert_eq!(Lang::Es.parse_move(Variant::Classic, "p"), Ok(Move::Paper))
+    }
+
+    #[test]
+    fn test_parse_spanish_respects_variant(){
+        assert_eq!(Lang::Es.parse_move(Variant::Classic, "lagarto"), Err(ParseMoveError::new(String::from("lagarto"))))
+    }
+
+    #[test]
+    fn test_english_does_not_accept_spanish(){
+        assert!(Lang::En.parse_move(Variant::Classic, "piedra").is_err())
+    }
+}
@@ -0,0 +1,1654 @@
+/*!
+ * The rules of rock paper scissors: moves, variants and scoring.
+ */
+
+pub mod commit;
+pub mod config;
+pub mod lang;
+pub mod opponent;
+pub mod rules;
+pub mod stats;
+pub mod tournament;
+pub mod transcript;
+
+use std::{cmp::Ordering, error::Error, fmt, str::FromStr};
+
+use clap::ValueEnum;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng,
+};
+use rand_chacha::ChaCha8Rng;
+
+/**
+ * The generator behind every game. It is pinned to ChaCha8 rather than
+ * `StdRng`, whose algorithm `rand` is free to change in any release, so a
+ * seed replays the same game across versions and platforms.
+ */
+pub type GameRng = ChaCha8Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Rock,
+    Paper,
+    Scissors,
+    Lizard,
+    Spock
+}
+
+/**
+ * The rule set in play. The classic game only uses the first three moves,
+ * lizard-spock adds Lizard and Spock.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Variant {
+    Classic,
+    LizardSpock
+}
+
+impl Variant {
+    /**
+     * The moves that can be played in this variant.
+     */
+    pub fn moves(&self) -> &'static [Move] {
+        match self {
+            Variant::Classic => &[Move::Rock, Move::Paper, Move::Scissors],
+            Variant::LizardSpock => &[Move::Rock, Move::Paper, Move::Scissors, Move::Lizard, Move::Spock],
+        }
+    }
+
+    /**
+     * Cycles through every move of the variant, each move beating the one
+     * before it and the last beaten by the first. The classic game is the one
+     * cycle of `Move::next_in_cycle`. In lizard-spock every move beats two
+     * others, which no single cycle can capture, so there are two: together
+     * they hold each matchup exactly once.
+     */
+    pub fn cycles(&self) -> &'static [&'static [Move]] {
+        match self {
+            Variant::Classic => &[&[Move::Rock, Move::Paper, Move::Scissors]],
+            Variant::LizardSpock => &[
+                &[Move::Rock, Move::Paper, Move::Scissors, Move::Spock, Move::Lizard],
+                &[Move::Rock, Move::Spock, Move::Paper, Move::Lizard, Move::Scissors],
+            ],
+        }
+    }
+
+    /**
+     * Parses a move, rejecting moves that are not part of this variant.
+     * Under lizard-spock a bare "s" is rejected as ambiguous, since it could
+     * mean Scissors or Spock.
+     */
+    pub fn parse_move(&self, pattern : &str) -> Result<Move, ParseMoveError> {
+        if let Some(candidates) = self.ambiguous(pattern) {
+            return Err(ParseMoveError { input: pattern.to_string(), candidates: candidates.to_vec() });
+        }
+        let our_move = Move::from_str(pattern)?;
+        if self.moves().contains(&our_move) {
+            Ok(our_move)
+        } else {
+            Err(ParseMoveError::new(pattern.to_string()))
+        }
+    }
+
+    /**
+     * The names of `m` that this variant accepts: its `Move::aliases`,
+     * without any that are ambiguous here.
+     */
+    pub fn aliases(&self, m: &Move) -> Vec<&'static str> {
+        m.aliases().iter().copied().filter(|alias| self.ambiguous(alias).is_none()).collect()
+    }
+
+    /**
+     * The moves an abbreviation could stand for when it names more than
+     * one move of this variant.
+     */
+    fn ambiguous(&self, pattern : &str) -> Option<&'static [Move]> {
+        let s = unquote(pattern.trim_matches(|c: char| c.is_ascii_whitespace()));
+        match self {
+            Variant::LizardSpock if s.eq_ignore_ascii_case("s") => Some(&[Move::Scissors, Move::Spock]),
+            _ => None,
+        }
+    }
+}
+
+impl Distribution<Move> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Move {
+        sample_up_to(rng, Move::Scissors)
+    }
+}
+
+impl Distribution<Move> for Variant {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Move {
+        match self {
+            // Sample exactly as `Standard` does so seeded classic games are unchanged.
+            Variant::Classic => Standard.sample(rng),
+            Variant::LizardSpock => sample_up_to(rng, Move::Spock),
+        }
+    }
+}
+
+/**
+ * Draws a move by its number, from Rock up to `last` inclusive.
+ */
+fn sample_up_to<R: Rng + ?Sized>(rng: &mut R, last: Move) -> Move {
+    // The index is drawn as an i32: drawing a u8 would consume the RNG
+    // differently and change every seeded game.
+    let index : i32 = rng.gen_range(0..=i32::from(u8::from(last)));
+    Move::try_from(index as u8).expect("index is a valid move number")
+}
+
+/**
+ * Moves are numbered in declaration order: 0 is Rock, 1 Paper, 2 Scissors,
+ * 3 Lizard and 4 Spock. These are also the numbers the opponent samples.
+ */
+impl TryFrom<u8> for Move {
+    type Error = ParseMoveError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Move::all()
+            .nth(usize::from(value))
+            .copied()
+            .ok_or_else(|| ParseMoveError::new(value.to_string()))
+    }
+}
+
+/**
+ * The move's number, the inverse of `TryFrom<u8>`.
+ */
+impl From<Move> for u8 {
+    fn from(m: Move) -> u8 {
+        // Discriminants follow declaration order, which is the numbering.
+        m as u8
+    }
+}
+
+impl Move {
+    /**
+     * Whether this move defeats `other`. Every pair of distinct moves has
+     * exactly one winner, and no move beats itself.
+     */
+    pub fn beats(&self, other: &Move) -> bool {
+        self.verb(other).is_some()
+    }
+
+    /**
+     * How this move defeats `other`, e.g. "covers" for Paper against Rock.
+     * `None` unless it beats `other`; this table is the whole beats relation.
+     */
+    pub fn verb(&self, other: &Move) -> Option<&'static str> {
+        match (self, other) {
+            (Move::Rock, Move::Scissors) => Some("crushes"),
+            (Move::Rock, Move::Lizard) => Some("crushes"),
+            (Move::Paper, Move::Rock) => Some("covers"),
+            (Move::Paper, Move::Spock) => Some("disproves"),
+            (Move::Scissors, Move::Paper) => Some("cuts"),
+            (Move::Scissors, Move::Lizard) => Some("decapitates"),
+            (Move::Lizard, Move::Paper) => Some("eats"),
+            (Move::Lizard, Move::Spock) => Some("poisons"),
+            (Move::Spock, Move::Rock) => Some("vaporizes"),
+            (Move::Spock, Move::Scissors) => Some("smashes"),
+            _ => None,
+        }
+    }
+
+    /**
+     * The move that beats this one in the classic game, e.g. Paper for Rock.
+     * Lizard and Spock aren't classic moves and have two counters each; this
+     * returns the first of `counters(Variant::LizardSpock)` for them.
+     */
+    pub fn counter(&self) -> Move {
+        let variant = if Variant::Classic.moves().contains(self) { Variant::Classic } else { Variant::LizardSpock };
+        self.counters(variant)[0]
+    }
+
+    /**
+     * Every move of `variant` that beats this one, in the variant's order.
+     */
+    pub fn counters(&self, variant: Variant) -> Vec<Move> {
+        variant.moves().iter().copied().filter(|m| m.beats(self)).collect()
+    }
+
+    /**
+     * The next move of the classic cycle rock, paper, scissors, where each
+     * move beats the one before it, so Scissors is followed by Rock. Lizard
+     * and Spock aren't on it: see `Variant::cycles` for lizard-spock.
+     */
+    pub fn next_in_cycle(&self) -> Option<Move> {
+        match self {
+            Move::Rock => Some(Move::Paper),
+            Move::Paper => Some(Move::Scissors),
+            Move::Scissors => Some(Move::Rock),
+            Move::Lizard | Move::Spock => None,
+        }
+    }
+
+    /**
+     * Walks the classic cycle from this move on, forever: e.g. Paper,
+     * Scissors, Rock, Paper... Each move is the one that beats the move
+     * before it. For Lizard and Spock it yields just the move itself.
+     */
+    pub fn cycle(self) -> impl Iterator<Item = Move> {
+        std::iter::successors(Some(self), Move::next_in_cycle)
+    }
+}
+
+impl Move {
+    /**
+     * Every move in any variant, in declaration order.
+     */
+    pub fn all() -> impl Iterator<Item = &'static Move> {
+        Variant::LizardSpock.moves().iter()
+    }
+
+    /**
+     * The move's name in lowercase, e.g. "rock". It is what JSON and CSV
+     * output write, and `Display` capitalizes it.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Move::Rock => "rock",
+            Move::Paper => "paper",
+            Move::Scissors => "scissors",
+            Move::Lizard => "lizard",
+            Move::Spock => "spock",
+        }
+    }
+
+    /**
+     * The lowercase names `FromStr` accepts for the move, full name first.
+     * "s" was taken by Scissors long before Spock arrived, so Spock is
+     * abbreviated "sp" or "k" (its hard sound) instead; `Variant::parse_move`
+     * refuses a bare "s" under lizard-spock rather than guess.
+     */
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Move::Rock => &["rock", "r"],
+            Move::Paper => &["paper", "p"],
+            Move::Scissors => &["scissors", "s"],
+            Move::Lizard => &["lizard", "l"],
+            Move::Spock => &["spock", "sp", "k"],
+        }
+    }
+
+    /**
+     * The hand sign for the move, e.g. "✊" for Rock.
+     */
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Move::Rock => "✊",
+            Move::Paper => "✋",
+            Move::Scissors => "✌️",
+            Move::Lizard => "🦎",
+            Move::Spock => "🖖",
+        }
+    }
+}
+
+/**
+ * Compares moves by who wins: `a > b` when `a` beats `b`.
+ *
+ * This relation is cyclic (rock beats scissors beats paper beats rock), so
+ * `Move` deliberately has no `Ord`: a total order would have to disagree with
+ * the game. When a total order is needed, e.g. to sort moves or key a
+ * `BTreeMap`, wrap them in `ByName` instead.
+ */
+impl PartialOrd for Move {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else if self.beats(other) {
+            Some(Ordering::Greater)
+        } else {
+            Some(Ordering::Less)
+        }
+    }
+}
+
+/**
+ * Orders moves alphabetically by name. It has nothing to do with who wins,
+ * use `Move::beats` for that.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByName(pub Move);
+
+impl Ord for ByName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.name().cmp(other.0.name())
+    }
+}
+
+impl PartialOrd for ByName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/**
+ * Writes the move's name, e.g. "Rock". The alternate form (`{:#}`) writes it
+ * in lowercase instead, e.g. "rock".
+ */
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.name();
+        if f.alternate() {
+            f.write_str(name)
+        } else {
+            let (first, rest) = name.split_at(1);
+            write!(f, "{}{}", first.to_ascii_uppercase(), rest)
+        }
+    }
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/**
+ * Reads a move back from its name, or any other spelling `from_str` takes.
+ */
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
+
+/**
+ * Returned when a string doesn't name a move. Carries the offending input,
+ * and the moves it could have meant when it was ambiguous.
+ */
+#[derive(Debug, PartialEq)]
+pub struct ParseMoveError {
+    pub input: String,
+    pub candidates: Vec<Move>
+}
+
+impl ParseMoveError {
+    /**
+     * An error for input that doesn't name any move.
+     */
+    pub fn new(input: impl Into<String>) -> ParseMoveError {
+        ParseMoveError { input: input.into(), candidates: Vec::new() }
+    }
+
+    /**
+     * Whether no move was given at all, as opposed to a word that isn't a
+     * move. Whitespace and empty quotes count as nothing.
+     */
+    pub fn is_empty(&self) -> bool {
+        unquote(self.input.trim_matches(|c: char| c.is_ascii_whitespace())).is_empty()
+    }
+}
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            write!(f, "No move provided — expected rock, paper, or scissors")
+        } else if !self.candidates.is_empty() {
+            let names : Vec<&str> = self.candidates.iter().map(|m| m.name()).collect();
+            write!(f, "Ambiguous move: {:?} could be {}; spell it out", self.input, names.join(" or "))
+        } else {
+            write!(f, "Invalid move: {:?}", self.input)
+        }
+    }
+}
+
+impl Error for ParseMoveError {}
+
+/**
+ * Parses a single-letter alias such as 'r' (in either case) or a hand emoji
+ * such as '✊'. Nothing is allocated unless the character is invalid.
+ */
+impl TryFrom<char> for Move {
+    type Error = ParseMoveError;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        let lower = value.to_ascii_lowercase();
+        let mut buffer = [0; 4];
+        let letter : &str = lower.encode_utf8(&mut buffer);
+        Move::all()
+            .find(|m| m.aliases().contains(&letter) || m.emoji().trim_end_matches(VARIATION_SELECTOR) == letter)
+            .copied()
+            .ok_or_else(|| ParseMoveError::new(value.to_string()))
+    }
+}
+
+/** Asks for the emoji presentation of the preceding character. */
+const VARIATION_SELECTOR: char = '\u{fe0f}';
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let s = unquote(input.trim_matches(|c: char| c.is_ascii_whitespace()));
+        let invalid = || ParseMoveError::new(input.to_string());
+        // A move's number is a single digit, so "+2" and "02" aren't moves.
+        if let [digit @ b'0'..=b'9'] = s.as_bytes() {
+            return Move::try_from(digit - b'0').map_err(|_| invalid());
+        }
+        // Emoji are matched without the variation selector that some
+        // keyboards add and others leave out, so "✌" and "✌️" both work.
+        let symbol = s.trim_end_matches(VARIATION_SELECTOR);
+        if let Some(m) = Move::all().find(|m| m.emoji().trim_end_matches(VARIATION_SELECTOR) == symbol) {
+            return Ok(*m);
+        }
+        let lower = s.to_ascii_lowercase();
+        Move::all()
+            .find(|m| m.aliases().contains(&lower.as_str()))
+            .or_else(|| Move::all().find(|m| is_plural_or_singular(&lower, m.name())))
+            .copied()
+            .ok_or_else(invalid)
+    }
+}
+
+/**
+ * Whether `word` is the other number of `name`: "rocks" for "rock", or
+ * "scissor" for "scissors". A name ending in "s" only loses it, so
+ * "scissorss" isn't a move. Only full names are bent this way, so short
+ * aliases like "p" don't turn "ps" into a move.
+ */
+fn is_plural_or_singular(word: &str, name: &str) -> bool {
+    match name.strip_suffix('s') {
+        Some(singular) => word == singular,
+        None => word.strip_suffix('s') == Some(name),
+    }
+}
+
+/**
+ * Strips one pair of matching single or double quotes, as left behind by
+ * over-quoted shell scripts.
+ */
+fn unquote(s: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(quote).and_then(|rest| rest.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    s
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    UserWin,
+    OpponentWin,
+    Tie
+}
+
+impl GameResult {
+    /**
+     * The result's name, e.g. "UserWin", as printed by --quiet and written
+     * to JSON and CSV.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameResult::UserWin => "UserWin",
+            GameResult::OpponentWin => "OpponentWin",
+            GameResult::Tie => "Tie",
+        }
+    }
+}
+
+impl Serialize for GameResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/**
+ * What a tie counts for under house rules. Some treat a tie as a loss for
+ * the challenger, others replay it.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TiePolicy {
+    Tie,
+    User,
+    Opponent,
+    Replay
+}
+
+impl TiePolicy {
+    /**
+     * Reinterprets a result from `calculate_winner`: a tie goes to the side
+     * the policy names, or is `None` when the round has to be replayed.
+     * Decisive results are left alone.
+     */
+    pub fn resolve(&self, result: GameResult) -> Option<GameResult> {
+        match (result, self) {
+            (GameResult::Tie, TiePolicy::Tie) => Some(GameResult::Tie),
+            (GameResult::Tie, TiePolicy::User) => Some(GameResult::UserWin),
+            (GameResult::Tie, TiePolicy::Opponent) => Some(GameResult::OpponentWin),
+            (GameResult::Tie, TiePolicy::Replay) => None,
+            (decisive, _) => Some(decisive),
+        }
+    }
+
+    /**
+     * The policy as it is written on the command line and in transcripts.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            TiePolicy::Tie => "tie",
+            TiePolicy::User => "user",
+            TiePolicy::Opponent => "opponent",
+            TiePolicy::Replay => "replay",
+        }
+    }
+}
+
+/**
+ * A single round: both moves and who won. This is the richer counterpart of
+ * `calculate_winner`, for reporting that needs the moves as well as the
+ * verdict, which stays available as `result`.
+ *
+ * ```
+ * use rock_paper_scissors::{GameResult, Move, Round};
+ *
+ * let round = Round::new(Move::Rock, Move::Paper);
+ * assert_eq!(round.result, GameResult::OpponentWin);
+ * assert_eq!(round.explain(), "Paper covers Rock, so you lose.");
+ * ```
+ */
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Round {
+    pub opponent_move: Move,
+    pub user_move: Move,
+    pub result: GameResult
+}
+
+/**
+ * Running count of results over a multi-round game.
+ */
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Tally {
+    pub user_wins: u32,
+    pub opponent_wins: u32,
+    pub ties: u32
+}
+
+impl Tally {
+    pub fn record(&mut self, result: &GameResult) {
+        match result {
+            GameResult::UserWin => self.user_wins += 1,
+            GameResult::OpponentWin => self.opponent_wins += 1,
+            GameResult::Tie => self.ties += 1,
+        }
+    }
+
+    /**
+     * How many rounds have been recorded.
+     */
+    pub fn played(&self) -> u32 {
+        self.user_wins + self.opponent_wins + self.ties
+    }
+
+    /**
+     * Share of rounds the user won, between 0 and 1. Zero when nothing has
+     * been played.
+     */
+    pub fn win_rate(&self) -> f64 {
+        let played = self.played();
+        if played == 0 {
+            0.0
+        } else {
+            f64::from(self.user_wins) / f64::from(played)
+        }
+    }
+
+    /**
+     * The outcome of the whole series: whoever won more rounds.
+     */
+    pub fn overall(&self) -> GameResult {
+        match self.user_wins.cmp(&self.opponent_wins) {
+            Ordering::Greater => GameResult::UserWin,
+            Ordering::Less => GameResult::OpponentWin,
+            Ordering::Equal => GameResult::Tie,
+        }
+    }
+}
+
+impl fmt::Display for Tally {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tie_word = if self.ties == 1 { "tie" } else { "ties" };
+        write!(f, "You {} - Opponent {} ({} {})", self.user_wins, self.opponent_wins, self.ties, tie_word)
+    }
+}
+
+impl Round {
+    /**
+     * Scores a round between the two moves.
+     */
+    pub fn new(user_move: Move, opponent_move: Move) -> Round {
+        let result = calculate_winner(user_move, opponent_move);
+        Round { opponent_move, user_move, result }
+    }
+
+    /**
+     * Why the round ended the way it did, see `explain`.
+     */
+    pub fn explain(&self) -> String {
+        explain(self.user_move, self.opponent_move)
+    }
+}
+
+/**
+ * The longest runs of wins, losses and ties in a row over a series.
+ */
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Streaks {
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32
+}
+
+impl Streaks {
+    /**
+     * Walks the rounds in order, keeping the longest run of each result.
+     */
+    pub fn of(rounds: &[Round]) -> Streaks {
+        let mut longest = Streaks::default();
+        let mut run = 0;
+        for (index, round) in rounds.iter().enumerate() {
+            if index > 0 && rounds[index - 1].result == round.result {
+                run += 1;
+            } else {
+                run = 1;
+            }
+            let best = match round.result {
+                GameResult::UserWin => &mut longest.wins,
+                GameResult::OpponentWin => &mut longest.losses,
+                GameResult::Tie => &mut longest.ties,
+            };
+            *best = (*best).max(run);
+        }
+        longest
+    }
+}
+
+/**
+ * The seed for round `index` (counting from 0) of a multi-round game seeded
+ * with `base`. Seeding a single game with it replays just that round.
+ */
+pub fn round_seed(base: u64, index: u32) -> u64 {
+    base ^ u64::from(index)
+}
+
+/**
+ * A seed from a memorable phrase, using the 64-bit FNV-1a hash of its UTF-8
+ * bytes. The hash is fixed here rather than borrowed from `std`, whose
+ * hashers may change between releases, so a phrase keeps giving the same game
+ * in every version.
+ *
+ * ```
+ * use rock_paper_scissors::seed_from_phrase;
+ *
+ * assert_eq!(seed_from_phrase("lucky cat"), seed_from_phrase("lucky cat"));
+ * ```
+ */
+pub fn seed_from_phrase(phrase: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    phrase.bytes().fold(OFFSET_BASIS, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(PRIME))
+}
+
+/**
+ * Every round of a series, plus the totals.
+ */
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SeriesResult {
+    pub rounds: Vec<Round>,
+    pub tally: Tally
+}
+
+/**
+ * Returned by `calculate_series` when the two sides threw a different
+ * number of moves.
+ */
+#[derive(Debug, PartialEq)]
+pub struct SeriesLengthError {
+    pub user_moves: usize,
+    pub opponent_moves: usize
+}
+
+impl fmt::Display for SeriesLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mismatched series: {} user moves but {} opponent moves", self.user_moves, self.opponent_moves)
+    }
+}
+
+impl Error for SeriesLengthError {}
+
+/**
+ * Scores a whole series, pairing the moves up round by round. Both slices
+ * must be the same length; nothing is scored otherwise.
+ *
+ * ```
+ * use rock_paper_scissors::{calculate_series, Move};
+ *
+ * let series = calculate_series(&[Move::Rock, Move::Paper], &[Move::Scissors, Move::Paper]).unwrap();
+ * assert_eq!(series.tally.user_wins, 1);
+ * assert_eq!(series.tally.ties, 1);
+ * ```
+ */
+pub fn calculate_series(user_moves: &[Move], opponent_moves: &[Move]) -> Result<SeriesResult, SeriesLengthError> {
+    if user_moves.len() != opponent_moves.len() {
+        return Err(SeriesLengthError { user_moves: user_moves.len(), opponent_moves: opponent_moves.len() });
+    }
+    let mut series = SeriesResult::default();
+    for (user, opponent) in user_moves.iter().zip(opponent_moves) {
+        let round = Round::new(*user, *opponent);
+        series.tally.record(&round.result);
+        series.rounds.push(round);
+    }
+    Ok(series)
+}
+
+/**
+ * Scores a round from the user's point of view. `Round::new` gives the
+ * same verdict together with the moves.
+ *
+ * ```
+ * use rock_paper_scissors::{calculate_winner, GameResult, Move};
+ *
+ * assert_eq!(calculate_winner(Move::Paper, Move::Rock), GameResult::UserWin);
+ * assert_eq!(calculate_winner(Move::Paper, Move::Scissors), GameResult::OpponentWin);
+ * ```
+ */
+pub fn calculate_winner(user: Move, opponent:Move) -> GameResult {
+    if user.beats(&opponent) {
+        GameResult::UserWin
+    } else if opponent.beats(&user) {
+        GameResult::OpponentWin
+    } else {
+        GameResult::Tie
+    }
+}
+
+/**
+ * Why the round ended the way it did, from the user's point of view, e.g.
+ * "Paper covers Rock, so you win."
+ *
+ * ```
+ * use rock_paper_scissors::{explain, Move};
+ *
+ * assert_eq!(explain(Move::Rock, Move::Paper), "Paper covers Rock, so you lose.");
+ * ```
+ */
+pub fn explain(user: Move, opponent: Move) -> String {
+    if let Some(verb) = user.verb(&opponent) {
+        format!("{} {} {}, so you win.", user, verb, opponent)
+    } else if let Some(verb) = opponent.verb(&user) {
+        format!("{} {} {}, so you lose.", opponent, verb, user)
+    } else {
+        format!("You both played {}, so it's a tie.", user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use rand::SeedableRng;
+
+        /** ==== Partial ordering ==== **/
+        /* ==== Rocks ==== */
+
+    #[test]
+    fn test_partial_ordering_rock_rock(){
+        assert_eq!(Move::Rock, Move::Rock)
+    }
+
+    #[test]
+    fn test_partial_ordering_rock_paper(){
+        assert!(Move::Rock < Move::Paper)
+    }
+
+    #[test]
+    fn test_partial_ordering_rock_scissors(){
+        assert!(Move::Rock > Move::Scissors)
+    }
+
+        /* ==== Paper ==== */
+
+    #[test]
+    fn test_partial_ordering_paper_rock(){
+        assert!(Move::Paper > Move::Rock)
+    }
+
+    #[test]
+    fn test_partial_ordering_paper_paper(){
+        assert_eq!(Move::Paper, Move::Paper)
+    }
+
+    #[test]
+    fn test_partial_ordering_paper_scissors(){
+        assert!(Move::Paper < Move::Scissors)
+    }
+
+        /* ==== Scissors ==== */
+
+    #[test]
+    fn test_partial_ordering_scissors_rock(){
+        assert!(Move::Scissors < Move::Rock)
+    }
+
+    #[test]
+    fn test_partial_ordering_scissors_paper(){
+        assert!(Move::Scissors > Move::Paper)
+    }
+
+    #[test]
+    fn test_partial_ordering_scissors_scissors(){
+        assert_eq!(Move::Scissors, Move::Scissors)
+    }
+
+        /* ==== Ordering is not transitive ==== */
+
+    #[test]
+    fn test_partial_ordering_is_cyclic(){
+        assert!(Move::Rock > Move::Scissors);
+        assert!(Move::Scissors > Move::Paper);
+        assert!(Move::Paper > Move::Rock)
+    }
+
+        /** ==== ByName ==== **/
+
+    #[test]
+    fn test_by_name_sorts_alphabetically(){
+        let mut moves : Vec<ByName> = Move::all().copied().map(ByName).collect();
+        moves.sort();
+        let sorted : Vec<Move> = moves.into_iter().map(|m| m.0).collect();
+        assert_eq!(sorted, [Move::Lizard, Move::Paper, Move::Rock, Move::Scissors, Move::Spock])
+    }
+
+    #[test]
+    fn test_by_name_ignores_game_logic(){
+        // Rock beats Scissors, yet sorts before it.
+        assert!(ByName(Move::Rock) < ByName(Move::Scissors));
+        assert_eq!(calculate_winner(Move::Rock, Move::Scissors), GameResult::UserWin)
+    }
+
+        /** ==== Hash ==== **/
+
+    #[test]
+    fn test_move_as_map_key(){
+        let mut counts : HashMap<Move, u32> = HashMap::new();
+        for m in [Move::Rock, Move::Paper, Move::Rock, Move::Spock, Move::Rock] {
+            *counts.entry(m).or_default() += 1;
+        }
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&Move::Rock], 3);
+        assert_eq!(counts[&Move::Paper], 1);
+        assert_eq!(counts.get(&Move::Scissors), None)
+    }
+
+        /** ==== Beats ==== **/
+
+    #[test]
+    fn test_beats_rock_rock(){
+        assert!(!Move::Rock.beats(&Move::Rock))
+    }
+
+    #[test]
+    fn test_beats_rock_paper(){
+        assert!(!Move::Rock.beats(&Move::Paper))
+    }
+
+    #[test]
+    fn test_beats_rock_scissors(){
+        assert!(Move::Rock.beats(&Move::Scissors))
+    }
+
+    #[test]
+    fn test_beats_paper_rock(){
+        assert!(Move::Paper.beats(&Move::Rock))
+    }
+
+    #[test]
+    fn test_beats_paper_paper(){
+        assert!(!Move::Paper.beats(&Move::Paper))
+    }
+
+    #[test]
+    fn test_beats_paper_scissors(){
+        assert!(!Move::Paper.beats(&Move::Scissors))
+    }
+
+    #[test]
+    fn test_beats_scissors_rock(){
+        assert!(!Move::Scissors.beats(&Move::Rock))
+    }
+
+    #[test]
+    fn test_beats_scissors_paper(){
+        assert!(Move::Scissors.beats(&Move::Paper))
+    }
+
+    #[test]
+    fn test_beats_scissors_scissors(){
+        assert!(!Move::Scissors.beats(&Move::Scissors))
+    }
+
+    #[test]
+    fn test_beats_has_exactly_one_winner(){
+        let moves = Variant::LizardSpock.moves();
+        for a in moves {
+            for b in moves {
+                if a != b {
+                    assert!(a.beats(b) != b.beats(a))
+                }
+            }
+        }
+    }
+
+        /** ==== Calculate winner==== **/
+
+    #[test]
+    fn test_calculate_winner_userwin(){
+        assert_eq!(calculate_winner(Move::Rock, Move::Scissors), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_calculate_winner_tie(){
+        assert_eq!(calculate_winner(Move::Rock, Move::Rock), GameResult::Tie)
+    }
+
+    #[test]
+    fn test_calculate_winner_opponentwin(){
+        assert_eq!(calculate_winner(Move::Rock, Move::Paper), GameResult::OpponentWin)
+    }
+
+        /** ==== FromStr ==== **/
+
+    #[test]
+    fn test_fromstr_rock(){
+        assert_eq!(Move::from_str("rock"), Ok(Move::Rock))
+    }
+
+    #[test]
+    fn test_fromstr_paper(){
+        assert_eq!(Move::from_str("paper"), Ok(Move::Paper))
+    }
+
+    #[test]
+    fn test_fromstr_scissors(){
+        assert_eq!(Move::from_str("scissors"), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_fromstr_alias_rock(){
+        assert_eq!(Move::from_str("r"), Ok(Move::Rock))
+    }
+
+    #[test]
+    fn test_fromstr_alias_paper(){
+        assert_eq!(Move::from_str("p"), Ok(Move::Paper))
+    }
+
+    #[test]
+    fn test_fromstr_alias_scissors(){
+        assert_eq!(Move::from_str("s"), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_fromstr_alias_case_insensitive(){
+        assert_eq!(Move::from_str("R"), Ok(Move::Rock));
+        assert_eq!(Move::from_str("ROCK"), Ok(Move::Rock))
+    }
+
+    #[test]
+    fn test_fromstr_accepts_every_alias(){
+        for m in Move::all() {
+            for alias in m.aliases() {
+                assert_eq!(&Move::from_str(alias).unwrap(), m)
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_moves(){
+        let all : Vec<&Move> = Move::all().collect();
+        assert_eq!(all, [&Move::Rock, &Move::Paper, &Move::Scissors, &Move::Lizard, &Move::Spock])
+    }
+
+    #[test]
+    fn test_fromstr_numeric_rock(){
+        assert_eq!(Move::from_str("0"), Ok(Move::Rock))
+    }
+
+    #[test]
+    fn test_fromstr_numeric_paper(){
+        assert_eq!(Move::from_str("1"), Ok(Move::Paper))
+    }
+
+    #[test]
+    fn test_fromstr_numeric_scissors(){
+        assert_eq!(Move::from_str("2"), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_fromstr_numeric_out_of_range(){
+        assert_eq!(Move::from_str("9"), Err(ParseMoveError::new(String::from("9"))));
+        assert_eq!(Move::from_str("300"), Err(ParseMoveError::new(String::from("300"))))
+    }
+
+    #[test]
+    fn test_fromstr_numeric_is_one_digit(){
+        assert_eq!(Move::from_str("+2"), Err(ParseMoveError::new(String::from("+2"))));
+        assert_eq!(Move::from_str("02"), Err(ParseMoveError::new(String::from("02"))))
+    }
+
+    #[test]
+    fn test_try_from_u8(){
+        assert_eq!(Move::try_from(3), Ok(Move::Lizard));
+        assert_eq!(Move::try_from(4), Ok(Move::Spock));
+        assert_eq!(Move::try_from(5), Err(ParseMoveError::new(String::from("5"))))
+    }
+
+    #[test]
+    fn test_u8_from_move(){
+        assert_eq!(u8::from(Move::Rock), 0);
+        assert_eq!(u8::from(Move::Paper), 1);
+        assert_eq!(u8::from(Move::Scissors), 2)
+    }
+
+    #[test]
+    fn test_u8_round_trip(){
+        for m in Move::all() {
+            assert_eq!(Move::try_from(u8::from(*m)), Ok(*m))
+        }
+        for n in 0..5u8 {
+            assert_eq!(u8::from(Move::try_from(n).unwrap()), n)
+        }
+    }
+
+    #[test]
+    fn test_numeric_spock_is_outside_classic(){
+        assert!(Variant::Classic.parse_move("4").is_err())
+    }
+
+    #[test]
+    fn test_fromstr_error(){
+        assert_eq!(Move::from_str("notreal"), Err(ParseMoveError::new(String::from("notreal"))))
+    }
+
+    #[test]
+    fn test_parse_move_error_display(){
+        let err = Move::from_str("notreal").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid move: \"notreal\"")
+    }
+
+    /** ==== Names ==== **/
+
+    #[test]
+    fn test_move_names(){
+        let names : Vec<&str> = Move::all().map(|m| m.name()).collect();
+        assert_eq!(names, ["rock", "paper", "scissors", "lizard", "spock"])
+    }
+
+    #[test]
+    fn test_move_name_is_first_alias(){
+        for m in Move::all() {
+            assert_eq!(m.aliases()[0], m.name())
+        }
+    }
+
+    #[test]
+    fn test_game_result_names(){
+        assert_eq!(GameResult::UserWin.name(), "UserWin");
+        assert_eq!(GameResult::OpponentWin.name(), "OpponentWin");
+        assert_eq!(GameResult::Tie.name(), "Tie")
+    }
+
+    #[test]
+    fn test_json_uses_names(){
+        let json = serde_json::to_string(&Round::new(Move::Spock, Move::Lizard)).unwrap();
+        assert_eq!(json, r#"{"opponent_move":"lizard","user_move":"spock","result":"OpponentWin"}"#)
+    }
+
+    /** ==== Display ==== **/
+
+    #[test]
+    fn test_display_rock(){
+        assert_eq!(format!("{}", Move::Rock), "Rock")
+    }
+
+    #[test]
+    fn test_display_all_moves(){
+        let names : Vec<String> = Variant::LizardSpock.moves().iter().map(|m| m.to_string()).collect();
+        assert_eq!(names, ["Rock", "Paper", "Scissors", "Lizard", "Spock"])
+    }
+
+    #[test]
+    fn test_display_lowercase(){
+        assert_eq!(format!("{:#}", Move::Scissors), "scissors")
+    }
+
+    #[test]
+    fn test_display_round_trips_through_fromstr(){
+        for m in Variant::LizardSpock.moves() {
+            assert_eq!(&Move::from_str(&m.to_string()).unwrap(), m)
+        }
+    }
+
+    #[test]
+    fn test_emoji(){
+        assert_eq!(Move::Rock.emoji(), "✊");
+        assert_eq!(Move::Paper.emoji(), "✋");
+        assert_eq!(Move::Scissors.emoji(), "✌️");
+        assert_eq!(Move::Lizard.emoji(), "🦎");
+        assert_eq!(Move::Spock.emoji(), "🖖")
+    }
+
+    #[test]
+    fn test_from_str_emoji(){
+        assert_eq!(Move::from_str("✊"), Ok(Move::Rock));
+        assert_eq!(Move::from_str("✋"), Ok(Move::Paper));
+        assert_eq!(Move::from_str("🦎"), Ok(Move::Lizard));
+        assert_eq!(Move::from_str("🖖"), Ok(Move::Spock))
+    }
+
+    #[test]
+    fn test_from_str_scissors_emoji_with_and_without_selector(){
+        assert_eq!(Move::from_str("\u{270c}\u{fe0f}"), Ok(Move::Scissors));
+        assert_eq!(Move::from_str("\u{270c}"), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_from_str_emoji_round_trips(){
+        for m in Move::all() {
+            assert_eq!(&Move::from_str(m.emoji()).unwrap(), m)
+        }
+    }
+
+    #[test]
+    fn test_from_str_trims_whitespace(){
+        assert_eq!(Move::from_str(" rock "), Ok(Move::Rock));
+        assert_eq!(Move::from_str("\tpaper\n"), Ok(Move::Paper))
+    }
+
+    #[test]
+    fn test_from_str_strips_quotes(){
+        assert_eq!(Move::from_str("'rock'"), Ok(Move::Rock));
+        assert_eq!(Move::from_str("\"scissors\""), Ok(Move::Scissors));
+        assert_eq!(Move::from_str(" \"2\" "), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_empty_input_is_its_own_error(){
+        for input in ["", "   ", "''", "\"\""] {
+            let err = Move::from_str(input).unwrap_err();
+            assert!(err.is_empty(), "{:?}", input);
+            assert_eq!(err.to_string(), "No move provided — expected rock, paper, or scissors")
+        }
+    }
+
+    #[test]
+    fn test_unknown_word_is_not_empty(){
+        let err = Move::from_str("rok").unwrap_err();
+        assert!(!err.is_empty());
+        assert_eq!(err.to_string(), "Invalid move: \"rok\"")
+    }
+
+    #[test]
+    fn test_from_str_still_rejects_invalid(){
+        assert_eq!(Move::from_str(" 'rok' "), Err(ParseMoveError::new(String::from(" 'rok' "))));
+        assert!(Move::from_str("'rock\"").is_err());
+        assert!(Move::from_str("''rock''").is_err());
+        assert!(Move::from_str("\"").is_err())
+    }
+
+    #[test]
+    fn test_try_from_char(){
+        assert_eq!(Move::try_from('r'), Ok(Move::Rock));
+        assert_eq!(Move::try_from('P'), Ok(Move::Paper));
+        assert_eq!(Move::try_from('s'), Ok(Move::Scissors));
+        assert_eq!(Move::try_from('S'), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_try_from_emoji_char(){
+        assert_eq!(Move::try_from('✊'), Ok(Move::Rock));
+        assert_eq!(Move::try_from('✋'), Ok(Move::Paper));
+        assert_eq!(Move::try_from('\u{270c}'), Ok(Move::Scissors));
+        assert_eq!(Move::try_from('🦎'), Ok(Move::Lizard));
+        assert_eq!(Move::try_from('🖖'), Ok(Move::Spock))
+    }
+
+    #[test]
+    fn test_try_from_invalid_char(){
+        assert_eq!(Move::try_from('x'), Err(ParseMoveError::new(String::from("x"))));
+        assert_eq!(Move::try_from('\u{fe0f}'), Err(ParseMoveError::new(String::from("\u{fe0f}"))))
+    }
+
+    #[test]
+    fn test_from_str_plurals(){
+        assert_eq!(Move::from_str("rocks"), Ok(Move::Rock));
+        assert_eq!(Move::from_str("Papers"), Ok(Move::Paper));
+        assert_eq!(Move::from_str("lizards"), Ok(Move::Lizard))
+    }
+
+    #[test]
+    fn test_from_str_singular_scissor(){
+        assert_eq!(Move::from_str("scissor"), Ok(Move::Scissors));
+        assert_eq!(Move::from_str("SCISSORS"), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_from_str_plurals_keep_invalid_words_invalid(){
+        assert!(Move::from_str("rockss").is_err());
+        assert!(Move::from_str("scissorss").is_err());
+        assert!(Move::from_str("ps").is_err());
+        assert!(Move::from_str("stones").is_err());
+        assert!(Move::from_str("roc").is_err())
+    }
+
+    #[test]
+    fn test_from_str_rejects_bare_selector(){
+        assert!(Move::from_str("\u{fe0f}").is_err())
+    }
+
+    /** ==== Seeded Random Completeness ==== **/
+
+    #[test]
+    fn test_distribution_rock(){
+        let mut seed = GameRng::seed_from_u64(2);
+        let seeded_move : Move = seed.gen();
+        assert_eq!(seeded_move, Move::Rock)
+    }
+    
+    #[test]
+    fn test_distribution_paper(){
+        let mut seed = GameRng::seed_from_u64(1);
+        let seeded_move : Move = seed.gen();
+        assert_eq!(seeded_move, Move::Paper)
+    }
+
+    #[test]
+    fn test_distribution_scissors(){
+        let mut seed = GameRng::seed_from_u64(0);
+        let seeded_move : Move = seed.gen();
+        assert_eq!(seeded_move, Move::Scissors)
+    }
+
+    #[test]
+    fn test_lizard_spock_abbreviations(){
+        let variant = Variant::LizardSpock;
+        assert_eq!(variant.parse_move("r"), Ok(Move::Rock));
+        assert_eq!(variant.parse_move("p"), Ok(Move::Paper));
+        assert_eq!(variant.parse_move("l"), Ok(Move::Lizard));
+        assert_eq!(variant.parse_move("sp"), Ok(Move::Spock));
+        assert_eq!(variant.parse_move("K"), Ok(Move::Spock))
+    }
+
+    #[test]
+    fn test_lizard_spock_bare_s_is_ambiguous(){
+        let err = Variant::LizardSpock.parse_move(" S ").unwrap_err();
+        assert_eq!(err.candidates, [Move::Scissors, Move::Spock]);
+        assert_eq!(err.to_string(), "Ambiguous move: \" S \" could be scissors or spock; spell it out")
+    }
+
+    #[test]
+    fn test_variant_aliases_skip_ambiguous_ones(){
+        assert_eq!(Variant::Classic.aliases(&Move::Scissors), ["scissors", "s"]);
+        assert_eq!(Variant::LizardSpock.aliases(&Move::Scissors), ["scissors"])
+    }
+
+    #[test]
+    fn test_classic_s_is_still_scissors(){
+        assert_eq!(Variant::Classic.parse_move("s"), Ok(Move::Scissors));
+        assert_eq!(Variant::LizardSpock.parse_move("scissors"), Ok(Move::Scissors))
+    }
+
+    #[test]
+    fn test_seeded_moves_are_pinned(){
+        // Recorded from ChaCha8: if this changes, every seeded game does too.
+        let mut rng = GameRng::seed_from_u64(2);
+        let moves : Vec<Move> = (0..6).map(|_| rng.gen()).collect();
+        assert_eq!(moves, [Move::Rock, Move::Scissors, Move::Paper, Move::Paper, Move::Rock, Move::Scissors])
+    }
+
+    #[test]
+    fn test_parse_move_rejects_moves_outside_variant(){
+        assert_eq!(Variant::Classic.parse_move("spock"), Err(ParseMoveError::new(String::from("spock"))));
+        assert_eq!(Variant::LizardSpock.parse_move("spock"), Ok(Move::Spock))
+    }
+
+    #[test]
+    fn test_tie_policy_tie(){
+        assert_eq!(TiePolicy::Tie.resolve(calculate_winner(Move::Rock, Move::Rock)), Some(GameResult::Tie))
+    }
+
+    #[test]
+    fn test_tie_policy_user(){
+        assert_eq!(TiePolicy::User.resolve(calculate_winner(Move::Rock, Move::Rock)), Some(GameResult::UserWin))
+    }
+
+    #[test]
+    fn test_tie_policy_opponent(){
+        assert_eq!(TiePolicy::Opponent.resolve(calculate_winner(Move::Rock, Move::Rock)), Some(GameResult::OpponentWin))
+    }
+
+    #[test]
+    fn test_tie_policy_replay(){
+        assert_eq!(TiePolicy::Replay.resolve(calculate_winner(Move::Rock, Move::Rock)), None)
+    }
+
+    #[test]
+    fn test_tie_policy_leaves_decisive_results_alone(){
+        for policy in [TiePolicy::Tie, TiePolicy::User, TiePolicy::Opponent, TiePolicy::Replay] {
+            assert_eq!(policy.resolve(calculate_winner(Move::Rock, Move::Paper)), Some(GameResult::OpponentWin));
+            assert_eq!(policy.resolve(calculate_winner(Move::Rock, Move::Scissors)), Some(GameResult::UserWin))
+        }
+    }
+
+    #[test]
+    fn test_tie_policy_name_matches_the_command_line(){
+        for policy in TiePolicy::value_variants() {
+            assert_eq!(TiePolicy::from_str(policy.name(), false), Ok(*policy))
+        }
+    }
+
+    #[test]
+    fn test_tally_overall(){
+        assert_eq!(Tally { user_wins: 3, opponent_wins: 1, ties: 1 }.overall(), GameResult::UserWin);
+        assert_eq!(Tally { user_wins: 1, opponent_wins: 1, ties: 4 }.overall(), GameResult::Tie);
+        assert_eq!(Tally { user_wins: 0, opponent_wins: 2, ties: 0 }.overall(), GameResult::OpponentWin)
+    }
+
+    #[test]
+    fn test_tally_win_rate(){
+        assert_eq!(Tally { user_wins: 1, opponent_wins: 2, ties: 1 }.win_rate(), 0.25);
+        assert_eq!(Tally::default().win_rate(), 0.0)
+    }
+
+    #[test]
+    fn test_seed_from_phrase_is_fnv1a(){
+        // Published FNV-1a test vectors; changing them would change everyone's games.
+        assert_eq!(seed_from_phrase(""), 0xcbf29ce484222325);
+        assert_eq!(seed_from_phrase("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(seed_from_phrase("foobar"), 0x85944171f73967e8)
+    }
+
+    #[test]
+    fn test_seed_from_phrase_tells_phrases_apart(){
+        assert_ne!(seed_from_phrase("lucky cat"), seed_from_phrase("lucky dog"))
+    }
+
+    #[test]
+    fn test_calculate_series(){
+        let user = [Move::Rock, Move::Paper, Move::Scissors, Move::Rock];
+        let opponent = [Move::Scissors, Move::Scissors, Move::Scissors, Move::Paper];
+        let series = calculate_series(&user, &opponent).unwrap();
+        let results: Vec<&GameResult> = series.rounds.iter().map(|r| &r.result).collect();
+        assert_eq!(results, [&GameResult::UserWin, &GameResult::OpponentWin, &GameResult::Tie, &GameResult::OpponentWin]);
+        assert_eq!(series.tally, Tally { user_wins: 1, opponent_wins: 2, ties: 1 })
+    }
+
+    #[test]
+    fn test_calculate_series_empty(){
+        assert_eq!(calculate_series(&[], &[]), Ok(SeriesResult::default()))
+    }
+
+    #[test]
+    fn test_calculate_series_length_mismatch(){
+        let err = calculate_series(&[Move::Rock, Move::Paper], &[Move::Rock]).unwrap_err();
+        assert_eq!(err, SeriesLengthError { user_moves: 2, opponent_moves: 1 });
+        assert_eq!(err.to_string(), "Mismatched series: 2 user moves but 1 opponent moves")
+    }
+
+    #[test]
+    fn test_tally_display(){
+        let tally = Tally { user_wins: 3, opponent_wins: 1, ties: 1 };
+        assert_eq!(tally.to_string(), "You 3 - Opponent 1 (1 tie)")
+    }
+
+    #[test]
+    fn test_tally_display_plural_ties(){
+        let tally = Tally { user_wins: 0, opponent_wins: 2, ties: 2 };
+        assert_eq!(tally.to_string(), "You 0 - Opponent 2 (2 ties)")
+    }
+
+    #[test]
+    fn test_round_keeps_both_moves(){
+        let round = Round::new(Move::Scissors, Move::Paper);
+        assert_eq!(round.user_move, Move::Scissors);
+        assert_eq!(round.opponent_move, Move::Paper);
+        assert_eq!(round.result, GameResult::UserWin);
+        let round = Round::new(Move::Spock, Move::Lizard);
+        assert_eq!((round.user_move, round.opponent_move, round.result), (Move::Spock, Move::Lizard, GameResult::OpponentWin))
+    }
+
+    #[test]
+    fn test_round_verdict_matches_calculate_winner(){
+        for user in Move::all() {
+            for opponent in Move::all() {
+                assert_eq!(Round::new(*user, *opponent).result, calculate_winner(*user, *opponent))
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_explains_itself(){
+        assert_eq!(Round::new(Move::Lizard, Move::Lizard).explain(), "You both played Lizard, so it's a tie.")
+    }
+
+    #[test]
+    fn test_round_seed(){
+        assert_eq!(round_seed(7, 0), 7);
+        assert_eq!(round_seed(7, 2), 5)
+    }
+
+    /** ==== Explain ==== **/
+
+    #[test]
+    fn test_explain_win(){
+        assert_eq!(explain(Move::Paper, Move::Rock), "Paper covers Rock, so you win.");
+        assert_eq!(explain(Move::Lizard, Move::Spock), "Lizard poisons Spock, so you win.")
+    }
+
+    #[test]
+    fn test_explain_loss(){
+        assert_eq!(explain(Move::Scissors, Move::Spock), "Spock smashes Scissors, so you lose.")
+    }
+
+    #[test]
+    fn test_explain_tie(){
+        assert_eq!(explain(Move::Rock, Move::Rock), "You both played Rock, so it's a tie.")
+    }
+
+    #[test]
+    fn test_every_win_has_a_verb(){
+        for a in Move::all() {
+            for b in Move::all() {
+                assert_eq!(a.verb(b).is_some(), calculate_winner(*a, *b) == GameResult::UserWin)
+            }
+        }
+    }
+
+    /** ==== Counters ==== **/
+
+    #[test]
+    fn test_counter_classic(){
+        assert_eq!(Move::Rock.counter(), Move::Paper);
+        assert_eq!(Move::Paper.counter(), Move::Scissors);
+        assert_eq!(Move::Scissors.counter(), Move::Rock)
+    }
+
+    #[test]
+    fn test_counter_lizard_spock_moves(){
+        assert_eq!(Move::Lizard.counter(), Move::Rock);
+        assert_eq!(Move::Spock.counter(), Move::Paper)
+    }
+
+    #[test]
+    fn test_counters_classic_are_unique(){
+        for m in Variant::Classic.moves() {
+            assert_eq!(m.counters(Variant::Classic), [m.counter()])
+        }
+    }
+
+    #[test]
+    fn test_counters_lizard_spock(){
+        assert_eq!(Move::Rock.counters(Variant::LizardSpock), [Move::Paper, Move::Spock]);
+        assert_eq!(Move::Paper.counters(Variant::LizardSpock), [Move::Scissors, Move::Lizard]);
+        assert_eq!(Move::Scissors.counters(Variant::LizardSpock), [Move::Rock, Move::Spock]);
+        assert_eq!(Move::Lizard.counters(Variant::LizardSpock), [Move::Rock, Move::Scissors]);
+        assert_eq!(Move::Spock.counters(Variant::LizardSpock), [Move::Paper, Move::Lizard])
+    }
+
+    #[test]
+    fn test_counters_beat_the_move(){
+        for m in Move::all() {
+            assert!(m.counters(Variant::LizardSpock).iter().all(|c| c.beats(m)))
+        }
+    }
+
+    #[test]
+    fn test_next_in_cycle(){
+        assert_eq!(Move::Rock.next_in_cycle(), Some(Move::Paper));
+        assert_eq!(Move::Paper.next_in_cycle(), Some(Move::Scissors));
+        assert_eq!(Move::Scissors.next_in_cycle(), Some(Move::Rock));
+        assert_eq!(Move::Lizard.next_in_cycle(), None);
+        assert_eq!(Move::Spock.next_in_cycle(), None)
+    }
+
+    #[test]
+    fn test_cycle_repeats_every_three_moves(){
+        let walked : Vec<Move> = Move::Scissors.cycle().take(7).collect();
+        assert_eq!(walked, [Move::Scissors, Move::Rock, Move::Paper, Move::Scissors, Move::Rock, Move::Paper, Move::Scissors]);
+        assert!(walked.windows(2).all(|pair| pair[1].beats(&pair[0])));
+        assert_eq!(Move::Lizard.cycle().take(3).collect::<Vec<Move>>(), [Move::Lizard])
+    }
+
+    #[test]
+    fn test_classic_cycle_is_next_in_cycle(){
+        let cycle : Vec<Move> = Move::Rock.cycle().take(3).collect();
+        assert_eq!(Variant::Classic.cycles(), [cycle.as_slice()])
+    }
+
+    #[test]
+    fn test_cycles_cover_each_matchup_once(){
+        for variant in [Variant::Classic, Variant::LizardSpock] {
+            let mut matchups = Vec::new();
+            for cycle in variant.cycles() {
+                assert_eq!(cycle.len(), variant.moves().len());
+                for (i, m) in cycle.iter().enumerate() {
+                    let next = cycle[(i + 1) % cycle.len()];
+                    assert!(next.beats(m), "{} doesn't beat {}", next, m);
+                    matchups.push((next, *m));
+                }
+            }
+            let all = variant.moves().iter().flat_map(|m| m.counters(variant).into_iter().map(move |c| (c, *m))).count();
+            matchups.sort_by_key(|(a, b)| (*a as u8, *b as u8));
+            matchups.dedup();
+            assert_eq!(matchups.len(), all)
+        }
+    }
+
+    /** ==== Lizard Spock ==== **/
+
+    #[test]
+    fn test_lizard_spock_rock_crushes_scissors(){
+        assert_eq!(calculate_winner(Move::Rock, Move::Scissors), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_rock_crushes_lizard(){
+        assert_eq!(calculate_winner(Move::Rock, Move::Lizard), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_paper_covers_rock(){
+        assert_eq!(calculate_winner(Move::Paper, Move::Rock), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_paper_disproves_spock(){
+        assert_eq!(calculate_winner(Move::Paper, Move::Spock), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_scissors_cuts_paper(){
+        assert_eq!(calculate_winner(Move::Scissors, Move::Paper), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_scissors_decapitates_lizard(){
+        assert_eq!(calculate_winner(Move::Scissors, Move::Lizard), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_lizard_eats_paper(){
+        assert_eq!(calculate_winner(Move::Lizard, Move::Paper), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_lizard_poisons_spock(){
+        assert_eq!(calculate_winner(Move::Lizard, Move::Spock), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_spock_smashes_scissors(){
+        assert_eq!(calculate_winner(Move::Spock, Move::Scissors), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_spock_vaporizes_rock(){
+        assert_eq!(calculate_winner(Move::Spock, Move::Rock), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_lizard_spock_losing_side(){
+        assert_eq!(calculate_winner(Move::Lizard, Move::Rock), GameResult::OpponentWin)
+    }
+
+    #[test]
+    fn test_fromstr_lizard_spock(){
+        assert_eq!(Move::from_str("Lizard"), Ok(Move::Lizard));
+        assert_eq!(Move::from_str("SPOCK"), Ok(Move::Spock))
+    }
+
+    #[test]
+    fn test_classic_variant_matches_standard(){
+        for seed in 0..50 {
+            let standard : Move = GameRng::seed_from_u64(seed).gen();
+            let classic : Move = GameRng::seed_from_u64(seed).sample(Variant::Classic);
+            assert_eq!(standard, classic)
+        }
+    }
+
+    #[test]
+    fn test_lizard_spock_variant_samples_all_moves(){
+        let mut rng = GameRng::seed_from_u64(0);
+        let samples : Vec<Move> = (0..100).map(|_| rng.sample(Variant::LizardSpock)).collect();
+        for m in Variant::LizardSpock.moves() {
+            assert!(samples.contains(m))
+        }
+    }
+
+    /** ==== Streaks ==== **/
+
+    #[test]
+    fn test_streaks_of_known_pattern(){
+        // win, win, tie, loss, loss, loss, tie, tie, win
+        let user = [Move::Rock; 9];
+        let opponent = [Move::Scissors, Move::Scissors, Move::Rock, Move::Paper, Move::Paper, Move::Paper, Move::Rock, Move::Rock, Move::Scissors];
+        let series = calculate_series(&user, &opponent).unwrap();
+        assert_eq!(Streaks::of(&series.rounds), Streaks { wins: 2, losses: 3, ties: 2 })
+    }
+
+    #[test]
+    fn test_streaks_of_no_rounds(){
+        assert_eq!(Streaks::of(&[]), Streaks::default())
+    }
+
+    #[test]
+    fn test_streaks_missing_result_is_zero(){
+        let series = calculate_series(&[Move::Paper, Move::Paper], &[Move::Rock, Move::Rock]).unwrap();
+        assert_eq!(Streaks::of(&series.rounds), Streaks { wins: 2, losses: 0, ties: 0 })
+    }
+}
\ No newline at end of file
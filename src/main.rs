@@ -1,241 +1,4245 @@
-use std::{cmp::Ordering, str::FromStr, process};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::{self, BufRead, IsTerminal, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex, OnceLock, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::{ArgGroup, ArgMatches, CommandFactory, ErrorKind, FromArgMatches, Parser, ValueEnum, ValueSource};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use owo_colors::OwoColorize;
+use rand::{Rng, RngCore, SeedableRng};
+use rock_paper_scissors::{
+    commit::{self, Reveal},
+    config::{self, Config},
+    lang::Lang,
+    opponent::{best_reply, expected_rates, win_probabilities, ConstantOpponent, Difficulty, HouseEdgeOpponent, MoveDistribution, MoveWeights, Opponent, OutcomeRates, RandomOpponent, ScriptedOpponent, Strategy},
+    rules::{DynamicMove, Ruleset},
+    stats::{self, Stats, StatsWriter},
+    transcript::{self, Row, Transcript},
+    tournament::{self, Standing},
+    calculate_winner, round_seed, seed_from_phrase, GameResult, GameRng, Move, ParseMoveError, Round, Streaks, Tally, TiePolicy, Variant,
+};
+
+const EXIT_INVALID_INPUT: i32 = 3;
+/** Used instead of `EXIT_INVALID_INPUT` when the move is missing altogether. */
+const EXIT_NO_MOVE: i32 = 4;
+/** How many ties --no-ties re-rolls before deciding the round can't be settled. */
+const MAX_REROLLS: u32 = 1000;
+/**
+ * The longest a --best-of or --first-to series can run, in case every round
+ * is a tie.
+ */
+const MAX_SERIES_ROUNDS: u32 = 1000;
+/** The exit code for a second Ctrl-C, as if the signal had killed us. */
+const EXIT_INTERRUPTED: i32 = 130;
+/** How often a waiting interactive session checks for Ctrl-C. */
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+/** Set by the first Ctrl-C of an interactive session. */
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/**
+ * Where everything that would go to stdout is written: stdout itself, or the
+ * --output-file. main picks it once, before printing anything.
+ */
+static OUTPUT: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
+/** The hand drawn for each move by --ascii, in the order of `Move::all`. */
+const HAND_ART: [[&str; 6]; 5] = [
+    [
+        r"    _______",
+        r"---'   ____)",
+        r"      (_____)",
+        r"      (_____)",
+        r"      (____)",
+        r"---.__(___)",
+    ],
+    [
+        r"    _______",
+        r"---'   ____)____",
+        r"          ______)",
+        r"          _______)",
+        r"         _______)",
+        r"---.__________)",
+    ],
+    [
+        r"    _______",
+        r"---'   ____)____",
+        r"          ______)",
+        r"       __________)",
+        r"      (____)",
+        r"---.__(___)",
+    ],
+    [
+        r"    _______",
+        r"---'   ____)____",
+        r"          ______)",
+        r"         _______)",
+        r"      (_____)",
+        r"---.__(_____)>",
+    ],
+    [
+        r"    _______",
+        r"---'   ____)__ __",
+        r"          __) (__)",
+        r"          __) (__)",
+        r"      (____)",
+        r"---.__(___)",
+    ],
+];
+
+/**
+ * A handle on `OUTPUT` for code that takes a writer. Until main has picked
+ * one, as in the unit tests, writes go through `print!`, which the test
+ * harness captures.
+ */
+struct Output;
+
+impl Write for Output {
+    fn write(&mut self, buf : &[u8]) -> io::Result<usize> {
+        match OUTPUT.get() {
+            Some(out) => out.lock().unwrap_or_else(PoisonError::into_inner).write(buf),
+            None => {
+                print!("{}", String::from_utf8_lossy(buf));
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match OUTPUT.get() {
+            Some(out) => out.lock().unwrap_or_else(PoisonError::into_inner).flush(),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+/** `print!` to `OUTPUT`. */
+macro_rules! out {
+    ($($arg:tt)*) => {
+        write!(Output, $($arg)*).expect("failed printing to the output")
+    };
+}
+
+/** `println!` to `OUTPUT`. */
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        writeln!(Output, $($arg)*).expect("failed printing to the output")
+    };
+}
+/**
+ * Options that contradict each other, by argument id. Giving both of a pair
+ * is an error rather than letting one of them quietly win.
+ */
+const CONFLICTS: &[(&str, &str)] = &[
+    ("seed", "seed-phrase"),
+    ("seed", "opponent"),
+    ("opponent", "ai"),
+    ("opponent", "sequence"),
+    ("quiet", "verbose"),
+    ("two-player", "ai"),
+    ("two-player", "sequence"),
+    ("interactive", "batch"),
+    ("best-of", "rounds"),
+    ("first-to", "best-of"),
+    ("first-to", "rounds"),
+    ("rules", "variant"),
+    ("nash-check", "ai"),
+    ("nash-check", "sequence"),
+    ("pattern", "user-weights"),
+    ("auto", "ai"),
+    ("auto", "sequence"),
+    ("house-edge", "rounds"),
+    ("house-edge", "best-of"),
+    ("house-edge", "first-to"),
+    ("house-edge", "interactive"),
+    ("house-edge", "batch"),
+    ("house-edge", "two-player"),
+    ("house-edge", "simulate"),
+    ("house-edge", "tournament"),
+    ("house-edge", "auto"),
+    ("house-edge", "rules"),
+    ("house-edge", "ai"),
+    ("house-edge", "sequence"),
+    ("house-edge", "opponent"),
+    ("replay", "log"),
+    ("commit", "reveal"),
+    ("ascii", "banner"),
+    ("ascii", "emoji"),
+    ("opponent-file", "opponent"),
+    ("opponent-file", "sequence"),
+    ("opponent-file", "ai"),
+    ("opponent-file", "auto"),
+    ("opponent-file", "house-edge"),
+    ("input-socket", "interactive"),
+    ("input-socket", "batch"),
+    ("seed-range", "seed"),
+    ("seed-range", "seed-phrase"),
+    ("hotkeys", "timeout"),
+    ("no-ties", "tie-policy"),
+    ("compare", "ai"),
+    ("compare", "nash-check"),
+    ("challenge", "opponent"),
+    ("challenge", "sequence"),
+    ("challenge", "opponent-file"),
+    ("difficulty", "ai"),
+    ("difficulty", "opponent"),
+    ("difficulty", "opponent-file"),
+    ("difficulty", "challenge"),
+    ("difficulty", "auto"),
+    ("difficulty", "compare"),
+    ("tie-policy", "tournament"),
+    ("tie-policy", "simulate"),
+    ("tie-policy", "two-player"),
+    ("tie-policy", "auto"),
+    ("tie-policy", "seed-range"),
+    ("tie-policy", "reveal"),
+    ("rules", "interactive"),
+    ("rules", "batch"),
+    ("rules", "tournament"),
+    ("rules", "simulate"),
+    ("simulate", "tournament"),
+    ("simulate", "interactive"),
+    ("sequence", "ai"),
+    ("log", "rules"),
+    ("log", "tournament"),
+    ("log", "simulate"),
+    ("log", "two-player"),
+    ("difficulty", "tournament"),
+];
+
+/**
+ * A basic command line rock paper scissors game.
+ *
+ * The exit code encodes the outcome: 0 when you win, 1 on a tie, 2 when you
+ * lose, 3 when the move or another option is invalid and 4 when the move is
+ * empty. Multi-round and interactive games exit with the outcome of the
+ * whole series.
+ */
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+#[clap(group = ArgGroup::new("record").multiple(true).args(&["stats", "stats-file"]))]
+struct Cli {
+    /// Your move. Falls back to the RPS_MOVE environment variable.
+    /// --simulate without a move plays a random move for you too. It is
+    /// checked against --variant, --lang and --rules once they are read, and
+    /// a bad move exits with 3, or 4 if it is empty, rather than clap's 2.
+    #[clap(env = "RPS_MOVE", required_unless_present_any = ["interactive", "batch", "two-player", "list-moves", "simulate", "version-detail", "explain-rules", "auto", "replay", "reveal", "dry-run", "input-socket", "self-test"])]
+    pattern: Option<String>,
+    /// Seed for the opponent's moves, for reproducible games.
+    #[clap(long)]
+    seed: Option<u64>,
+    /// A phrase to derive the seed from instead, e.g. "lucky cat". The same
+    /// phrase gives the same game in every version.
+    #[clap(long, value_name = "PHRASE")]
+    seed_phrase: Option<String>,
+    /// Rule set to play with.
+    #[clap(long, value_enum, ignore_case = true, default_value_t = Variant::Classic)]
+    variant: Variant,
+    /// Strategy the computer opponent plays with.
+    #[clap(long, value_enum, ignore_case = true, default_value_t = Strategy::Random)]
+    ai: Strategy,
+    /// How hard the computer is to beat: easy plays at random, medium mixes
+    /// in countering your favourite move and hard learns your habits.
+    /// Replaces --ai.
+    #[clap(long, value_enum, ignore_case = true)]
+    difficulty: Option<Difficulty>,
+    /// Relative odds of the opponent picking each move, e.g. 3,1,1 makes
+    /// rock three times as likely. One weight per move of the variant.
+    #[clap(long, value_delimiter = ',', allow_hyphen_values = true)]
+    weights: Option<Vec<f64>>,
+    /// Print a commitment to your move instead of playing, for games where
+    /// neither side should see the other's move first. Reveal it with
+    /// --reveal once both players have committed.
+    #[clap(long)]
+    commit: bool,
+    /// Check both players' reveals against the commitments they published
+    /// earlier and score the game: player 1's commitment and reveal, then
+    /// player 2's, as printed by --commit.
+    #[clap(long, number_of_values = 4, value_names = &["COMMITMENT1", "REVEAL1", "COMMITMENT2", "REVEAL2"])]
+    reveal: Option<Vec<String>>,
+    /// Read a transcript saved with --log and narrate its rounds again,
+    /// without playing anything.
+    #[clap(long, value_name = "PATH")]
+    replay: Option<PathBuf>,
+    /// Let the computer play both sides for --rounds rounds, each with its
+    /// own strategy, e.g. frequency:random. --weights bias the second.
+    #[clap(long, value_name = "A:B")]
+    auto: Option<String>,
+    /// Number of rounds to play. The same move is thrown every round.
+    #[clap(long, default_value_t = 1)]
+    rounds: u32,
+    /// Play --rounds rounds against each of several computer opponents in turn
+    /// and rank them by how often you beat them.
+    #[clap(long)]
+    tournament: bool,
+    /// Keep reading moves from stdin until "quit" or end of input.
+    #[clap(long)]
+    interactive: bool,
+    /// In --interactive games, say after each lost or tied round which move
+    /// would have beaten the opponent's.
+    #[clap(long, requires = "interactive")]
+    practice: bool,
+    /// In --interactive games, forfeit the round to the opponent if no move
+    /// is entered within this many seconds. 0 waits forever.
+    #[clap(long, value_name = "SECS", default_value_t = 0, requires = "interactive")]
+    timeout: u64,
+    /// In --interactive games, end the session after this many rounds, as a
+    /// safeguard for unattended runs. 0 means no limit.
+    #[clap(long, value_name = "N", default_value_t = 0, requires = "interactive")]
+    max_rounds: u32,
+    /// In --interactive games, play each move with a single keypress (r, p,
+    /// s, ...) instead of typing a line. Falls back to lines when stdin isn't
+    /// a terminal.
+    #[clap(long, requires = "interactive")]
+    hotkeys: bool,
+    /// In --interactive games, make the opponent's moves depend on the seed
+    /// alone, however you play, so a --seed can be shared as a challenge.
+    #[clap(long, requires = "interactive")]
+    challenge: bool,
+    /// Print the seed used so the game can be replayed with --seed.
+    #[clap(long)]
+    show_seed: bool,
+    /// Print the moves of the variant, with their short aliases, and exit.
+    #[clap(long)]
+    list_moves: bool,
+    /// Print the version along with the variants, AI strategies, languages
+    /// and move aliases this build supports, and exit.
+    #[clap(long)]
+    version_detail: bool,
+    /// Print which move beats which, for the variant or the --rules file,
+    /// and exit.
+    #[clap(long)]
+    explain_rules: bool,
+    /// Play every move against every move of the built-in variants and check
+    /// that the rules hold together. Exits with 3 if they don't. A --rules
+    /// file is already checked as it loads, so for one this only reports
+    /// what loading it found wrong.
+    #[clap(long, hide = true)]
+    self_test: bool,
+    /// Check the move, weights, opponent and rules file, then exit without
+    /// playing. Exits with 3 if anything is invalid.
+    #[clap(long)]
+    dry_run: bool,
+    /// Play one game per line of stdin, without prompting. Invalid lines are
+    /// reported on stderr and skipped.
+    #[clap(long)]
+    batch: bool,
+    /// Listen on a Unix domain socket and play one game per line each
+    /// client sends, writing the results back. Runs until killed.
+    #[clap(long, value_name = "PATH")]
+    input_socket: Option<PathBuf>,
+    /// Play against another person at the same keyboard instead of the
+    /// computer. Both moves are read from stdin.
+    #[clap(long)]
+    two_player: bool,
+    /// How results are printed.
+    #[clap(long, value_enum, ignore_case = true, default_value_t = Format::Text)]
+    format: Format,
+    /// With --format csv, print a header line before the first row.
+    #[clap(long)]
+    csv_header: bool,
+    /// Keep a lifetime record of games against the computer and print it
+    /// after each game.
+    #[clap(long)]
+    stats: bool,
+    /// Where to keep the lifetime record. Implies --stats.
+    #[clap(long, value_name = "PATH")]
+    stats_file: Option<PathBuf>,
+    /// In --interactive and --batch sessions, also save the lifetime record
+    /// every N rounds, so a crash doesn't lose the whole session. 0 only
+    /// saves at the end. Needs --stats or --stats-file.
+    #[clap(long, value_name = "N", default_value_t = 0, requires = "record")]
+    flush_every: u32,
+    /// Show moves as hand emoji instead of words.
+    #[clap(long)]
+    emoji: bool,
+    /// Show each result in a box with both moves' emoji and a colored
+    /// WIN, LOSE or TIE. Plain text when colors are off.
+    #[clap(long)]
+    banner: bool,
+    /// Draw both hands in ASCII art above the result, for terminals
+    /// without emoji.
+    #[clap(long)]
+    ascii: bool,
+    /// Print only the result of each round: UserWin, Tie or OpponentWin.
+    #[clap(long, short)]
+    quiet: bool,
+    /// Never color the output. Also honoured through the NO_COLOR variable.
+    #[clap(long)]
+    no_color: bool,
+    /// Color the output even when it isn't a terminal, set by `color = true`
+    /// in the config file.
+    #[clap(skip)]
+    force_color: bool,
+    /// Play with the moves and rules from a TOML file instead of a built-in
+    /// variant. The opponent picks uniformly at random.
+    #[clap(long, value_name = "PATH")]
+    rules: Option<PathBuf>,
+    /// Before each round, print how likely each move is to beat the
+    /// opponent's next move, given its strategy and the rounds so far.
+    #[clap(long)]
+    predict: bool,
+    /// In a single game, print the odds of your move winning, tying and
+    /// losing against the opponent's distribution (see --weights) first.
+    #[clap(long)]
+    probabilities: bool,
+    /// Count down "Rock... Paper... Scissors... Shoot!" before each reveal.
+    #[clap(long)]
+    dramatic: bool,
+    /// Pause between the words of the --dramatic countdown. There is never a
+    /// pause when stdout isn't a terminal.
+    #[clap(long, value_name = "MS", default_value_t = 400)]
+    countdown_ms: u64,
+    /// Replay ties: the opponent draws again until someone wins.
+    #[clap(long)]
+    no_ties: bool,
+    /// What a tie counts for: a tie, a win for you or the opponent, or a
+    /// replay as with --no-ties.
+    #[clap(long, value_enum, value_name = "POLICY", default_value_t = TiePolicy::Tie)]
+    tie_policy: TiePolicy,
+    /// Before each round, suggest the move most likely to beat the
+    /// opponent's strategy.
+    #[clap(long)]
+    hint: bool,
+    /// Play N games silently against the --ai opponent and print how often
+    /// each side won.
+    #[clap(long, value_name = "N")]
+    simulate: Option<u32>,
+    /// Play your move once against an opponent seeded with each seed of a
+    /// range, e.g. 1..100 or 1..=100, and count the outcomes. --verbose
+    /// lists every seed.
+    #[clap(long, value_name = "RANGE", value_parser = parse_seed_range)]
+    seed_range: Option<RangeInclusive<u64>>,
+    /// Pause between --simulate games and print each one with the running
+    /// totals, so it can be watched. There is never a pause when stdout
+    /// isn't a terminal.
+    #[clap(long, value_name = "MS", default_value_t = 0, requires = "simulate")]
+    delay_ms: u64,
+    /// Relative odds of your own moves when --simulate plays them for you,
+    /// like --weights for the opponent.
+    #[clap(long, value_delimiter = ',', allow_hyphen_values = true)]
+    user_weights: Option<Vec<f64>>,
+    /// With --simulate, compare the simulated win, loss and tie rates of the
+    /// two mixed strategies against the exact ones, with your expected value.
+    #[clap(long, requires = "simulate")]
+    nash_check: bool,
+    /// With --simulate, pit two computer strategies against each other, e.g.
+    /// frequency:random, and say which wins more often and by how much.
+    /// --weights bias the second.
+    #[clap(long, value_name = "A:B", requires = "simulate")]
+    compare: Option<String>,
+    /// Also print your own move and why the round was won, lost or tied.
+    #[clap(long, short)]
+    verbose: bool,
+    /// Append every round to a CSV file: your move, the opponent's move, the
+    /// result and the seed.
+    #[clap(long, value_name = "PATH")]
+    log: Option<PathBuf>,
+    /// Write everything that would go to stdout to this file instead,
+    /// creating or truncating it. Errors still go to stderr.
+    #[clap(long, value_name = "PATH")]
+    output_file: Option<PathBuf>,
+    /// Language for move names and results. Moves can also be typed in it.
+    #[clap(long, value_enum, ignore_case = true, default_value_t = Lang::En)]
+    lang: Lang,
+    /// Play a best-of-N series: stop as soon as one side has won a majority
+    /// of N rounds. Ties don't count.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    best_of: Option<u32>,
+    /// Play until one side has won N rounds, however long that takes. Ties
+    /// don't count.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    first_to: Option<u32>,
+    /// If a --rounds or --best-of game ends level on wins, keep playing
+    /// single rounds until one side wins one.
+    #[clap(long)]
+    sudden_death: bool,
+    /// Make the opponent play this sequence of moves over and over, one
+    /// letter per move, e.g. rpsr. Overrides --ai.
+    #[clap(long, value_name = "MOVES")]
+    sequence: Option<String>,
+    /// Make the opponent throw this move, for scripts that need a known
+    /// outcome without hunting for a seed.
+    #[clap(long, value_name = "MOVE")]
+    opponent: Option<String>,
+    /// Make the opponent play the moves in this file, one per line, in
+    /// order. Running out before the game is over is an error.
+    #[clap(long, value_name = "PATH")]
+    opponent_file: Option<PathBuf>,
+    /// Print the opponent's move on stderr and only the result on stdout, so
+    /// piping the output keeps just the outcome.
+    #[clap(long)]
+    split_output: bool,
+    /// In a single game, let the opponent peek at your move and play its
+    /// counter with probability P, e.g. 0.2. Otherwise it plays at random.
+    #[clap(long, value_name = "P", value_parser = parse_probability)]
+    house_edge: Option<f64>,
+    /// After a multi-round game, list every round again followed by the
+    /// margin between you and the opponent.
+    #[clap(long)]
+    scoreboard: bool,
+    /// After a multi-round game, count how often the opponent threw each
+    /// move, to check that it plays fair.
+    #[clap(long)]
+    count: bool
+}
+
+/**
+ * Output format for results. Json prints one object per round, Csv one row
+ * per round, Text the usual sentences.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Csv
+}
+
+/**
+ * How rounds are presented, resolved once from the flags and the terminal.
+ */
+struct Style {
+    format: Format,
+    color: bool,
+    emoji: bool,
+    quiet: bool,
+    verbose: bool,
+    split: bool,
+    banner: bool,
+    ascii: bool,
+    lang: Lang
+}
+
+impl Style {
+    fn from_args(args : &Cli) -> Style {
+        // Colors are only for people: skip them when piped or when asked not to.
+        let color = !args.no_color && env::var_os("NO_COLOR").is_none() && (args.force_color || output_is_terminal(args));
+        Style { format: args.format, color, emoji: args.emoji, quiet: args.quiet, verbose: args.verbose, split: args.split_output, banner: args.banner, ascii: args.ascii, lang: args.lang }
+    }
+
+    fn move_name(&self, m : &Move) -> String {
+        if self.emoji {
+            m.emoji().to_string()
+        } else {
+            self.lang.move_name(m).to_string()
+        }
+    }
+}
+
+fn main() {
+    // Initalize cli parser.
+    let matches = Cli::command().try_get_matches().unwrap_or_else(|err| exit_on_usage_error(err));
+    let mut args = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Some(path) = config::find() {
+        let (config, warnings) = Config::load(&path);
+        for warning in warnings {
+            eprintln!("Warning: {}: {}", path.display(), warning);
+        }
+        apply_config(&mut args, &matches, &config);
+    }
+    let conflicts = conflicts(&matches);
+    if !conflicts.is_empty() {
+        eprintln!("{}", format_conflicts(&conflicts));
+        process::exit(EXIT_INVALID_INPUT);
+    }
+    if let Err(err) = check_tie_replays(&args) {
+        eprintln!("{}", err);
+        process::exit(EXIT_INVALID_INPUT);
+    }
+    let output : Box<dyn Write + Send> = match &args.output_file {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(err) => {
+                eprintln!("Could not open output file {}: {}", path.display(), err);
+                process::exit(EXIT_INVALID_INPUT);
+            }
+        },
+        None => Box::new(io::stdout()),
+    };
+    // Nothing has printed yet, so nothing else can have picked the output.
+    let _ = OUTPUT.set(Mutex::new(output));
+
+    if args.dry_run {
+        match validate(&args) {
+            Ok(()) => outln!("Dry run: everything is valid"),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(EXIT_INVALID_INPUT)
+            }
+        }
+        return;
+    }
+
+    if args.list_moves {
+        outln!("{}", list_moves(args.variant));
+        return;
+    }
+
+    if args.version_detail {
+        outln!("{}", version_detail(args.variant));
+        return;
+    }
+
+    if args.explain_rules {
+        let rules = match &args.rules {
+            Some(path) => load_rules(path),
+            None => Ruleset::from_variant(args.variant),
+        };
+        outln!("{}", rules.describe());
+        return;
+    }
+
+    if args.self_test {
+        let problems = self_test(&args);
+        if !problems.is_empty() {
+            for problem in &problems {
+                eprintln!("{}", problem);
+            }
+            process::exit(EXIT_INVALID_INPUT);
+        }
+        outln!("Self-test passed: every matchup is consistent");
+        return;
+    }
+
+    if args.commit {
+        let reveal = Reveal::new(parse_move(&args), commit::new_salt(&mut rand::thread_rng()));
+        outln!("{}", format_commitment(&reveal, args.format));
+        return;
+    }
+
+    if let Some(reveals) = &args.reveal {
+        match check_reveals(reveals, args.variant) {
+            Ok(round) => {
+                outln!("{}", format_players(&round, &Style::from_args(&args)));
+                process::exit(exit_code(&round.result))
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(EXIT_INVALID_INPUT)
+            }
+        }
+    }
+
+    if args.two_player {
+        match play_two_player(io::stdin().lock(), &args) {
+            Some(round) => process::exit(exit_code(&round.result)),
+            None => process::exit(EXIT_INVALID_INPUT),
+        }
+    }
+
+    // Settle the seed once so every round, and the transcript, agree on it.
+    let seed_given = requested_seed(&args).is_some();
+    args.seed = Some(resolve_seed(requested_seed(&args)));
+    let mut transcript = open_transcript(&args);
+    let mut stats = stats_path(&args).map(|path| StatsWriter::new(path, args.flush_every));
+    if args.format == Format::Csv && args.csv_header {
+        outln!("{}", transcript::ROUND_HEADER);
+    }
+
+    if let Some(path) = &args.input_socket {
+        serve(&args, path, &mut transcript);
+    }
+
+    // A sudden death settles a level game without counting toward its score.
+    let mut sudden_death = None;
+    let tally = if let Some(path) = &args.replay {
+        let tally = replay(&args, path);
+        if !args.quiet {
+            outln!("{}", format_tally(&tally, args.format));
+        }
+        tally
+    } else if let Some(path) = &args.rules {
+        let tally = play_custom(&args, &load_rules(path));
+        if args.rounds > 1 && !args.quiet {
+            outln!("{}", format_tally(&tally, args.format));
+        }
+        tally
+    } else if let (Some(games), Some(spec)) = (args.simulate, &args.compare) {
+        let sides = matchup(spec);
+        let tally = compare(&args, sides, games);
+        if args.quiet && args.format == Format::Text {
+            outln!("{}", tally.overall().name());
+        } else {
+            outln!("{}", format_comparison(sides, &tally, args.format));
+        }
+        tally
+    } else if let Some(games) = args.simulate {
+        let tally = simulate(&args, games);
+        if args.nash_check {
+            outln!("{}", format_nash_check(&tally, &expected_simulation(&args), args.format));
+        } else {
+            outln!("{}", format_simulation(&tally, args.format));
+        }
+        tally
+    } else if let Some(seeds) = &args.seed_range {
+        let rounds = sweep_seeds(&args, seeds);
+        let style = Style::from_args(&args);
+        if args.verbose && style.format != Format::Json {
+            for (seed, round) in seeds.clone().zip(&rounds) {
+                outln!("{}", format_seed_outcome(seed, round, &style));
+            }
+        }
+        let mut tally = Tally::default();
+        for round in &rounds {
+            tally.record(&round.result);
+        }
+        outln!("{}", format_seed_sweep(seeds, &tally, args.format));
+        tally
+    } else if let Some(spec) = &args.auto {
+        let sides = matchup(spec);
+        let tally = play_auto(&args, sides);
+        if !args.quiet {
+            outln!("{}", format_auto_summary(sides, &tally, args.format));
+        }
+        tally
+    } else if args.tournament {
+        play_tournament(&args)
+    } else if args.interactive {
+        let mut opponent : Box<dyn Opponent + '_> = if args.challenge {
+            Box::new(ChallengeOpponent::new(&args, challenge_seed(&args, seed_given)))
+        } else {
+            opponent(&args, move_weights(&args), seeded_rng(&args))
+        };
+        watch_for_interrupts();
+        let tally = if args.hotkeys && io::stdin().is_terminal() {
+            play_session(MoveSource::Hotkeys, &args, opponent.as_mut(), &mut transcript, &mut stats, &INTERRUPTED)
+        } else {
+            play_interactive(io::BufReader::new(io::stdin()), &args, opponent.as_mut(), &mut transcript, &mut stats, &INTERRUPTED)
+        };
+        if !args.quiet {
+            outln!("{}", format_tally(&tally, args.format));
+        }
+        tally
+    } else if args.batch {
+        let mut opponent = opponent(&args, move_weights(&args), seeded_rng(&args));
+        let tally = play_batch(io::stdin().lock(), &args, opponent.as_mut(), &mut transcript, &mut stats);
+        if !args.quiet {
+            outln!("{}", format_tally(&tally, args.format));
+        }
+        tally
+    } else if let Some(games) = args.best_of {
+        let series = play_best_of(&args, games, &mut transcript);
+        if !args.quiet {
+            outln!("{}", format_series(&series.tally, args.format));
+        }
+        sudden_death = series.sudden_death;
+        series.tally
+    } else if let Some(wins) = args.first_to {
+        let series = play_first_to(&args, wins, &mut transcript);
+        if !args.quiet {
+            outln!("{}", format_series(&series.tally, args.format));
+        }
+        sudden_death = series.sudden_death;
+        series.tally
+    } else if args.rounds > 1 {
+        let series = play_rounds(&args, &mut transcript);
+        if !args.quiet {
+            outln!("{}", format_tally(&series.tally, args.format));
+        }
+        sudden_death = series.sudden_death;
+        series.tally
+    } else {
+        let mut tally = Tally::default();
+        tally.record(&play_game(&args, &mut transcript));
+        tally
+    };
+
+    if let Some(writer) = &mut stats {
+        if !args.interactive && !args.batch {
+            writer.record(fixed_move(&args), &tally);
+        }
+        let stats = update_stats(writer);
+        match args.format {
+            Format::Text | Format::Csv => outln!("{}", stats),
+            Format::Json => outln!("{}", serde_json::to_string(&stats).expect("stats always serialize")),
+        }
+    }
+
+    process::exit(exit_code(&sudden_death.unwrap_or_else(|| tally.overall())))
+}
+
+/**
+ * Reports a misspelt option value, like `--ai frequnecy`, and exits with
+ * `EXIT_INVALID_INPUT` like any other invalid option. Every other error,
+ * and --help and --version, exits the way clap does.
+ */
+fn exit_on_usage_error(err : clap::Error) -> ! {
+    if err.kind() != ErrorKind::InvalidValue {
+        err.exit();
+    }
+    let _ = err.print();
+    process::exit(EXIT_INVALID_INPUT)
+}
+
+/**
+ * The pairs from `CONFLICTS` that were both given. Defaults don't count, so
+ * only what the user actually asked for is reported.
+ */
+fn conflicts(matches : &ArgMatches) -> Vec<(&'static str, &'static str)> {
+    CONFLICTS.iter().copied().filter(|(a, b)| was_given(matches, a) && was_given(matches, b)).collect()
+}
+
+/**
+ * Whether the user set the argument, on the command line or through its
+ * environment variable, rather than leaving it at its default.
+ */
+fn was_given(matches : &ArgMatches, id : &str) -> bool {
+    matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable))
+}
+
+/**
+ * Fills in the settings from the config file that the user didn't give, so
+ * flags and environment variables always win over the file. A setting also
+ * gives way to a flag it is in `CONFLICTS` with, e.g. the file's `ai` to
+ * --opponent, so the file never makes a command line contradict itself.
+ * `color = true` colors even output that isn't a terminal, though NO_COLOR
+ * and --no-color are still honoured.
+ */
+fn apply_config(args : &mut Cli, matches : &ArgMatches, config : &Config) {
+    let applies = |id : &str| !was_given(matches, id) && !CONFLICTS.iter().any(|(a, b)| (*a == id && was_given(matches, b)) || (*b == id && was_given(matches, a)));
+    if let (Some(variant), true) = (config.variant, applies("variant")) {
+        args.variant = variant;
+    }
+    if let (Some(ai), true) = (config.ai, applies("ai")) {
+        args.ai = ai;
+    }
+    if let (Some(lang), true) = (config.lang, applies("lang")) {
+        args.lang = lang;
+    }
+    match config.color {
+        Some(true) => args.force_color = true,
+        Some(false) => args.no_color = true,
+        None => {}
+    }
+}
+
+/**
+ * E.g. "Conflicting options: --quiet and --verbose; --best-of and --rounds".
+ */
+fn format_conflicts(conflicts : &[(&str, &str)]) -> String {
+    // The move is the only positional argument, so it has no flag to name.
+    let name = |id : &str| if id == "pattern" { String::from("a move") } else { format!("--{}", id) };
+    let pairs : Vec<String> = conflicts.iter().map(|(a, b)| format!("{} and {}", name(a), name(b))).collect();
+    format!("Conflicting options: {}", pairs.join("; "))
+}
+
+/**
+ * The moves of the variant, e.g. "rock (r), paper (p), scissors (s)".
+ */
+fn list_moves(variant : Variant) -> String {
+    let names : Vec<String> = variant
+        .moves()
+        .iter()
+        .map(|m| match variant.aliases(m).as_slice() {
+            [name] => name.to_string(),
+            [name, short @ ..] => format!("{} ({})", name, short.join(", ")),
+            [] => unreachable!("every move has a name"),
+        })
+        .collect();
+    names.join(", ")
+}
+
+/**
+ * The version and everything this build can play, listed from the same enums
+ * the flags are parsed with so it never goes out of date.
+ */
+fn version_detail(variant : Variant) -> String {
+    [
+        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        format!("Variant: {}", value_name(&variant)),
+        format!("Variants: {}", value_names::<Variant>()),
+        format!("AI strategies: {}", value_names::<Strategy>()),
+        format!("Languages: {}", value_names::<Lang>()),
+        format!("Formats: {}", value_names::<Format>()),
+        format!("Moves: {}", list_moves(variant)),
+    ].join("\n")
+}
+
+/**
+ * The name a value is given on the command line, e.g. "lizard-spock".
+ */
+fn value_name<T : ValueEnum>(value : &T) -> String {
+    value.to_possible_value().expect("no values are hidden").get_name().to_string()
+}
+
+/**
+ * Every name a flag accepts, comma separated.
+ */
+fn value_names<T : ValueEnum>() -> String {
+    T::value_variants().iter().map(value_name).collect::<Vec<_>>().join(", ")
+}
+
+/**
+ * Opens the --log transcript, if any. Failing to open it is an error since
+ * the user asked for a record of the game.
+ */
+fn open_transcript(args : &Cli) -> Option<Transcript<File>> {
+    let path = args.log.as_ref()?;
+    match Transcript::open(path, args.tie_policy) {
+        Ok(x) => Some(x),
+        Err(err) => {
+            eprintln!("Could not open transcript {}: {}", path.display(), err);
+            process::exit(EXIT_INVALID_INPUT)
+        }
+    }
+}
+
+/**
+ * Whether the output is a terminal, which it never is with --output-file.
+ */
+fn output_is_terminal(args : &Cli) -> bool {
+    args.output_file.is_none() && io::stdout().is_terminal()
+}
+
+/**
+ * Narrates the rounds of the transcript at `path` as if they were being
+ * played. Malformed rows are reported on stderr with their line number and
+ * skipped; a missing file or a wrong header stops the replay.
+ */
+fn replay(args : &Cli, path : &Path) -> Tally {
+    let text = fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("Could not read transcript {}: {}", path.display(), err);
+        process::exit(EXIT_INVALID_INPUT)
+    });
+    let mut lines = text.lines();
+    if let Err(err) = transcript::check_header(lines.next().unwrap_or_default()) {
+        eprintln!("{}: {}", path.display(), err);
+        process::exit(EXIT_INVALID_INPUT)
+    }
+    let style = Style::from_args(args);
+    let mut tally = Tally::default();
+    for (number, line) in lines.enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match transcript::parse_row(line, args.tie_policy) {
+            Ok(Row::Played(round)) => {
+                print_round(&round, &style);
+                tally.record(&round.result);
+            }
+            Ok(Row::Forfeit) => {
+                outln!("{}", format_forfeit(&style));
+                tally.record(&GameResult::OpponentWin);
+            }
+            // The header is line 1.
+            Err(err) => eprintln!("Line {}: {}", number + 2, err),
+        }
+    }
+    tally
+}
+
+/**
+ * Adds the round to the transcript. A failed write is reported but doesn't
+ * stop the game.
+ */
+fn log_round(transcript : &mut Option<Transcript<File>>, round : &Round, seed : Option<u64>) {
+    if let Some(transcript) = transcript {
+        if let Err(err) = transcript.record(round, seed) {
+            eprintln!("Could not write to transcript: {}", err);
+        }
+    }
+}
+
+/**
+ * Adds a round forfeited to --timeout to the transcript, reporting a failed
+ * write like `log_round`.
+ */
+fn log_forfeit(transcript : &mut Option<Transcript<File>>, seed : Option<u64>) {
+    if let Some(transcript) = transcript {
+        if let Err(err) = transcript.record_forfeit(seed) {
+            eprintln!("Could not write to transcript: {}", err);
+        }
+    }
+}
+
+/**
+ * The stats file to update, if the user asked for stats.
+ */
+fn stats_path(args : &Cli) -> Option<PathBuf> {
+    if let Some(path) = &args.stats_file {
+        return Some(path.clone());
+    }
+    if !args.stats {
+        return None;
+    }
+    let path = stats::default_path();
+    if path.is_none() {
+        eprintln!("Could not find a config directory to keep stats in");
+    }
+    path
+}
+
+/**
+ * Adds the game to the record. Failing to save is reported but doesn't
+ * affect the game that was just played.
+ */
+fn update_stats(writer : &mut StatsWriter) -> Stats {
+    if let Err(err) = writer.save() {
+        eprintln!("Could not save stats to {}: {}", writer.path().display(), err);
+    }
+    writer.totals()
+}
+
+/**
+ * The move every round was played with, for the per-move stats. Games with
+ * no move of the user's own, or a custom rule set, have none; --interactive
+ * and --batch record each round's move as it is played instead.
+ */
+fn fixed_move(args : &Cli) -> Option<Move> {
+    let varies = args.interactive
+        || args.batch
+        || args.two_player
+        || args.replay.is_some()
+        || args.rules.is_some()
+        || args.auto.is_some()
+        || args.compare.is_some();
+    if varies {
+        return None;
+    }
+    args.pattern.as_deref().and_then(|pattern| args.lang.parse_move(args.variant, pattern).ok())
+}
+
+/**
+ * Records a round of an --interactive or --batch session in the stats, and
+ * saves them every --flush-every rounds. As with `update_stats`, a failed
+ * save doesn't stop the game.
+ */
+fn record_stats(stats : &mut Option<StatsWriter>, chosen : Option<Move>, result : &GameResult) {
+    if let Some(writer) = stats {
+        let mut round = Tally::default();
+        round.record(result);
+        writer.record(chosen, &round);
+        if let Err(err) = writer.update() {
+            eprintln!("Could not save stats to {}: {}", writer.path().display(), err);
+        }
+    }
+}
+
+fn exit_code(result: &GameResult) -> i32 {
+    match result {
+        GameResult::UserWin => 0,
+        GameResult::Tie => 1,
+        GameResult::OpponentWin => 2,
+    }
+}
+
+fn colored_result_message(result: &GameResult, lang: Lang) -> String {
+    let message = lang.result_message(result);
+    match result {
+            GameResult::UserWin => message.green().to_string(),
+            GameResult::Tie => message.yellow().to_string(),
+            GameResult::OpponentWin => message.red().to_string(),
+    }
+}
+
+/**
+ * The text for a round's result, given the name of the opponent's move.
+ */
+fn format_result(opponent_move : &str, result : &GameResult, style : &Style) -> String {
+    if style.quiet {
+        return result.name().to_string();
+    }
+    // Let the user know what the move the opponent generated.
+    format!("{}: {}. {}", style.lang.strings().opponent_move, opponent_move, result_message(result, style))
+}
+
+/**
+ * "You win!" and friends, colored if the style allows.
+ */
+fn result_message(result : &GameResult, style : &Style) -> String {
+    if style.color {
+        colored_result_message(result, style.lang)
+    } else {
+        style.lang.result_message(result).to_string()
+    }
+}
+
+/**
+ * Prints a round. With --split-output a text round is split in two: the
+ * moves go to stderr and only the result to stdout, so a pipe gets nothing
+ * but the outcome.
+ */
+fn print_round(round : &Round, style : &Style) {
+    if style.split && style.format == Format::Text && !style.quiet {
+        let (preamble, result) = split_round(round, style);
+        eprintln!("{}", preamble);
+        outln!("{}", result);
+    } else {
+        outln!("{}", format_round(round, style));
+    }
+}
+
+/**
+ * A text round as its informational part (the moves, and with --verbose
+ * the explanation) and its result.
+ */
+fn split_round(round : &Round, style : &Style) -> (String, String) {
+    let opponent = format!("{}: {}.", style.lang.strings().opponent_move, style.move_name(&round.opponent_move));
+    let preamble = if style.verbose {
+        format!("Your move: {}. {}\n{}", style.move_name(&round.user_move), opponent, round.explain())
+    } else {
+        opponent
+    };
+    (preamble, result_message(&round.result, style))
+}
+
+fn format_round(round: &Round, style: &Style) -> String {
+    match style.format {
+        Format::Text if style.banner && !style.quiet => format_banner(round, style),
+        Format::Text if style.ascii && !style.quiet => format_ascii_art(round, style),
+        Format::Text if style.verbose && !style.quiet => format!(
+            "Your move: {}. {}\n{}",
+            style.move_name(&round.user_move),
+            format_result(&style.move_name(&round.opponent_move), &round.result, style),
+            round.explain()
+        ),
+        Format::Text => format_result(&style.move_name(&round.opponent_move), &round.result, style),
+        Format::Json => serde_json::to_string(round).expect("rounds always serialize"),
+        Format::Csv => transcript::round_row(round),
+    }
+}
+
+/**
+ * A round in a box: both moves, then WIN, LOSE or TIE. With colors on the
+ * moves get their emoji and the box its banner color; without them it's
+ * plain ASCII, so a log or a pipe gets nothing but text.
+ */
+fn format_banner(round : &Round, style : &Style) -> String {
+    let hand = |m : &Move| {
+        let name = style.lang.move_name(m);
+        if style.color { format!("{} {}", m.emoji(), name) } else { name.to_string() }
+    };
+    let word = match round.result {
+        GameResult::UserWin => "WIN",
+        GameResult::Tie => "TIE",
+        GameResult::OpponentWin => "LOSE",
+    };
+    let lines = [format!("You:      {}", hand(&round.user_move)), format!("Opponent: {}", hand(&round.opponent_move))];
+    let width = lines.iter().map(|line| display_width(line)).max().unwrap_or(0).max(word.len());
+    let pad = |text : &str, shown : usize| format!("{}{}", text, " ".repeat(width - shown));
+    let painted = match round.result {
+        _ if !style.color => word.to_string(),
+        GameResult::UserWin => word.bold().green().to_string(),
+        GameResult::Tie => word.bold().yellow().to_string(),
+        GameResult::OpponentWin => word.bold().red().to_string(),
+    };
+    let indent = (width - word.len()) / 2;
+    let banner = pad(&format!("{}{}", " ".repeat(indent), painted), indent + word.len());
+    let (corner, edge, side) = if style.color { (["╭", "╮", "╰", "╯"], "─", "│") } else { (["+", "+", "+", "+"], "-", "|") };
+    let rule = edge.repeat(width + 2);
+    let mut boxed = vec![format!("{}{}{}", corner[0], rule, corner[1])];
+    boxed.extend(lines.iter().map(|line| format!("{} {} {}", side, pad(line, display_width(line)), side)));
+    boxed.push(format!("{} {} {}", side, banner, side));
+    boxed.push(format!("{}{}{}", corner[2], rule, corner[3]));
+    boxed.join("\n")
+}
+
+/**
+ * A round drawn with `HAND_ART`: your hand, the opponent's, then the result.
+ * Nothing but ASCII apart from the move names.
+ */
+fn format_ascii_art(round : &Round, style : &Style) -> String {
+    let art = |m : &Move| HAND_ART[usize::from(u8::from(*m))].join("\n");
+    format!(
+        "Your move: {}\n{}\n{}: {}\n{}\n{}",
+        style.lang.move_name(&round.user_move),
+        art(&round.user_move),
+        style.lang.strings().opponent_move,
+        style.lang.move_name(&round.opponent_move),
+        art(&round.opponent_move),
+        result_message(&round.result, style)
+    )
+}
+
+/**
+ * How many terminal columns `text` takes: emoji are two wide and the
+ * variation selector after ✌ takes none.
+ */
+fn display_width(text : &str) -> usize {
+    text.chars()
+        .map(|c| match c {
+            '\u{FE0F}' => 0,
+            '\u{2600}'..='\u{27BF}' | '\u{1F000}'.. => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+fn format_prediction(chances : &[(Move, f64)], format : Format) -> String {
+    match format {
+        Format::Text | Format::Csv => {
+            let chances : Vec<String> = chances.iter().map(|(m, p)| format!("{} {:.0}%", m, p * 100.0)).collect();
+            format!("Chance of winning: {}", chances.join(", "))
+        }
+        Format::Json => {
+            let chances : serde_json::Map<String, serde_json::Value> =
+                chances.iter().map(|(m, p)| (m.name().to_string(), serde_json::Value::from(*p))).collect();
+            serde_json::json!({ "win_probabilities": chances }).to_string()
+        }
+    }
+}
+
+/**
+ * The odds of a move against the opponent's next draw, e.g. "Win 33.3% /
+ * Tie 33.3% / Lose 33.3%". `None` when the opponent's move isn't random.
+ */
+fn format_probabilities(rates : Option<OutcomeRates>, format : Format) -> String {
+    match (format, rates) {
+        (Format::Text | Format::Csv, Some(rates)) => {
+            format!("Win {:.1}% / Tie {:.1}% / Lose {:.1}%", rates.win * 100.0, rates.tie * 100.0, rates.loss * 100.0)
+        }
+        (Format::Text | Format::Csv, None) => "The opponent's move isn't random, so there are no odds to show".to_string(),
+        (Format::Json, rates) => serde_json::json!({ "probabilities": rates }).to_string(),
+    }
+}
+
+fn format_hint(hint : Option<Move>, format : Format) -> String {
+    match (format, hint) {
+        (Format::Text | Format::Csv, Some(m)) => format!("Hint: play {}", m),
+        (Format::Text | Format::Csv, None) => "Hint: every move is equally good".to_string(),
+        (Format::Json, hint) => serde_json::json!({ "hint": hint }).to_string(),
+    }
+}
+
+fn outcome_word(result : &GameResult) -> &'static str {
+    match result {
+        GameResult::UserWin => "win",
+        GameResult::Tie => "tie",
+        GameResult::OpponentWin => "loss",
+    }
+}
+
+/**
+ * One line per round, e.g. "Round 1: you Rock vs Paper — loss", then the
+ * margin between the two sides.
+ */
+fn format_scoreboard(rounds : &[Round], style : &Style) -> String {
+    let mut tally = Tally::default();
+    for round in rounds {
+        tally.record(&round.result);
+    }
+    let margin = i64::from(tally.user_wins) - i64::from(tally.opponent_wins);
+    match style.format {
+        Format::Text | Format::Csv => {
+            let mut lines : Vec<String> = rounds
+                .iter()
+                .enumerate()
+                .map(|(i, round)| {
+                    format!("Round {}: you {} vs {} — {}", i + 1, style.move_name(&round.user_move), style.move_name(&round.opponent_move), outcome_word(&round.result))
+                })
+                .collect();
+            lines.push(match margin {
+                0 => "Margin: level".to_string(),
+                m if m > 0 => format!("Margin: you by {}", m),
+                m => format!("Margin: the opponent by {}", -m),
+            });
+            lines.join("\n")
+        }
+        Format::Json => serde_json::json!({ "rounds": rounds, "margin": margin }).to_string(),
+    }
+}
+
+/**
+ * How many times the opponent threw each move of the variant, e.g.
+ * "Opponent: Rock×4 Paper×2 Scissors×4".
+ */
+fn format_move_counts(rounds : &[Round], variant : Variant, style : &Style) -> String {
+    let mut counts : HashMap<Move, u32> = HashMap::new();
+    for round in rounds {
+        *counts.entry(round.opponent_move).or_insert(0) += 1;
+    }
+    let count = |m : &Move| counts.get(m).copied().unwrap_or(0);
+    match style.format {
+        Format::Text | Format::Csv => {
+            let parts : Vec<String> = variant.moves().iter().map(|m| format!("{}×{}", style.move_name(m), count(m))).collect();
+            format!("Opponent: {}", parts.join(" "))
+        }
+        Format::Json => {
+            let counts : serde_json::Map<String, serde_json::Value> = variant.moves().iter().map(|m| (m.name().to_string(), count(m).into())).collect();
+            serde_json::json!({ "opponent_moves": counts }).to_string()
+        }
+    }
+}
+
+/**
+ * The longest runs of each result, e.g. "Longest streaks: 3 wins, 1 loss,
+ * 0 ties".
+ */
+fn format_streaks(streaks : &Streaks, format : Format) -> String {
+    let count = |n : u32, one : &str, many : &str| format!("{} {}", n, if n == 1 { one } else { many });
+    match format {
+        Format::Text | Format::Csv => format!(
+            "Longest streaks: {}, {}, {}",
+            count(streaks.wins, "win", "wins"),
+            count(streaks.losses, "loss", "losses"),
+            count(streaks.ties, "tie", "ties")
+        ),
+        Format::Json => serde_json::json!({ "longest_streaks": streaks }).to_string(),
+    }
+}
+
+fn format_series(tally : &Tally, format : Format) -> String {
+    let score = |ours : u32, theirs : u32| format!("{}-{}", ours, theirs);
+    match (format, tally.overall()) {
+        (Format::Text | Format::Csv, GameResult::UserWin) => format!("You clinched the series {}", score(tally.user_wins, tally.opponent_wins)),
+        (Format::Text | Format::Csv, GameResult::OpponentWin) => format!("The opponent clinched the series {}", score(tally.opponent_wins, tally.user_wins)),
+        (Format::Text | Format::Csv, GameResult::Tie) => format!("Nobody clinched the series: {}", tally),
+        (Format::Json, _) => format_tally(tally, format),
+    }
+}
+
+fn format_tally(tally: &Tally, format: Format) -> String {
+    match format {
+        Format::Text | Format::Csv => format!("Final: {}", tally),
+        Format::Json => serde_json::to_string(tally).expect("tallies always serialize"),
+    }
+}
+
+fn play_game(args : &Cli, transcript : &mut Option<Transcript<File>>) -> GameResult{
+    let our_move = parse_move(args);
+    let mut opponent = match args.house_edge {
+        Some(edge) => Box::new(HouseEdgeOpponent::new(our_move, edge, move_weights(args), seeded_rng(args))),
+        None => opponent(args, move_weights(args), seeded_rng(args)),
+    };
+    if args.probabilities {
+        let rates = opponent.move_distribution(&[]).map(|distribution| expected_rates(&[(our_move, 1.0)], &distribution));
+        outln!("{}", format_probabilities(rates, args.format));
+    }
+    let round = play_round(args, opponent.as_mut(), our_move, &[]);
+    print_round(&round, &Style::from_args(args));
+    log_round(transcript, &round, args.seed);
+    round.result
+}
+
+/**
+ * Plays `args.rounds` rounds, reusing the user's move for every round. Each
+ * round gets an opponent with its own RNG derived from the base seed, so any
+ * single round can be replayed on its own with `--seed`. The opponents only
+ * remember past rounds through `history`, so this doesn't weaken them.
+ */
+fn play_rounds(args : &Cli, transcript : &mut Option<Transcript<File>>) -> Series {
+    play_series(args, transcript, args.rounds, |_| false)
+}
+
+/**
+ * Plays a best-of-`games` series: it ends as soon as either side has won a
+ * majority of `games`. Ties don't count toward that, so a series with ties
+ * runs longer, up to `MAX_SERIES_ROUNDS` rounds.
+ */
+fn play_best_of(args : &Cli, games : u32, transcript : &mut Option<Transcript<File>>) -> Series {
+    let needed = games / 2 + 1;
+    play_series(args, transcript, MAX_SERIES_ROUNDS, |tally| tally.user_wins >= needed || tally.opponent_wins >= needed)
+}
+
+/**
+ * Plays until either side has won `wins` rounds. Unlike --best-of there is
+ * no result short of that, so if neither side gets there within
+ * `MAX_SERIES_ROUNDS` rounds, e.g. against an opponent that only ever ties,
+ * the game stops with an error.
+ */
+fn play_first_to(args : &Cli, wins : u32, transcript : &mut Option<Transcript<File>>) -> Series {
+    let is_over = |tally : &Tally| tally.user_wins >= wins || tally.opponent_wins >= wins;
+    let series = play_series(args, transcript, MAX_SERIES_ROUNDS, is_over);
+    if !is_over(&series.tally) {
+        eprintln!("Neither side reached {} {} in {} rounds", wins, if wins == 1 { "win" } else { "wins" }, MAX_SERIES_ROUNDS);
+        process::exit(EXIT_INVALID_INPUT)
+    }
+    series
+}
+
+/**
+ * A finished --rounds, --best-of or --first-to game: the tally of its rounds
+ * and, if it ended level and went to --sudden-death, who won that. The
+ * sudden death is kept out of the tally, so the final score is the series'.
+ */
+#[derive(Debug, PartialEq)]
+struct Series {
+    tally: Tally,
+    sudden_death: Option<GameResult>
+}
+
+/**
+ * Plays up to `rounds` rounds, stopping early once `is_over` says the series
+ * is decided. Shared by --rounds, --best-of and --first-to.
+ */
+fn play_series(args : &Cli, transcript : &mut Option<Transcript<File>>, rounds : u32, is_over : impl Fn(&Tally) -> bool) -> Series {
+    let our_move = parse_move(args);
+    let weights = move_weights(args);
+    let style = Style::from_args(args);
+    let seed = base_seed(args);
+    // A --sequence or --opponent-file runs on across rounds, so it has to
+    // outlive them.
+    let mut scripted = sequence(args);
+    let mut play = |index : u32, history : &[Round]| {
+        let derived = round_seed(seed, index);
+        if args.show_seed {
+            eprintln!("(round {} seed: {})", index + 1, derived);
+        }
+        let mut seeded;
+        let opponent : &mut dyn Opponent = match &mut scripted {
+            Some(scripted) => scripted.as_mut(),
+            None => {
+                seeded = computer(args, weights.clone(), GameRng::seed_from_u64(derived));
+                seeded.as_mut()
+            }
+        };
+        let round = play_round(args, opponent, our_move, history);
+        print_round(&round, &style);
+        log_round(transcript, &round, Some(derived));
+        round
+    };
+    let mut tally = Tally::default();
+    let mut history = Vec::new();
+    for index in 0..rounds {
+        if is_over(&tally) {
+            break;
+        }
+        let round = play(index, &history);
+        tally.record(&round.result);
+        history.push(round);
+    }
+    let played = history.len();
+    let mut sudden_death = None;
+    if args.sudden_death && tally.user_wins == tally.opponent_wins {
+        if style.format == Format::Text && !style.quiet {
+            outln!("Level at {}-{}: sudden death!", tally.user_wins, tally.opponent_wins);
+        }
+        // Sudden death carries on the series' round numbering, and with it
+        // the seeds, so a seeded game still replays exactly.
+        for index in played as u32..played as u32 + MAX_SERIES_ROUNDS {
+            let round = play(index, &history);
+            let decided = round.result != GameResult::Tie;
+            history.push(round);
+            if decided {
+                break;
+            }
+        }
+        let last = history.last().expect("sudden death plays at least one round");
+        if style.format == Format::Text && !style.quiet {
+            outln!("{}", format_sudden_death(&last.result, history.len() - played));
+        }
+        if last.result != GameResult::Tie {
+            sudden_death = Some(last.result);
+        }
+    }
+    // The scoreboard and the rest cover the main series only.
+    let series = &history[..played];
+    if args.scoreboard {
+        outln!("{}", format_scoreboard(series, &style));
+    }
+    if args.count {
+        outln!("{}", format_move_counts(series, args.variant, &style));
+    }
+    if !args.quiet {
+        outln!("{}", format_streaks(&Streaks::of(series), args.format));
+    }
+    Series { tally, sudden_death }
+}
+
+/**
+ * How a sudden death ended, e.g. "You won the sudden death in round 2".
+ */
+fn format_sudden_death(result : &GameResult, rounds : usize) -> String {
+    match result {
+        GameResult::UserWin => format!("You won the sudden death in round {}", rounds),
+        GameResult::OpponentWin => format!("The opponent won the sudden death in round {}", rounds),
+        GameResult::Tie => format!("Sudden death still tied after {} rounds", rounds),
+    }
+}
+
+/**
+ * Plays `games` games without printing them. The user throws the given move
+ * every game, or a uniformly random one if no move was given. Everything is
+ * drawn from one seeded RNG so a simulation can be repeated exactly.
+ */
+fn simulate(args : &Cli, games : u32) -> Tally {
+    let rng = SharedRng::new(seeded_rng(args));
+    let mut user : Box<dyn Opponent> = match &args.pattern {
+        Some(_) => Box::new(ConstantOpponent(parse_move(args))),
+        None => Box::new(RandomOpponent::new(user_move_weights(args), rng.clone())),
+    };
+    let mut opponent = opponent(args, move_weights(args), rng);
+    let style = Style::from_args(args);
+    let delay = simulation_delay(args);
+    let mut tally = Tally::default();
+    let mut history = Vec::new();
+    for game in 1..=games {
+        let round = Round::new(user.next_move(&history), opponent.next_move(&history));
+        tally.record(&round.result);
+        if args.delay_ms > 0 {
+            outln!("{}", format_running_total(game, &round, &tally, &style));
+            thread::sleep(delay);
+        }
+        history.push(round);
+    }
+    tally
+}
+
+/**
+ * Reads a --seed-range: "1..100" leaves out 100, "1..=100" includes it.
+ */
+fn parse_seed_range(s : &str) -> Result<RangeInclusive<u64>, String> {
+    let bound = |part : &str| part.trim().parse::<u64>().map_err(|_| format!("{:?} is not a seed", part.trim()));
+    let (start, end) = s.split_once("..").ok_or_else(|| format!("expected a range such as 1..100 or 1..=100, found {:?}", s))?;
+    let start = bound(start)?;
+    let range = match end.strip_prefix('=') {
+        Some(end) => start..=bound(end)?,
+        None => match bound(end)?.checked_sub(1) {
+            Some(last) => start..=last,
+            None => return Err(format!("{} is empty", s.trim())),
+        },
+    };
+    if range.is_empty() {
+        return Err(format!("{} is empty", s.trim()));
+    }
+    Ok(range)
+}
+
+/**
+ * Plays the user's move once against a fresh opponent seeded with each seed
+ * of `seeds`, in order. Nothing is printed.
+ */
+fn sweep_seeds(args : &Cli, seeds : &RangeInclusive<u64>) -> Vec<Round> {
+    let our_move = parse_move(args);
+    let weights = move_weights(args);
+    seeds
+        .clone()
+        .map(|seed| Round::new(our_move, opponent(args, weights.clone(), GameRng::seed_from_u64(seed)).next_move(&[])))
+        .collect()
+}
+
+/**
+ * One seed of a --seed-range sweep, e.g. "Seed 3: you Rock vs Paper — loss".
+ */
+fn format_seed_outcome(seed : u64, round : &Round, style : &Style) -> String {
+    format!("Seed {}: you {} vs {} — {}", seed, style.move_name(&round.user_move), style.move_name(&round.opponent_move), outcome_word(&round.result))
+}
+
+/**
+ * How the seeds of a sweep split, e.g. "Seeds 1..=10: 4 wins, 3 ties, 3 losses".
+ */
+fn format_seed_sweep(seeds : &RangeInclusive<u64>, tally : &Tally, format : Format) -> String {
+    let count = |n : u32, one : &str, many : &str| format!("{} {}", n, if n == 1 { one } else { many });
+    let range = format!("{}..={}", seeds.start(), seeds.end());
+    match format {
+        Format::Text | Format::Csv => format!(
+            "Seeds {}: {}, {}, {}",
+            range,
+            count(tally.user_wins, "win", "wins"),
+            count(tally.ties, "tie", "ties"),
+            count(tally.opponent_wins, "loss", "losses")
+        ),
+        Format::Json => serde_json::json!({ "seeds": range, "tally": tally }).to_string(),
+    }
+}
+
+/**
+ * Like `countdown_delay`, no pauses when piped.
+ */
+fn simulation_delay(args : &Cli) -> Duration {
+    if output_is_terminal(args) {
+        Duration::from_millis(args.delay_ms)
+    } else {
+        Duration::ZERO
+    }
+}
+
+/**
+ * A paced simulation game with the totals so far, e.g. "Game 3: you Rock vs
+ * Paper — loss. You 1 - Opponent 2 (0 ties)".
+ */
+fn format_running_total(game : u32, round : &Round, tally : &Tally, style : &Style) -> String {
+    match style.format {
+        Format::Text | Format::Csv => format!(
+            "Game {}: you {} vs {} — {}. {}",
+            game,
+            style.move_name(&round.user_move),
+            style.move_name(&round.opponent_move),
+            outcome_word(&round.result),
+            tally
+        ),
+        Format::Json => serde_json::json!({ "game": game, "round": round, "tally": tally }).to_string(),
+    }
+}
+
+fn format_simulation(tally : &Tally, format : Format) -> String {
+    let games = tally.user_wins + tally.opponent_wins + tally.ties;
+    let percent = |n : u32| if games == 0 { 0.0 } else { f64::from(n) * 100.0 / f64::from(games) };
+    match format {
+        Format::Text | Format::Csv => format!(
+            "Simulated {} games: {:.1}% wins, {:.1}% losses, {:.1}% ties",
+            games,
+            percent(tally.user_wins),
+            percent(tally.opponent_wins),
+            percent(tally.ties)
+        ),
+        Format::Json => serde_json::json!({ "games": games, "tally": tally, "win_rate": tally.win_rate() }).to_string(),
+    }
+}
+
+/**
+ * The exact outcome rates of a --simulate run against a random opponent:
+ * your move or --user-weights against the opponent's --weights.
+ */
+fn expected_simulation(args : &Cli) -> OutcomeRates {
+    let user = match &args.pattern {
+        Some(_) => vec![(parse_move(args), 1.0)],
+        None => user_move_weights(args).probabilities(),
+    };
+    expected_rates(&user, &move_weights(args).probabilities())
+}
+
+fn format_nash_check(tally : &Tally, expected : &OutcomeRates, format : Format) -> String {
+    let games = tally.user_wins + tally.opponent_wins + tally.ties;
+    let simulated = OutcomeRates::from_tally(tally);
+    let describe = |rates : &OutcomeRates| format!(
+        "{:.1}% wins, {:.1}% losses, {:.1}% ties, expected value {:+.3}",
+        rates.win * 100.0,
+        rates.loss * 100.0,
+        rates.tie * 100.0,
+        rates.value()
+    );
+    match format {
+        Format::Text | Format::Csv => format!("Simulated {} games: {}\nExact: {}", games, describe(&simulated), describe(expected)),
+        Format::Json => serde_json::json!({
+            "games": games,
+            "simulated": simulated,
+            "simulated_value": simulated.value(),
+            "expected": expected,
+            "expected_value": expected.value(),
+        }).to_string(),
+    }
+}
+
+/**
+ * Plays `args.rounds` rounds with a custom rule set, seeded the same way as
+ * `play_rounds`. The opponent has no strategy of its own: it picks uniformly.
+ */
+fn play_custom(args : &Cli, rules : &Ruleset) -> Tally {
+    let pattern = args.pattern.as_deref().unwrap_or_default();
+    let our_move = match rules.parse_move(pattern) {
+        Ok(x) => x,
+        Err(err) => exit_on_bad_move(&err),
+    };
+    let style = Style::from_args(args);
+    let seed = base_seed(args);
+    let mut tally = Tally::default();
+    for index in 0..args.rounds {
+        let mut rng = GameRng::seed_from_u64(round_seed(seed, index));
+        let opponent_move = if replays_ties(args) {
+            let is_tie = |m : &DynamicMove| rules.outcome(our_move, *m) == GameResult::Tie;
+            let decisive = if rules.moves().all(|m| is_tie(&m)) {
+                None
+            } else {
+                reroll_ties(|| rng.sample(rules), is_tie)
+            };
+            let (opponent_move, rerolls) = no_decisive_outcome(decisive).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(EXIT_INVALID_INPUT)
+            });
+            if let Some(note) = format_rerolls(args, rerolls) {
+                outln!("{}", note);
+            }
+            opponent_move
+        } else {
+            rng.sample(rules)
+        };
+        let result = resolve_tie(args, rules.outcome(our_move, opponent_move));
+        match style.format {
+            Format::Text if style.verbose && !style.quiet => outln!(
+                "Your move: {}. {}\n{}",
+                rules.name(our_move),
+                format_result(rules.name(opponent_move), &result, &style),
+                rules.explain(our_move, opponent_move)
+            ),
+            Format::Text => outln!("{}", format_result(rules.name(opponent_move), &result, &style)),
+            Format::Json => outln!(
+                "{}",
+                serde_json::json!({ "opponent_move": rules.name(opponent_move), "user_move": rules.name(our_move), "result": result })
+            ),
+            Format::Csv => outln!("{},{},{}", rules.name(our_move), rules.name(opponent_move), result.name()),
+        }
+        tally.record(&result);
+    }
+    tally
+}
+
+/**
+ * Everything --dry-run checks, without drawing a move: the move (against the
+ * rules file, if there is one), both sets of weights and a forced, scripted
+ * or file opponent. The variant and strategy were already checked by clap.
+ */
+fn validate(args : &Cli) -> Result<(), String> {
+    let pattern = args.pattern.as_deref();
+    match &args.rules {
+        Some(path) => {
+            let rules = Ruleset::load(path).map_err(|err| err.to_string())?;
+            if let Some(pattern) = pattern {
+                rules.parse_move(pattern).map_err(|err| err.to_string())?;
+            }
+        }
+        None => {
+            if let Some(pattern) = pattern {
+                args.lang.parse_move(args.variant, pattern).map_err(|err| err.to_string())?;
+            }
+        }
+    }
+    for weights in [&args.weights, &args.user_weights].into_iter().flatten() {
+        MoveWeights::new(args.variant, weights).map_err(|err| err.to_string())?;
+    }
+    if let Some(forced) = args.opponent.as_deref() {
+        args.lang.parse_move(args.variant, forced).map_err(|err| format!("Invalid opponent move {:?}: {}", forced, err))?;
+    }
+    if let Some(sequence) = args.sequence.as_deref() {
+        ScriptedOpponent::parse(args.variant, sequence).map_err(|err| format!("Invalid sequence {:?}: {}", sequence, err))?;
+    }
+    if let Some(path) = &args.opponent_file {
+        read_opponent_file(args, path).and_then(|moves| check_file_length(args, path, moves))?;
+    }
+    check_tie_replays(args)
+}
+
+/**
+ * Everything --self-test finds wrong. Each built-in variant is checked, and
+ * `calculate_winner` has to give the same results as the variant's rule set.
+ * A --rules file can't load unless every matchup is settled exactly once, so
+ * for one this is the loader's complaint, if any.
+ */
+fn self_test(args : &Cli) -> Vec<String> {
+    if let Some(path) = &args.rules {
+        return match Ruleset::load(path) {
+            Ok(rules) => rules.self_test(),
+            Err(err) => vec![err.to_string()],
+        };
+    }
+    let mut problems = Vec::new();
+    for variant in Variant::value_variants() {
+        let rules = Ruleset::from_variant(*variant);
+        problems.extend(rules.self_test());
+        for (a, dynamic_a) in variant.moves().iter().zip(rules.moves()) {
+            for (b, dynamic_b) in variant.moves().iter().zip(rules.moves()) {
+                if calculate_winner(*a, *b) != rules.outcome(dynamic_a, dynamic_b) {
+                    problems.push(format!("calculate_winner disagrees with beats on {} against {}", a, b));
+                }
+            }
+        }
+    }
+    problems
+}
+
+fn load_rules(path : &Path) -> Ruleset {
+    match Ruleset::load(path) {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(EXIT_INVALID_INPUT)
+        }
+    }
+}
+
+/**
+ * Plays `args.rounds` rounds against each tournament opponent, then prints
+ * the leaderboard. Opponent `i` is seeded with the base seed plus `i`, so a
+ * seeded tournament is reproducible. Returns the combined tally.
+ */
+fn play_tournament(args : &Cli) -> Tally {
+    let our_move = parse_move(args);
+    let weights = move_weights(args);
+    let style = Style::from_args(args);
+    let seed = base_seed(args);
+    let strategies = [Strategy::Random, Strategy::Frequency, Strategy::AlwaysRock];
+    let opponents = strategies.iter().zip(0..).map(|(strategy, offset)| {
+        (value_name(strategy), strategy.opponent(weights.clone(), GameRng::seed_from_u64(seed.wrapping_add(offset))))
+    });
+
+    let mut total = Tally::default();
+    let mut standings = Vec::new();
+    for (name, mut opponent) in opponents {
+        let mut tally = Tally::default();
+        let mut history = Vec::new();
+        for _ in 0..args.rounds {
+            let round = Round::new(our_move, opponent.next_move(&history));
+            print_round(&round, &style);
+            tally.record(&round.result);
+            total.record(&round.result);
+            history.push(round);
+        }
+        standings.push(Standing { name: name.to_string(), tally });
+    }
+    outln!("{}", format_leaderboard(&tournament::leaderboard(standings), args.format));
+    total
+}
+
+/**
+ * Reads an --auto or --compare matchup such as "frequency:random": the
+ * strategy for the first side, then the second.
+ */
+fn parse_matchup(spec : &str) -> Result<(Strategy, Strategy), String> {
+    let (first, second) = spec.split_once(':').ok_or_else(|| "expected two strategies, e.g. frequency:random".to_string())?;
+    let strategy = |name : &str| Strategy::from_str(name.trim(), true).map_err(|_| format!("unknown strategy {:?}, expected one of {}", name, value_names::<Strategy>()));
+    Ok((strategy(first)?, strategy(second)?))
+}
+
+/**
+ * The --auto or --compare matchup, exiting with `EXIT_INVALID_INPUT` if it
+ * can't be read.
+ */
+fn matchup(spec : &str) -> (Strategy, Strategy) {
+    parse_matchup(spec).unwrap_or_else(|err| {
+        eprintln!("Invalid matchup {:?}: {}", spec, err);
+        process::exit(EXIT_INVALID_INPUT)
+    })
+}
+
+/**
+ * Plays `args.rounds` rounds between two computer strategies. The rounds are
+ * scored from the first side's point of view, so a `UserWin` is a win for
+ * it. Each side learns from the history as it saw it, with its own moves in
+ * the opponent's place.
+ */
+fn play_auto(args : &Cli, sides : (Strategy, Strategy)) -> Tally {
+    let style = Style::from_args(args);
+    auto_rounds(args, sides, args.rounds, |index, round| outln!("{}", format_auto_round(index + 1, sides, round, &style)))
+}
+
+/**
+ * Plays `games` rounds of --compare, like --auto but silently.
+ */
+fn compare(args : &Cli, sides : (Strategy, Strategy), games : u32) -> Tally {
+    auto_rounds(args, sides, games, |_, _| ())
+}
+
+/**
+ * The rounds of a game between two strategies, shared by --auto and
+ * --compare. `on_round` sees each round as it is played.
+ */
+fn auto_rounds(args : &Cli, (first, second) : (Strategy, Strategy), rounds : u32, mut on_round : impl FnMut(u32, &Round)) -> Tally {
+    let seed = base_seed(args);
+    let mut first_player = first.opponent(MoveWeights::uniform(args.variant), GameRng::seed_from_u64(seed));
+    let mut second_player = second.opponent(move_weights(args), GameRng::seed_from_u64(seed.wrapping_add(1)));
+    let mut tally = Tally::default();
+    let mut history = Vec::new();
+    let mut mirrored = Vec::new();
+    for index in 0..rounds {
+        let round = Round::new(first_player.next_move(&mirrored), second_player.next_move(&history));
+        on_round(index, &round);
+        tally.record(&round.result);
+        mirrored.push(Round::new(round.opponent_move, round.user_move));
+        history.push(round);
+    }
+    tally
+}
+
+/**
+ * One round of an --auto game, e.g. "Round 1: frequency Rock vs random
+ * Paper — random wins".
+ */
+fn format_auto_round(number : u32, (first, second) : (Strategy, Strategy), round : &Round, style : &Style) -> String {
+    match style.format {
+        Format::Text if style.quiet => round.result.name().to_string(),
+        Format::Text => {
+            let outcome = match round.result {
+                GameResult::UserWin => format!("{} wins", value_name(&first)),
+                GameResult::OpponentWin => format!("{} wins", value_name(&second)),
+                GameResult::Tie => "tie".to_string(),
+            };
+            format!(
+                "Round {}: {} {} vs {} {} — {}",
+                number,
+                value_name(&first),
+                style.move_name(&round.user_move),
+                value_name(&second),
+                style.move_name(&round.opponent_move),
+                outcome
+            )
+        }
+        Format::Json => serde_json::json!({
+            "round": number,
+            "first": value_name(&first),
+            "first_move": round.user_move,
+            "second": value_name(&second),
+            "second_move": round.opponent_move,
+            "result": round.result,
+        })
+        .to_string(),
+        Format::Csv => transcript::round_row(round),
+    }
+}
+
+/**
+ * The score of an --auto game, e.g. "Final: frequency 3 - random 2 (1 tie)".
+ */
+fn format_auto_summary((first, second) : (Strategy, Strategy), tally : &Tally, format : Format) -> String {
+    match format {
+        Format::Text | Format::Csv => {
+            let tie_word = if tally.ties == 1 { "tie" } else { "ties" };
+            format!("Final: {} {} - {} {} ({} {})", value_name(&first), tally.user_wins, value_name(&second), tally.opponent_wins, tally.ties, tie_word)
+        }
+        Format::Json => serde_json::json!({
+            "first": value_name(&first),
+            "second": value_name(&second),
+            "first_wins": tally.user_wins,
+            "second_wins": tally.opponent_wins,
+            "ties": tally.ties,
+        })
+        .to_string(),
+    }
+}
+
+/**
+ * How far the first side of a --compare is ahead, in points of the share of
+ * games won, and the margin of error on that at 95% confidence. Each game
+ * scores +1, 0 or -1 for the first side, so the lead is the mean score and
+ * the margin comes from its standard error.
+ */
+fn comparison_margin(tally : &Tally) -> (f64, f64) {
+    if tally.played() == 0 {
+        return (0.0, 0.0);
+    }
+    let rates = OutcomeRates::from_tally(tally);
+    let lead = rates.value();
+    let variance = (rates.win + rates.loss - lead * lead).max(0.0);
+    (lead * 100.0, 1.96 * (variance / f64::from(tally.played())).sqrt() * 100.0)
+}
+
+/**
+ * The verdict of --compare, e.g. "frequency won 40.1% of 10000 games,
+ * random 33.2% (26.7% ties)" and which one is ahead by how much, or that
+ * the lead is within the margin of error.
+ */
+fn format_comparison((first, second) : (Strategy, Strategy), tally : &Tally, format : Format) -> String {
+    let games = tally.played();
+    let rates = OutcomeRates::from_tally(tally);
+    let (lead, margin) = comparison_margin(tally);
+    match format {
+        Format::Text | Format::Csv => {
+            let (leader, ahead) = if lead < 0.0 { (second, -lead) } else { (first, lead) };
+            let verdict = if games > 0 && ahead > margin {
+                format!("{} is ahead by {:.1} ± {:.1} points", value_name(&leader), ahead, margin)
+            } else {
+                format!("Too close to call: {} leads by {:.1} ± {:.1} points", value_name(&leader), ahead, margin)
+            };
+            format!(
+                "{} won {:.1}% of {} games, {} {:.1}% ({:.1}% ties)\n{} (95% confidence)",
+                value_name(&first),
+                rates.win * 100.0,
+                games,
+                value_name(&second),
+                rates.loss * 100.0,
+                rates.tie * 100.0,
+                verdict
+            )
+        }
+        Format::Json => serde_json::json!({
+            "first": value_name(&first),
+            "second": value_name(&second),
+            "games": games,
+            "first_wins": tally.user_wins,
+            "second_wins": tally.opponent_wins,
+            "ties": tally.ties,
+            "lead": lead,
+            "margin_of_error": margin,
+        })
+        .to_string(),
+    }
+}
+
+fn format_leaderboard(standings : &[Standing], format : Format) -> String {
+    match format {
+        Format::Text | Format::Csv => {
+            let lines : Vec<String> = standings
+                .iter()
+                .enumerate()
+                .map(|(i, s)| format!("{}. {}: {:.0}% ({})", i + 1, s.name, s.tally.win_rate() * 100.0, s.tally))
+                .collect();
+            format!("Leaderboard:\n{}", lines.join("\n"))
+        }
+        Format::Json => {
+            let entries : Vec<serde_json::Value> = standings
+                .iter()
+                .map(|s| serde_json::json!({ "opponent": s.name, "win_rate": s.tally.win_rate(), "tally": s.tally }))
+                .collect();
+            serde_json::Value::from(entries).to_string()
+        }
+    }
+}
+
+/**
+ * One attempt at reading an interactive move.
+ */
+#[derive(Debug, PartialEq)]
+enum Entry {
+    Line(String),
+    TimedOut,
+    Interrupted,
+    End
+}
+
+/**
+ * Where interactive moves come from. Lines are normally read by a helper
+ * thread, so the wait can be cut short by a --timeout or a Ctrl-C; the
+ * thread finishes by itself at the end of the input. --hotkeys on a terminal
+ * reads each key directly instead, since it can't read ahead without taking
+ * over the terminal, and gets Ctrl-C as a key anyway.
+ */
+enum MoveSource {
+    Hotkeys,
+    Threaded(Receiver<String>, Option<Duration>)
+}
+
+impl MoveSource {
+    fn new<R: BufRead + Send + 'static>(input : R, args : &Cli) -> MoveSource {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in input.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let timeout = (args.timeout > 0).then(|| Duration::from_secs(args.timeout));
+        MoveSource::Threaded(receiver, timeout)
+    }
+
+    /**
+     * Waits for the next move, checking `interrupted` every
+     * `INTERRUPT_POLL` while it does. Lines that were already read are
+     * handed out first, so moves typed before a Ctrl-C are still played.
+     */
+    fn next_entry(&mut self, interrupted : &AtomicBool) -> Entry {
+        let (receiver, timeout) = match self {
+            MoveSource::Hotkeys if interrupted.load(Ordering::SeqCst) => return Entry::Interrupted,
+            MoveSource::Hotkeys => return read_hotkey().unwrap_or(Entry::End),
+            MoveSource::Threaded(receiver, timeout) => (receiver, *timeout),
+        };
+        if let Ok(line) = receiver.try_recv() {
+            return Entry::Line(line);
+        }
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if interrupted.load(Ordering::SeqCst) {
+                return Entry::Interrupted;
+            }
+            let wait = deadline.map_or(INTERRUPT_POLL, |deadline| deadline.saturating_duration_since(Instant::now()).min(INTERRUPT_POLL));
+            match receiver.recv_timeout(wait) {
+                Ok(line) => return Entry::Line(line),
+                Err(RecvTimeoutError::Disconnected) => return Entry::End,
+                Err(RecvTimeoutError::Timeout) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => return Entry::TimedOut,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+    }
+}
+
+/**
+ * Keeps the terminal in raw mode for as long as it lives. Dropping it, on
+ * an early return or a panic too, puts the terminal back the way it was.
+ */
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // Nothing more can be done if the terminal won't go back.
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/**
+ * What a --hotkeys keypress stands for: the character itself as a line,
+ * "quit" for Escape and Ctrl-D, and an interrupt for Ctrl-C, which raw mode
+ * delivers as a key instead of a signal. Other keys are ignored.
+ */
+fn key_entry(key : &KeyEvent) -> Option<Entry> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    match key.code {
+        KeyCode::Esc => Some(Entry::Line(String::from("quit"))),
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Entry::Interrupted),
+        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Entry::Line(String::from("quit"))),
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => Some(Entry::Line(c.to_string())),
+        _ => None,
+    }
+}
+
+/**
+ * Waits for a --hotkeys keypress that stands for something, so
+ * `play_interactive` can take it like a typed move. Raw mode is only on
+ * while waiting for a key, so the game's output in between prints normally.
+ */
+fn read_hotkey() -> io::Result<Entry> {
+    let entry = {
+        let _raw = RawMode::enable()?;
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if let Some(entry) = key_entry(&key) {
+                    break entry;
+                }
+            }
+        }
+    };
+    // Raw mode doesn't echo, so show the key where it would have been typed.
+    if let Entry::Line(line) = &entry {
+        eprintln!("{}", line);
+    }
+    Ok(entry)
+}
+
+/**
+ * The --practice suggestion after a round that wasn't won, e.g. "You should
+ * have played Paper". Nothing after a win, or outside plain text output.
+ */
+fn format_coaching(round : &Round, style : &Style) -> Option<String> {
+    if round.result == GameResult::UserWin || style.format != Format::Text || style.quiet {
+        return None;
+    }
+    Some(format!("{} {}", style.lang.strings().should_have_played, style.move_name(&round.opponent_move.counter())))
+}
+
+/**
+ * The text for a round forfeited to --timeout.
+ */
+fn format_forfeit(style : &Style) -> String {
+    match style.format {
+        Format::Text if style.quiet => GameResult::OpponentWin.name().to_string(),
+        Format::Text => style.lang.strings().timed_out.to_string(),
+        Format::Json => serde_json::json!({ "result": GameResult::OpponentWin, "forfeit": true }).to_string(),
+        Format::Csv => transcript::forfeit_row(),
+    }
+}
+
+/**
+ * Lets the first Ctrl-C of an interactive session end it through
+ * `INTERRUPTED`, so the scoreboard still gets printed. A second one quits
+ * straight away, in case the session doesn't get to the check.
+ */
+fn watch_for_interrupts() {
+    let handler = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(EXIT_INTERRUPTED);
+        }
+    });
+    // Without the handler Ctrl-C still works, it just quits at once.
+    if let Err(err) = handler {
+        eprintln!("Could not catch Ctrl-C: {}", err);
+    }
+}
+
+/**
+ * Reads moves from `input` one line at a time, playing a round for each,
+ * until the user types "quit" or the input ends. Invalid lines are reported
+ * and skipped rather than ending the session. With a --timeout, a move that
+ * is too slow loses the round, and anything typed late counts towards the
+ * next one. With --max-rounds the session also ends once that many rounds,
+ * forfeits included, have been played. Once `interrupted` is set, by Ctrl-C,
+ * the session ends at the next move and prints the scoreboard.
+ */
+fn play_interactive<R: BufRead + Send + 'static>(input: R, args : &Cli, opponent : &mut dyn Opponent, transcript : &mut Option<Transcript<File>>, stats : &mut Option<StatsWriter>, interrupted : &AtomicBool) -> Tally {
+    play_session(MoveSource::new(input, args), args, opponent, transcript, stats, interrupted)
+}
+
+/**
+ * The session `play_interactive` plays, taking its moves from `source`.
+ */
+fn play_session(mut source : MoveSource, args : &Cli, opponent : &mut dyn Opponent, transcript : &mut Option<Transcript<File>>, stats : &mut Option<StatsWriter>, interrupted : &AtomicBool) -> Tally {
+    let style = Style::from_args(args);
+    let mut tally = Tally::default();
+    let mut history = Vec::new();
+    let mut was_interrupted = false;
+    loop {
+        if args.max_rounds > 0 && tally.played() >= args.max_rounds {
+            if args.format == Format::Text && !args.quiet {
+                outln!("Reached the limit of {} rounds.", args.max_rounds);
+            }
+            break;
+        }
+        if args.format == Format::Text && !args.quiet {
+            out!("Your move: ");
+            // A failed flush only affects the prompt, the game can carry on.
+            let _ = Output.flush();
+        }
+        let line = match source.next_entry(interrupted) {
+            Entry::Line(line) => line,
+            Entry::TimedOut => {
+                // End the prompt's line before reporting the forfeit.
+                if args.format == Format::Text && !args.quiet {
+                    outln!();
+                }
+                outln!("{}", format_forfeit(&style));
+                // Neither side threw anything, so the forfeit stays out of
+                // the history the opponent learns from.
+                log_forfeit(transcript, args.seed);
+                tally.record(&GameResult::OpponentWin);
+                record_stats(stats, None, &GameResult::OpponentWin);
+                continue;
+            }
+            Entry::Interrupted => {
+                if args.format == Format::Text && !args.quiet {
+                    outln!();
+                    outln!("Interrupted.");
+                }
+                was_interrupted = true;
+                break;
+            }
+            Entry::End => break,
+        };
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("quit") {
+            break;
+        }
+        match args.lang.parse_move(args.variant, line) {
+            Ok(our_move) => {
+                let round = play_round(args, opponent, our_move, &history);
+                print_round(&round, &style);
+                if args.practice {
+                    if let Some(coaching) = format_coaching(&round, &style) {
+                        outln!("{}", coaching);
+                    }
+                }
+                log_round(transcript, &round, args.seed);
+                tally.record(&round.result);
+                record_stats(stats, Some(round.user_move), &round.result);
+                history.push(round);
+            }
+            Err(err) => outln!("{}", err),
+        }
+    }
+    if args.scoreboard || was_interrupted {
+        outln!("{}", format_scoreboard(&history, &style));
+    }
+    if args.count {
+        outln!("{}", format_move_counts(&history, args.variant, &style));
+    }
+    if !args.quiet {
+        outln!("{}", format_streaks(&Streaks::of(&history), args.format));
+    }
+    tally
+}
+
+/**
+ * Plays a round for every non-blank line of `input`, printing one result per
+ * move. Unlike interactive mode there is no prompt and no "quit": the whole
+ * input is played, and invalid lines are reported on stderr with their line
+ * number.
+ */
+fn play_batch<R: BufRead>(input: R, args : &Cli, opponent : &mut dyn Opponent, transcript : &mut Option<Transcript<File>>, stats : &mut Option<StatsWriter>) -> Tally {
+    let style = Style::from_args(args);
+    let mut tally = Tally::default();
+    let mut history = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("Could not read input: {}", err);
+                break;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match args.lang.parse_move(args.variant, line) {
+            Ok(our_move) => {
+                let round = play_round(args, opponent, our_move, &history);
+                print_round(&round, &style);
+                log_round(transcript, &round, args.seed);
+                tally.record(&round.result);
+                record_stats(stats, Some(round.user_move), &round.result);
+                history.push(round);
+            }
+            Err(err) => eprintln!("Line {}: {}", number + 1, err),
+        }
+    }
+    if args.scoreboard {
+        outln!("{}", format_scoreboard(&history, &style));
+    }
+    if args.count {
+        outln!("{}", format_move_counts(&history, args.variant, &style));
+    }
+    if !args.quiet {
+        outln!("{}", format_streaks(&Streaks::of(&history), args.format));
+    }
+    tally
+}
+
+/**
+ * Serves games on the Unix domain socket at `path`, one client at a time.
+ * A stale socket left behind by an earlier run is replaced, but one a server
+ * is still listening on is left alone. Every client
+ * gets a fresh opponent seeded the same way, so each connection plays like
+ * a new --batch game.
+ */
+#[cfg(unix)]
+fn serve(args : &Cli, path : &Path, transcript : &mut Option<Transcript<File>>) -> ! {
+    use std::os::unix::{fs::FileTypeExt, net::{UnixListener, UnixStream}};
+
+    if fs::metadata(path).map(|meta| meta.file_type().is_socket()).unwrap_or(false) {
+        if UnixStream::connect(path).is_ok() {
+            eprintln!("Could not listen on {}: another server is already using it", path.display());
+            process::exit(EXIT_INVALID_INPUT)
+        }
+        let _ = fs::remove_file(path);
+    }
+    let listener = UnixListener::bind(path).unwrap_or_else(|err| {
+        eprintln!("Could not listen on {}: {}", path.display(), err);
+        process::exit(EXIT_INVALID_INPUT)
+    });
+    for client in listener.incoming() {
+        match client {
+            Ok(stream) => {
+                let reader = match stream.try_clone() {
+                    Ok(reader) => io::BufReader::new(reader),
+                    Err(err) => {
+                        eprintln!("Could not read from client: {}", err);
+                        continue;
+                    }
+                };
+                let mut opponent = opponent(args, move_weights(args), seeded_rng(args));
+                serve_client(reader, stream, args, opponent.as_mut(), transcript);
+            }
+            Err(err) => eprintln!("Could not accept client: {}", err),
+        }
+    }
+    unreachable!("incoming() never runs out")
+}
+
+#[cfg(not(unix))]
+fn serve(_args : &Cli, _path : &Path, _transcript : &mut Option<Transcript<File>>) -> ! {
+    eprintln!("--input-socket needs Unix domain sockets, which this platform doesn't have");
+    process::exit(EXIT_INVALID_INPUT)
+}
+
+/**
+ * Plays a round for every non-blank line a socket client sends, writing the
+ * result, or what was wrong with the line or the round, back to it along with
+ * anything shown before the result. Output is never colored. The client
+ * hanging up, even halfway through, just ends its games.
+ */
+fn serve_client<R: BufRead, W: Write>(input : R, mut output : W, args : &Cli, opponent : &mut dyn Opponent, transcript : &mut Option<Transcript<File>>) -> Tally {
+    let style = Style { color: false, split: false, ..Style::from_args(args) };
+    let mut tally = Tally::default();
+    let mut history = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        // A client that resets the connection has simply gone away.
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let reply = match args.lang.parse_move(args.variant, line).map_err(|err| err.to_string()).and_then(|our_move| play_round_to(&mut output, args, opponent, our_move, &history)) {
+            Ok(round) => {
+                log_round(transcript, &round, args.seed);
+                tally.record(&round.result);
+                let reply = format_round(&round, &style);
+                history.push(round);
+                reply
+            }
+            Err(err) => format!("Line {}: {}", number + 1, err),
+        };
+        if writeln!(output, "{}", reply).and_then(|_| output.flush()).is_err() {
+            break;
+        }
+    }
+    tally
+}
+
+/**
+ * Reads one move for each player and scores them, player 1 standing in for
+ * the user. The screen is cleared between the two prompts so player 2 can't
+ * see what player 1 picked. Returns `None` if the input ends first.
+ */
+fn play_two_player<R: BufRead>(input: R, args : &Cli) -> Option<Round> {
+    let mut lines = input.lines();
+    let first = prompt_move(&mut lines, args, "Player 1")?;
+    if output_is_terminal(args) {
+        out!("\x1b[2J\x1b[H");
+    }
+    let second = prompt_move(&mut lines, args, "Player 2")?;
+    let round = Round::new(first, second);
+    outln!("{}", format_players(&round, &Style::from_args(args)));
+    Some(round)
+}
+
+/**
+ * A round between two people, player 1 in the user's seat, e.g. "Player 1's
+ * move: Rock. Player 2's move: Paper. Player 2 wins!".
+ */
+fn format_players(round : &Round, style : &Style) -> String {
+    let message = match round.result {
+        GameResult::UserWin => "Player 1 wins!",
+        GameResult::OpponentWin => "Player 2 wins!",
+        GameResult::Tie => "Tie",
+    };
+    format!(
+        "Player 1's move: {}. Player 2's move: {}. {}",
+        style.move_name(&round.user_move),
+        style.move_name(&round.opponent_move),
+        message
+    )
+}
+
+/**
+ * What --commit prints: the commitment to share now and the reveal to keep
+ * secret until the other player has committed too.
+ */
+fn format_commitment(reveal : &Reveal, format : Format) -> String {
+    match format {
+        Format::Text | Format::Csv => format!("Commitment (share now): {}\nReveal (share later): {}", reveal.commitment(), reveal),
+        Format::Json => serde_json::json!({ "commitment": reveal.commitment(), "reveal": reveal.to_string() }).to_string(),
+    }
+}
+
+/**
+ * Verifies each player's reveal against the commitment they published and
+ * scores the moves, player 1 first. `values` holds player 1's commitment and
+ * reveal, then player 2's. Both moves also have to belong to the variant.
+ */
+fn check_reveals(values : &[String], variant : Variant) -> Result<Round, String> {
+    let mut moves = Vec::new();
+    for (number, pair) in (1..).zip(values.chunks(2)) {
+        let [commitment, text] = pair else { unreachable!("clap takes the values in pairs") };
+        let verified = text.parse::<Reveal>().and_then(|reveal| reveal.verify(commitment)).map_err(|err| format!("Player {}: {}", number, err))?;
+        if !variant.moves().contains(&verified) {
+            return Err(format!("Player {}: {} isn't played in this variant", number, verified));
+        }
+        moves.push(verified);
+    }
+    Ok(Round::new(moves[0], moves[1]))
+}
+
+/**
+ * Prompts until a valid move is entered, reporting invalid lines the same way
+ * interactive mode does.
+ */
+fn prompt_move<R: BufRead>(lines : &mut io::Lines<R>, args : &Cli, player : &str) -> Option<Move> {
+    loop {
+        out!("{}, your move: ", player);
+        // A failed flush only affects the prompt, the game can carry on.
+        let _ = Output.flush();
+        let line = lines.next()?.ok()?;
+        match args.lang.parse_move(args.variant, line.trim()) {
+            Ok(x) => return Some(x),
+            Err(err) => outln!("{}", err),
+        }
+    }
+}
+
+/**
+ * Lets the opponent pick a move, given the rounds played so far, and scores it.
+ * A round that can't be played ends the game.
+ */
+fn play_round(args : &Cli, opponent : &mut dyn Opponent, our_move : Move, history : &[Round]) -> Round {
+    play_round_to(&mut Output, args, opponent, our_move, history).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(EXIT_INVALID_INPUT)
+    })
+}
+
+/**
+ * Plays a round like `play_round`, writing everything shown before the result
+ * (predictions, hints, the countdown and re-rolls) to `out`. Returns what was
+ * wrong if the round can't be played, so a server can tell just that client.
+ */
+fn play_round_to<W: Write>(out : &mut W, args : &Cli, opponent : &mut dyn Opponent, our_move : Move, history : &[Round]) -> Result<Round, String> {
+    let distribution = opponent.move_distribution(history);
+    if let (true, Some(distribution)) = (args.predict, &distribution) {
+        writeln!(out, "{}", format_prediction(&win_probabilities(args.variant, distribution), args.format)).map_err(|err| err.to_string())?;
+    }
+    if let (true, Some(distribution)) = (args.hint, &distribution) {
+        writeln!(out, "{}", format_hint(best_reply(args.variant, distribution), args.format)).map_err(|err| err.to_string())?;
+    }
+    if args.dramatic && args.format == Format::Text && !args.quiet {
+        // The countdown is only for show, the game can carry on without it.
+        let _ = countdown(out, countdown_delay(args));
+    }
+    if !replays_ties(args) {
+        check_moves_left(args, opponent, history)?;
+        let Round { opponent_move, user_move, result } = Round::new(our_move, opponent.next_move(history));
+        return Ok(Round { opponent_move, user_move, result: resolve_tie(args, result) });
+    }
+    // The distribution can't settle whether only ties are left: a scripted
+    // opponent is sure of its next move but moves on to another one.
+    let draw = || check_moves_left(args, opponent, history).map(|()| opponent.next_move(history));
+    // A draw the opponent couldn't make isn't a tie, so it stops the re-rolls.
+    let decisive = reroll_ties(draw, |m| m.as_ref().is_ok_and(|m| *m == our_move));
+    let (opponent_move, rerolls) = no_decisive_outcome(decisive)?;
+    if let Some(note) = format_rerolls(args, rerolls) {
+        writeln!(out, "{}", note).map_err(|err| err.to_string())?;
+    }
+    Ok(Round::new(our_move, opponent_move?))
+}
+
+/**
+ * Draws until `is_tie` says the move settles the round, giving up after
+ * `MAX_REROLLS` re-rolls. Returns the move and how many ties were re-rolled.
+ */
+fn reroll_ties<M>(mut draw : impl FnMut() -> M, is_tie : impl Fn(&M) -> bool) -> Option<(M, u32)> {
+    for rerolls in 0..=MAX_REROLLS {
+        let m = draw();
+        if !is_tie(&m) {
+            return Some((m, rerolls));
+        }
+    }
+    None
+}
+
+/**
+ * Whether ties are replayed, by --no-ties or `--tie-policy replay`.
+ */
+fn replays_ties(args : &Cli) -> bool {
+    args.no_ties || args.tie_policy == TiePolicy::Replay
+}
+
+/**
+ * Rejects replaying ties against `--ai shuffle-bag`. The last move of each
+ * bag is already decided, so when it matches the user's move every re-roll
+ * is a tie.
+ */
+fn check_tie_replays(args : &Cli) -> Result<(), String> {
+    let deals_from_a_bag = args.ai == Strategy::ShuffleBag && args.difficulty.is_none() && args.opponent.is_none() && args.sequence.is_none() && args.opponent_file.is_none();
+    if replays_ties(args) && deals_from_a_bag {
+        return Err(String::from("--ai shuffle-bag can't replay ties: the last move in each bag is fixed, so a re-roll may only ever tie"));
+    }
+    Ok(())
+}
+
+/**
+ * The result under --tie-policy, for rounds whose ties aren't replayed.
+ */
+fn resolve_tie(args : &Cli, result : GameResult) -> GameResult {
+    args.tie_policy.resolve(result).expect("replayed ties never reach the tie policy")
+}
+
+/**
+ * Stops the game once an opponent that can run out of moves, i.e. an
+ * --opponent-file, has none left for the round after `history`.
+ */
+fn check_moves_left(args : &Cli, opponent : &dyn Opponent, history : &[Round]) -> Result<(), String> {
+    if opponent.moves_left() == Some(0) {
+        let path = args.opponent_file.as_ref().expect("only an --opponent-file runs out");
+        return Err(format!("{}: ran out of moves in round {}", path.display(), history.len() + 1));
+    }
+    Ok(())
+}
+
+fn no_decisive_outcome<M>(decisive : Option<(M, u32)>) -> Result<(M, u32), String> {
+    decisive.ok_or_else(|| String::from("No decisive outcome is possible: every re-roll was a tie"))
+}
+
+fn format_rerolls(args : &Cli, rerolls : u32) -> Option<String> {
+    (rerolls > 0 && args.format == Format::Text && !args.quiet).then(|| format!("(re-rolled {} {})", rerolls, if rerolls == 1 { "tie" } else { "ties" }))
+}
+
+/**
+ * Prints the countdown one word at a time, pausing `delay` after each.
+ */
+fn countdown<W: Write>(out : &mut W, delay : Duration) -> io::Result<()> {
+    let words = ["Rock...", "Paper...", "Scissors...", "Shoot!"];
+    for (i, word) in words.iter().enumerate() {
+        let separator = if i + 1 == words.len() { "\n" } else { " " };
+        write!(out, "{}{}", word, separator)?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+/**
+ * No pauses when piped, so scripts using --dramatic don't slow down.
+ */
+fn countdown_delay(args : &Cli) -> Duration {
+    if output_is_terminal(args) {
+        Duration::from_millis(args.countdown_ms)
+    } else {
+        Duration::ZERO
+    }
+}
+
+/**
+ * The computer opponent the flags ask for: the --sequence or --opponent-file
+ * if there is one, otherwise the --difficulty or --ai strategy.
+ */
+fn opponent<R: Rng + SeedableRng + 'static>(args : &Cli, weights : MoveWeights, rng : R) -> Box<dyn Opponent> {
+    match sequence(args) {
+        Some(scripted) => scripted,
+        None => computer(args, weights, rng),
+    }
+}
+
+/**
+ * The opponent that makes up its own moves, by --difficulty if one was
+ * given and by the --ai strategy otherwise.
+ */
+fn computer<R: Rng + SeedableRng + 'static>(args : &Cli, weights : MoveWeights, rng : R) -> Box<dyn Opponent> {
+    match args.difficulty {
+        Some(difficulty) => difficulty.opponent(weights, rng),
+        None => args.ai.opponent(weights, rng),
+    }
+}
+
+/**
+ * The scripted opponent the flags ask for, if any. --opponent is a sequence
+ * of just one move.
+ */
+fn sequence(args : &Cli) -> Option<Box<dyn Opponent>> {
+    if let Some(path) = &args.opponent_file {
+        return match read_opponent_file(args, path).and_then(|moves| check_file_length(args, path, moves)) {
+            Ok(moves) => Some(Box::new(FileOpponent { moves, next: 0 })),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(EXIT_INVALID_INPUT)
+            }
+        };
+    }
+    if let Some(forced) = args.opponent.as_deref() {
+        return match args.lang.parse_move(args.variant, forced) {
+            Ok(m) => Some(Box::new(ScriptedOpponent::new(vec![m]))),
+            Err(err) => {
+                eprintln!("Invalid opponent move {:?}: {}", forced, err);
+                process::exit(EXIT_INVALID_INPUT)
+            }
+        };
+    }
+    let sequence = args.sequence.as_deref()?;
+    match ScriptedOpponent::parse(args.variant, sequence) {
+        Ok(x) => Some(Box::new(x)),
+        Err(err) => {
+            eprintln!("Invalid sequence {:?}: {}", sequence, err);
+            process::exit(EXIT_INVALID_INPUT)
+        }
+    }
+}
+
+/**
+ * Reads the moves of an --opponent-file, one per line in the spelling the
+ * command line accepts. Blank lines are skipped; a line that isn't a move is
+ * reported with its number.
+ */
+fn read_opponent_file(args : &Cli, path : &Path) -> Result<Vec<Move>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Could not read opponent file {}: {}", path.display(), err))?;
+    let mut moves = Vec::new();
+    for (number, line) in (1..).zip(text.lines()) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let m = args.lang.parse_move(args.variant, line.trim()).map_err(|err| format!("{}: line {}: {}", path.display(), number, err))?;
+        moves.push(m);
+    }
+    if moves.is_empty() {
+        return Err(format!("{}: no moves in the opponent file", path.display()));
+    }
+    Ok(moves)
+}
+
+/**
+ * Rejects an --opponent-file that is too short for the game before any of
+ * it is played. Games whose length isn't known up front, such as
+ * --interactive or --first-to, are stopped by `play_round` instead once the
+ * file runs out.
+ */
+fn check_file_length(args : &Cli, path : &Path, moves : Vec<Move>) -> Result<Vec<Move>, String> {
+    let count = |n : usize, one : &str, many : &str| format!("{} {}", n, if n == 1 { one } else { many });
+    match longest_game(args) {
+        Some(rounds) if moves.len() < rounds as usize => Err(format!("{}: {} for a game of up to {}", path.display(), count(moves.len(), "move", "moves"), count(rounds as usize, "round", "rounds"))),
+        _ => Ok(moves),
+    }
+}
+
+/**
+ * The most moves the opponent can be asked for, or `None` when the game has
+ * no fixed length. Re-rolled ties draw again, so they leave it open too.
+ */
+fn longest_game(args : &Cli) -> Option<u32> {
+    if args.interactive || args.batch || args.input_socket.is_some() || args.first_to.is_some() || args.sudden_death || replays_ties(args) {
+        return None;
+    }
+    Some(args.simulate.or(args.best_of).unwrap_or(args.rounds))
+}
+
+/**
+ * Plays the moves of an --opponent-file once, in order. Unlike --sequence it
+ * doesn't start over: the file is meant to cover the whole game, so running
+ * out ends it with an error.
+ */
+struct FileOpponent {
+    moves: Vec<Move>,
+    next: usize
+}
+
+impl Opponent for FileOpponent {
+    fn next_move(&mut self, _history : &[Round]) -> Move {
+        let m = self.moves[self.next];
+        self.next += 1;
+        m
+    }
+
+    fn moves_left(&self) -> Option<usize> {
+        Some(self.moves.len() - self.next)
+    }
+}
+
+/**
+ * The --challenge opponent. Round `i` is played by a fresh opponent seeded
+ * with `round_seed(seed, i)` that hasn't seen the earlier rounds, so its
+ * moves only depend on the seed, and round N is the single game played with
+ * that round's seed. Each one is built by `computer` from the same flags.
+ * Re-rolled ties carry on with the same round's opponent.
+ */
+struct ChallengeOpponent<'a> {
+    args: &'a Cli,
+    seed: u64,
+    current: Option<(usize, Box<dyn Opponent>)>
+}
+
+impl<'a> ChallengeOpponent<'a> {
+    fn new(args : &'a Cli, seed : u64) -> ChallengeOpponent<'a> {
+        ChallengeOpponent { args, seed, current: None }
+    }
+
+    fn round_opponent(&self, round : usize) -> Box<dyn Opponent> {
+        let seed = round_seed(self.seed, u32::try_from(round).unwrap_or(u32::MAX));
+        computer(self.args, move_weights(self.args), GameRng::seed_from_u64(seed))
+    }
+}
+
+impl Opponent for ChallengeOpponent<'_> {
+    fn next_move(&mut self, history : &[Round]) -> Move {
+        let round = history.len();
+        if !matches!(&self.current, Some((current, _)) if *current == round) {
+            self.current = Some((round, self.round_opponent(round)));
+        }
+        let (_, opponent) = self.current.as_mut().expect("just set");
+        opponent.next_move(&[])
+    }
+
+    fn move_distribution(&self, history : &[Round]) -> Option<MoveDistribution> {
+        self.round_opponent(history.len()).move_distribution(&[])
+    }
+}
+
+/**
+ * The seed of a --challenge. One picked at random is always shown, since
+ * the point is to pass it on. main settles the seed before the game starts,
+ * so `seed_given` says whether the user picked it.
+ */
+fn challenge_seed(args : &Cli, seed_given : bool) -> u64 {
+    let seed = base_seed(args);
+    if !seed_given && !args.show_seed {
+        eprintln!("(seed: {})", seed);
+    }
+    seed
+}
+
+fn parse_move(args : &Cli) -> Move {
+    let pattern = args.pattern.as_deref().unwrap_or_default();
+    match args.lang.parse_move(args.variant, pattern) {
+        Ok(x) => x,
+        Err(err) => exit_on_bad_move(&err),
+    }
+}
+
+/**
+ * Reports a move that can't be played the way clap reports its own errors,
+ * with the usage text, then exits with `bad_move_exit_code`. The move is
+ * checked here rather than by a clap value parser because whether it is
+ * valid depends on --variant, --lang and --rules.
+ */
+fn exit_on_bad_move(err : &ParseMoveError) -> ! {
+    let kind = if err.is_empty() { ErrorKind::EmptyValue } else { ErrorKind::InvalidValue };
+    let _ = Cli::command().error(kind, err).print();
+    process::exit(bad_move_exit_code(err))
+}
+
+/**
+ * A missing move gets its own exit code so scripts can tell it from a typo.
+ */
+fn bad_move_exit_code(err : &ParseMoveError) -> i32 {
+    if err.is_empty() {
+        EXIT_NO_MOVE
+    } else {
+        EXIT_INVALID_INPUT
+    }
+}
+
+fn move_weights(args : &Cli) -> MoveWeights {
+    parse_weights(args.variant, &args.weights)
+}
+
+/**
+ * Reads a probability for --house-edge, between 0 and 1.
+ */
+fn parse_probability(s : &str) -> Result<f64, String> {
+    let p : f64 = s.parse().map_err(|_| format!("{:?} is not a number", s))?;
+    if (0.0..=1.0).contains(&p) {
+        Ok(p)
+    } else {
+        Err(format!("{} is not between 0 and 1", p))
+    }
+}
+
+fn user_move_weights(args : &Cli) -> MoveWeights {
+    parse_weights(args.variant, &args.user_weights)
+}
+
+fn parse_weights(variant : Variant, weights : &Option<Vec<f64>>) -> MoveWeights {
+    match weights {
+        None => MoveWeights::uniform(variant),
+        Some(weights) => match MoveWeights::new(variant, weights) {
+            Ok(x) => x,
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(EXIT_INVALID_INPUT)
+            }
+        },
+    }
+}
+
+fn seeded_rng(args : &Cli) -> GameRng {
+    GameRng::seed_from_u64(base_seed(args))
+}
+
+/**
+ * A `GameRng` that both sides of a --simulate draw from, so the user's
+ * random moves and the opponent's come from the one seeded stream.
+ */
+#[derive(Clone)]
+struct SharedRng(Rc<RefCell<GameRng>>);
+
+impl SharedRng {
+    fn new(rng : GameRng) -> SharedRng {
+        SharedRng(Rc::new(RefCell::new(rng)))
+    }
+}
+
+impl RngCore for SharedRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.borrow_mut().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.borrow_mut().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest : &mut [u8]) {
+        self.0.borrow_mut().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest : &mut [u8]) -> Result<(), rand::Error> {
+        self.0.borrow_mut().try_fill_bytes(dest)
+    }
+}
+
+impl SeedableRng for SharedRng {
+    type Seed = <GameRng as SeedableRng>::Seed;
+
+    fn from_seed(seed : Self::Seed) -> SharedRng {
+        SharedRng::new(GameRng::from_seed(seed))
+    }
+}
+
+/**
+ * The seed the whole game derives from, printed if the user asked for it.
+ */
+fn base_seed(args : &Cli) -> u64 {
+    let seed = resolve_seed(requested_seed(args));
+    if args.show_seed {
+        eprintln!("(seed: {})", seed);
+    }
+    seed
+}
+
+/**
+ * The seed the user picked, by number or by phrase.
+ */
+fn requested_seed(args : &Cli) -> Option<u64> {
+    args.seed.or_else(|| args.seed_phrase.as_deref().map(seed_from_phrase))
+}
+
+fn resolve_seed(seed : Option<u64>) -> u64 {
+    // If optional seed provided, use it, else, pick one from entropy so the
+    // game can still be replayed with `--seed`.
+    seed.unwrap_or_else(rand::random)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_opponent(seed : u64) -> RandomOpponent<GameRng> {
+        RandomOpponent::new(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(seed))
+    }
+
+    fn assert_rates_close(tally : &Tally, expected : &OutcomeRates){
+        let simulated = OutcomeRates::from_tally(tally);
+        for (actual, exact) in [(simulated.win, expected.win), (simulated.loss, expected.loss), (simulated.tie, expected.tie)] {
+            assert!((actual - exact).abs() < 0.01, "simulated {:?}, expected {:?}", simulated, expected)
+        }
+    }
+
+    #[test]
+    fn test_cli_is_well_formed(){
+        Cli::command().debug_assert()
+    }
+
+    /** ==== Play Game ==== **/
+
+    #[test]
+    fn test_play_game_with_seed(){
+        let args = Cli::parse_from(["rps", "Scissors", "--seed", "1"]);
+        assert_eq!(play_game(&args, &mut None), GameResult::UserWin)
+    }
+
+    #[test]
+    fn test_play_game_against_forced_move(){
+        let args = Cli::parse_from(["rps", "rock", "--opponent", "scissors"]);
+        assert_eq!(play_game(&args, &mut None), GameResult::UserWin);
+        let args = Cli::parse_from(["rps", "rock", "--opponent", "p", "--rounds", "3"]);
+        assert_eq!(play_rounds(&args, &mut None).tally, Tally { user_wins: 0, opponent_wins: 3, ties: 0 })
+    }
+
+    #[test]
+    fn test_play_game_lizard_spock_with_seed(){
+        let args = Cli::parse_from(["rps", "spock", "--seed", "4", "--variant", "lizard-spock"]);
+        assert_eq!(play_game(&args, &mut None), GameResult::OpponentWin)
+    }
+
+    #[test]
+    fn test_replaying_resolved_seed_gives_same_opponent_move(){
+        let seed = resolve_seed(None).to_string();
+        let args = Cli::parse_from(["rps", "rock", "--seed", &seed]);
+        let first = play_round(&args, opponent(&args, move_weights(&args), seeded_rng(&args)).as_mut(), Move::Rock, &[]);
+        let replay = play_round(&args, opponent(&args, move_weights(&args), seeded_rng(&args)).as_mut(), Move::Rock, &[]);
+        assert_eq!(first, replay)
+    }
+
+    #[test]
+    fn test_replaying_a_seed_at_each_difficulty_gives_same_opponent_moves(){
+        for difficulty in ["easy", "medium", "hard"] {
+            let args = Cli::parse_from(["rps", "rock", "--seed", "7", "--difficulty", difficulty]);
+            let play = || {
+                let mut opponent = opponent(&args, move_weights(&args), seeded_rng(&args));
+                let mut history = Vec::new();
+                for _ in 0..10 {
+                    let round = play_round(&args, opponent.as_mut(), Move::Rock, &history);
+                    history.push(round);
+                }
+                history
+            };
+            assert_eq!(play(), play(), "{}", difficulty)
+        }
+    }
+
+    #[test]
+    fn test_same_seed_phrase_gives_same_opponent_move(){
+        let args = Cli::parse_from(["rps", "rock", "--seed-phrase", "lucky cat"]);
+        let first = play_round(&args, opponent(&args, move_weights(&args), seeded_rng(&args)).as_mut(), Move::Rock, &[]);
+        let replay = play_round(&args, opponent(&args, move_weights(&args), seeded_rng(&args)).as_mut(), Move::Rock, &[]);
+        assert_eq!(first, replay)
+    }
+
+    #[test]
+    fn test_seed_phrase_plays_like_its_hash(){
+        let phrase = Cli::parse_from(["rps", "rock", "--seed-phrase", "lucky cat", "--rounds", "5"]);
+        let hashed = seed_from_phrase("lucky cat").to_string();
+        let numeric = Cli::parse_from(["rps", "rock", "--seed", &hashed, "--rounds", "5"]);
+        assert_eq!(play_rounds(&phrase, &mut None), play_rounds(&numeric, &mut None))
+    }
+
+    #[test]
+    fn test_resolve_seed_keeps_given_seed(){
+        assert_eq!(resolve_seed(Some(12345)), 12345)
+    }
+
+    #[test]
+    fn test_empty_and_unknown_moves_exit_differently(){
+        let empty = Variant::Classic.parse_move("").unwrap_err();
+        let unknown = Variant::Classic.parse_move("rok").unwrap_err();
+        assert_eq!(bad_move_exit_code(&empty), EXIT_NO_MOVE);
+        assert_eq!(bad_move_exit_code(&unknown), EXIT_INVALID_INPUT)
+    }
+
+    #[test]
+    fn test_exit_codes(){
+        assert_eq!(exit_code(&GameResult::UserWin), 0);
+        assert_eq!(exit_code(&GameResult::Tie), 1);
+        assert_eq!(exit_code(&GameResult::OpponentWin), 2)
+    }
+
+    #[test]
+    fn test_format_round_text(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Paper, result: GameResult::UserWin };
+        let style = Style::plain();
+        assert_eq!(format_round(&round, &style), "Opponent's move: Rock. You win!")
+    }
+
+    #[test]
+    fn test_format_round_text_colored(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Paper, result: GameResult::UserWin };
+        let style = Style { color: true, ..Style::plain() };
+        assert_eq!(format_round(&round, &style), format!("Opponent's move: Rock. {}", "You win!".green()))
+    }
+
+    #[test]
+    fn test_format_round_emoji(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Paper, result: GameResult::UserWin };
+        let style = Style { emoji: true, ..Style::plain() };
+        assert_eq!(format_round(&round, &style), "Opponent's move: ✊. You win!")
+    }
+
+    #[test]
+    fn test_format_round_plain_banner(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Paper, result: GameResult::UserWin };
+        let style = Style { banner: true, ..Style::plain() };
+        assert_eq!(format_round(&round, &style), "+-----------------+\n| You:      Paper |\n| Opponent: Rock  |\n|       WIN       |\n+-----------------+")
+    }
+
+    #[test]
+    fn test_format_round_colored_banner(){
+        let round = Round { opponent_move: Move::Scissors, user_move: Move::Paper, result: GameResult::OpponentWin };
+        let style = Style { color: true, banner: true, ..Style::plain() };
+        let banner = format_round(&round, &style);
+        assert!(banner.contains("│ Opponent: ✌️ Scissors │"));
+        assert!(banner.contains(&"LOSE".bold().red().to_string()))
+    }
+
+    #[test]
+    fn test_hand_art_is_recognizable(){
+        let markers = [(Move::Rock, "(_____)"), (Move::Paper, "_______)"), (Move::Scissors, "__________)"), (Move::Lizard, ")>"), (Move::Spock, "(__)")];
+        for (m, marker) in markers {
+            let art = HAND_ART[usize::from(u8::from(m))];
+            assert!(art.iter().any(|line| line.contains(marker)), "{:?}", m);
+            assert!(art.iter().all(|line| line.is_ascii()))
+        }
+        assert!(HAND_ART.iter().enumerate().all(|(i, a)| HAND_ART[..i].iter().all(|b| a != b)))
+    }
+
+    #[test]
+    fn test_format_round_ascii_art(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Paper, result: GameResult::UserWin };
+        let style = Style { ascii: true, ..Style::plain() };
+        let art = format_round(&round, &style);
+        assert!(art.starts_with("Your move: Paper\n    _______\n"));
+        assert!(art.contains("Opponent's move: Rock\n"));
+        assert!(art.ends_with("---.__(___)\nYou win!"))
+    }
+
+    #[test]
+    fn test_display_width_counts_emoji_twice(){
+        assert_eq!(display_width("✋ Paper"), 8);
+        assert_eq!(display_width("✌️"), 2)
+    }
+
+    #[test]
+    fn test_format_round_quiet(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Scissors, result: GameResult::OpponentWin };
+        let style = Style { color: true, emoji: true, quiet: true, ..Style::plain() };
+        assert_eq!(format_round(&round, &style), "OpponentWin")
+    }
+
+    #[test]
+    fn test_emoji_style_move_names(){
+        let style = Style::from_args(&Cli::parse_from(["rps", "rock", "--emoji"]));
+        assert_eq!(style.move_name(&Move::Rock), "✊");
+        assert_eq!(style.move_name(&Move::Paper), "✋");
+        assert_eq!(style.move_name(&Move::Scissors), "✌️")
+    }
+
+    #[test]
+    fn test_no_color_flag_disables_color(){
+        let style = Style::from_args(&Cli::parse_from(["rps", "rock", "--no-color"]));
+        assert!(!style.color)
+    }
+
+    #[test]
+    fn test_format_round_spanish(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Paper, result: GameResult::UserWin };
+        let style = Style { lang: Lang::Es, ..Style::plain() };
+        assert_eq!(format_round(&round, &style), "Jugada del rival: Piedra. ¡Ganaste!")
+    }
+
+    #[test]
+    fn test_format_round_verbose(){
+        let round = Round { opponent_move: Move::Spock, user_move: Move::Lizard, result: GameResult::UserWin };
+        let style = Style { verbose: true, ..Style::plain() };
+        assert_eq!(format_round(&round, &style), "Your move: Lizard. Opponent's move: Spock. You win!\nLizard poisons Spock, so you win.")
+    }
+
+    #[test]
+    fn test_format_round_json(){
+        let round = Round { opponent_move: Move::Rock, user_move: Move::Paper, result: GameResult::UserWin };
+        let style = Style { format: Format::Json, color: true, emoji: true, ..Style::plain() };
+        let json : serde_json::Value = serde_json::from_str(&format_round(&round, &style)).unwrap();
+        assert_eq!(json["opponent_move"], "rock");
+        assert_eq!(json["user_move"], "paper");
+        assert_eq!(json["result"], "UserWin")
+    }
+
+    #[test]
+    fn test_format_tally_json(){
+        let tally = Tally { user_wins: 3, opponent_wins: 1, ties: 1 };
+        let json : serde_json::Value = serde_json::from_str(&format_tally(&tally, Format::Json)).unwrap();
+        assert_eq!(json["user_wins"], 3);
+        assert_eq!(json["opponent_wins"], 1);
+        assert_eq!(json["ties"], 1)
+    }
+
+    /** ==== Rounds ==== **/
+
+    #[test]
+    fn test_play_rounds_with_seed(){
+        let args = Cli::parse_from(["rps", "rock", "--seed", "7", "--rounds", "5"]);
+        assert_eq!(play_rounds(&args, &mut None).tally, Tally { user_wins: 1, opponent_wins: 1, ties: 3 })
+    }
+
+    #[test]
+    fn test_round_three_replays_as_a_single_game(){
+        let derived = round_seed(7, 2).to_string();
+        let single = play_game(&Cli::parse_from(["rps", "rock", "--seed", &derived]), &mut None);
+        let rounds = play_rounds(&Cli::parse_from(["rps", "rock", "--seed", "7", "--rounds", "3"]), &mut None).tally;
+        let first_two = play_rounds(&Cli::parse_from(["rps", "rock", "--seed", "7", "--rounds", "2"]), &mut None).tally;
+        let mut expected = first_two;
+        expected.record(&single);
+        assert_eq!(rounds, expected)
+    }
+
+    #[test]
+    fn test_play_rounds_against_frequency_ai(){
+        // After the first round the AI always counters the repeated move.
+        let args = Cli::parse_from(["rps", "rock", "--seed", "1", "--rounds", "5", "--ai", "frequency"]);
+        assert_eq!(play_rounds(&args, &mut None).tally, Tally { user_wins: 0, opponent_wins: 5, ties: 0 })
+    }
+
+    #[test]
+    fn test_play_rounds_against_always_paper(){
+        let args = Cli::parse_from(["rps", "rock", "--rounds", "4", "--ai", "always-paper"]);
+        assert_eq!(play_rounds(&args, &mut None).tally, Tally { user_wins: 0, opponent_wins: 4, ties: 0 })
+    }
+
+    #[test]
+    fn test_play_rounds_follows_sequence(){
+        // Rock against rock, paper, scissors, then rock again.
+        let args = Cli::parse_from(["rps", "rock", "--rounds", "4", "--sequence", "rps"]);
+        assert_eq!(play_rounds(&args, &mut None).tally, Tally { user_wins: 1, opponent_wins: 1, ties: 2 })
+    }
+
+    #[test]
+    fn test_play_rounds_is_reproducible(){
+        let first = play_rounds(&Cli::parse_from(["rps", "paper", "--seed", "42", "--rounds", "10"]), &mut None);
+        let second = play_rounds(&Cli::parse_from(["rps", "paper", "--seed", "42", "--rounds", "10"]), &mut None);
+        assert_eq!(first, second)
+    }
+
+    #[test]
+    fn test_format_scoreboard(){
+        let style = Style::plain();
+        let rounds = [Round::new(Move::Rock, Move::Paper), Round::new(Move::Rock, Move::Scissors), Round::new(Move::Paper, Move::Scissors)];
+        assert_eq!(
+            format_scoreboard(&rounds, &style),
+            "Round 1: you Rock vs Paper — loss\nRound 2: you Rock vs Scissors — win\nRound 3: you Paper vs Scissors — loss\nMargin: the opponent by 1"
+        )
+    }
+
+    /** ==== Dry run ==== **/
+
+    #[test]
+    fn test_dry_run_accepts_valid_inputs(){
+        let args = Cli::parse_from(["rps", "lizard", "--variant", "lizard-spock", "--weights", "1,1,1,1,2", "--sequence", "rpk", "--dry-run"]);
+        assert_eq!(validate(&args), Ok(()))
+    }
+
+    #[test]
+    fn test_dry_run_reports_bad_inputs(){
+        let weights = Cli::parse_from(["rps", "rock", "--weights", "1,2", "--dry-run"]);
+        assert_eq!(validate(&weights), Err(String::from("Invalid weights: expected 3 weights, found 2")));
+        let sequence = Cli::parse_from(["rps", "rock", "--sequence", "rpx", "--dry-run"]);
+        assert!(validate(&sequence).unwrap_err().starts_with("Invalid sequence \"rpx\""));
+        let lizard = Cli::parse_from(["rps", "lizard", "--dry-run"]);
+        assert_eq!(validate(&lizard), Err(String::from("Invalid move: \"lizard\"")))
+    }
+
+    #[test]
+    fn test_shuffle_bag_cannot_replay_ties(){
+        for flag in [&["--no-ties"][..], &["--tie-policy", "replay"]] {
+            let args = Cli::parse_from(["rps", "rock", "--ai", "shuffle-bag"].iter().chain(flag));
+            assert!(check_tie_replays(&args).unwrap_err().starts_with("--ai shuffle-bag can't replay ties"));
+        }
+        let scripted = Cli::parse_from(["rps", "rock", "--ai", "shuffle-bag", "--sequence", "rp", "--no-ties"]);
+        assert_eq!(check_tie_replays(&scripted), Ok(()))
+    }
+
+    /** ==== Opponent file ==== **/
+
+    #[test]
+    fn test_opponent_file_skips_blank_lines(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("moves.txt");
+        fs::write(&path, "rock\n\n  paper \ns\n").unwrap();
+        let args = Cli::parse_from(["rps", "rock", "--rounds", "3"]);
+        assert_eq!(read_opponent_file(&args, &path), Ok(vec![Move::Rock, Move::Paper, Move::Scissors]))
+    }
+
+    #[test]
+    fn test_opponent_file_reports_the_bad_line(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("moves.txt");
+        fs::write(&path, "rock\n\nstone\n").unwrap();
+        let args = Cli::parse_from(["rps", "rock"]);
+        assert_eq!(read_opponent_file(&args, &path), Err(format!("{}: line 3: Invalid move: \"stone\"", path.display())))
+    }
+
+    #[test]
+    fn test_opponent_file_must_cover_a_fixed_length_game(){
+        let moves = vec![Move::Rock, Move::Paper];
+        let path = Path::new("moves.txt");
+        let rounds = Cli::parse_from(["rps", "rock", "--rounds", "3", "--opponent-file", "moves.txt"]);
+        assert_eq!(check_file_length(&rounds, path, moves.clone()), Err(String::from("moves.txt: 2 moves for a game of up to 3 rounds")));
+        let best_of = Cli::parse_from(["rps", "rock", "--best-of", "3", "--opponent-file", "moves.txt"]);
+        assert!(check_file_length(&best_of, path, moves.clone()).is_err());
+        // How long these go on isn't known until they are played.
+        for argv in [&["rps", "--interactive"][..], &["rps", "rock", "--first-to", "3"], &["rps", "rock", "--rounds", "3", "--no-ties"]] {
+            assert_eq!(check_file_length(&Cli::parse_from(argv), path, moves.clone()), Ok(moves.clone()));
+        }
+    }
+
+    /** ==== Best of ==== **/
+
+    #[test]
+    fn test_best_of_stops_once_clinched(){
+        // Always paper wins every round against rock: three rounds, not five.
+        let args = Cli::parse_from(["rps", "rock", "--ai", "always-paper", "--best-of", "5"]);
+        assert_eq!(play_best_of(&args, 5, &mut None).tally, Tally { user_wins: 0, opponent_wins: 3, ties: 0 })
+    }
+
+    #[test]
+    fn test_best_of_with_seed_ends_early(){
+        // The opponent reaches four wins after six rounds, one of them a tie.
+        let args = Cli::parse_from(["rps", "rock", "--seed", "10", "--best-of", "7"]);
+        assert_eq!(play_best_of(&args, 7, &mut None).tally, Tally { user_wins: 1, opponent_wins: 4, ties: 1 })
+    }
+
+    #[test]
+    fn test_best_of_gives_up_on_endless_ties(){
+        let args = Cli::parse_from(["rps", "rock", "--ai", "always-rock", "--best-of", "3"]);
+        assert_eq!(play_best_of(&args, 3, &mut None).tally, Tally { user_wins: 0, opponent_wins: 0, ties: MAX_SERIES_ROUNDS })
+    }
+
+    /** ==== Sudden death ==== **/
+
+    #[test]
+    fn test_sudden_death_settles_a_level_series(){
+        // The four rounds end 1-1; sudden death ties once, then the user wins.
+        // Its rounds stay out of the tally.
+        let level = Cli::parse_from(["rps", "rock", "--seed", "1", "--rounds", "4"]);
+        assert_eq!(play_rounds(&level, &mut None), Series { tally: Tally { user_wins: 1, opponent_wins: 1, ties: 2 }, sudden_death: None });
+        let settled = Cli::parse_from(["rps", "rock", "--seed", "1", "--rounds", "4", "--sudden-death"]);
+        assert_eq!(play_rounds(&settled, &mut None), Series { tally: Tally { user_wins: 1, opponent_wins: 1, ties: 2 }, sudden_death: Some(GameResult::UserWin) })
+    }
+
+    #[test]
+    fn test_sudden_death_only_when_level(){
+        let args = Cli::parse_from(["rps", "rock", "--seed", "4", "--first-to", "3", "--sudden-death"]);
+        assert_eq!(play_first_to(&args, 3, &mut None), Series { tally: Tally { user_wins: 3, opponent_wins: 2, ties: 6 }, sudden_death: None })
+    }
+
+    #[test]
+    fn test_format_sudden_death(){
+        assert_eq!(format_sudden_death(&GameResult::UserWin, 2), "You won the sudden death in round 2");
+        assert_eq!(format_sudden_death(&GameResult::OpponentWin, 1), "The opponent won the sudden death in round 1")
+    }
+
+    /** ==== First to ==== **/
+
+    #[test]
+    fn test_first_to_ignores_ties(){
+        // Six ties along the way, but only the third win ends it.
+        let args = Cli::parse_from(["rps", "rock", "--seed", "4", "--first-to", "3"]);
+        assert_eq!(play_first_to(&args, 3, &mut None).tally, Tally { user_wins: 3, opponent_wins: 2, ties: 6 })
+    }
+
+    #[test]
+    fn test_move_counts_with_seed(){
+        let args = Cli::parse_from(["rps", "--batch"]);
+        let mut opponent = random_opponent(25);
+        let rounds : Vec<Round> = (0..10).map(|_| Round::new(Move::Rock, opponent.next_move(&[]))).collect();
+        assert_eq!(format_move_counts(&rounds, Variant::Classic, &Style::from_args(&args)), "Opponent: Rock×3 Paper×4 Scissors×3")
+    }
+
+    #[test]
+    fn test_move_counts_json_include_unplayed_moves(){
+        let args = Cli::parse_from(["rps", "--batch", "--format", "json"]);
+        let rounds = [Round::new(Move::Rock, Move::Spock)];
+        let json : serde_json::Value = serde_json::from_str(&format_move_counts(&rounds, Variant::LizardSpock, &Style::from_args(&args))).unwrap();
+        assert_eq!(json["opponent_moves"]["spock"], 1);
+        assert_eq!(json["opponent_moves"]["rock"], 0)
+    }
+
+    #[test]
+    fn test_streaks_against_scripted_opponent(){
+        // The opponent's cycle gives win, win, win, tie, tie, loss.
+        let args = Cli::parse_from(["rps", "rock", "--rounds", "6", "--sequence", "sssrrp", "--quiet"]);
+        let mut scripted = sequence(&args).unwrap();
+        let mut history = Vec::new();
+        for _ in 0..args.rounds {
+            let round = play_round(&args, scripted.as_mut(), Move::Rock, &history);
+            history.push(round);
+        }
+        assert_eq!(Streaks::of(&history), Streaks { wins: 3, losses: 1, ties: 2 })
+    }
+
+    #[test]
+    fn test_format_streaks(){
+        let streaks = Streaks { wins: 3, losses: 1, ties: 0 };
+        assert_eq!(format_streaks(&streaks, Format::Text), "Longest streaks: 3 wins, 1 loss, 0 ties");
+        let json : serde_json::Value = serde_json::from_str(&format_streaks(&streaks, Format::Json)).unwrap();
+        assert_eq!(json["longest_streaks"]["wins"], 3)
+    }
+
+    #[test]
+    fn test_format_series(){
+        let won = Tally { user_wins: 4, opponent_wins: 2, ties: 1 };
+        assert_eq!(format_series(&won, Format::Text), "You clinched the series 4-2");
+        let lost = Tally { user_wins: 1, opponent_wins: 2, ties: 0 };
+        assert_eq!(format_series(&lost, Format::Text), "The opponent clinched the series 2-1")
+    }
+
+    /** ==== Predict ==== **/
+
+    #[test]
+    fn test_format_prediction_text(){
+        let chances = [(Move::Rock, 0.2), (Move::Paper, 0.6), (Move::Scissors, 0.2)];
+        assert_eq!(format_prediction(&chances, Format::Text), "Chance of winning: Rock 20%, Paper 60%, Scissors 20%")
+    }
+
+    #[test]
+    fn test_probabilities_against_uniform_opponent(){
+        let args = Cli::parse_from(["rps", "rock", "--probabilities"]);
+        let rates = opponent(&args, move_weights(&args), seeded_rng(&args)).move_distribution(&[]).map(|d| expected_rates(&[(Move::Rock, 1.0)], &d));
+        assert_eq!(format_probabilities(rates, Format::Text), "Win 33.3% / Tie 33.3% / Lose 33.3%")
+    }
 
-use clap::{Parser};
-use rand::{
-    distributions::{Distribution, Standard},
-    Rng, SeedableRng, prelude::StdRng,
-};
+    #[test]
+    fn test_probabilities_against_weighted_opponent(){
+        // Rock three times in five: rock mostly ties, paper mostly wins.
+        let args = Cli::parse_from(["rps", "paper", "--weights", "3,1,1", "--probabilities"]);
+        let rates = opponent(&args, move_weights(&args), seeded_rng(&args)).move_distribution(&[]).map(|d| expected_rates(&[(Move::Paper, 1.0)], &d));
+        assert_eq!(format_probabilities(rates, Format::Text), "Win 60.0% / Tie 20.0% / Lose 20.0%");
+        assert_eq!(format_probabilities(None, Format::Json), r#"{"probabilities":null}"#)
+    }
 
-/**
- * A basic command line rock paper scissors game.
- */
+    #[test]
+    fn test_format_prediction_json(){
+        let chances = [(Move::Rock, 0.0), (Move::Paper, 0.0), (Move::Scissors, 1.0)];
+        assert_eq!(
+            format_prediction(&chances, Format::Json),
+            r#"{"win_probabilities":{"paper":0.0,"rock":0.0,"scissors":1.0}}"#
+        )
+    }
 
-#[derive(Parser)]
-#[clap(author, version, about, long_about = None)]
-struct Cli {
-    pattern: String,
-    seed: Option<u64>
-}
+    #[test]
+    fn test_format_hint(){
+        assert_eq!(format_hint(Some(Move::Paper), Format::Text), "Hint: play Paper");
+        assert_eq!(format_hint(None, Format::Text), "Hint: every move is equally good");
+        assert_eq!(format_hint(Some(Move::Paper), Format::Json), r#"{"hint":"paper"}"#)
+    }
 
-#[derive(Debug, PartialEq)]
-enum Move {
-    Rock,
-    Paper,
-    Scissors
-}
+    /** ==== Dramatic ==== **/
+
+    #[test]
+    fn test_countdown_text(){
+        let mut out = Vec::new();
+        countdown(&mut out, Duration::ZERO).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "Rock... Paper... Scissors... Shoot!\n")
+    }
+
+    /** ==== No ties ==== **/
+
+    #[test]
+    fn test_reroll_ties_counts_rerolls(){
+        let mut draws = [Move::Rock, Move::Rock, Move::Paper].into_iter();
+        assert_eq!(reroll_ties(|| draws.next().unwrap(), |m| *m == Move::Rock), Some((Move::Paper, 2)))
+    }
+
+    #[test]
+    fn test_reroll_ties_gives_up(){
+        assert_eq!(reroll_ties(|| Move::Rock, |m| *m == Move::Rock), None)
+    }
+
+    #[test]
+    fn test_no_ties_rerolls_seeded_tie(){
+        // Seed 13 draws Rock first, a tie that gets re-rolled into a loss.
+        assert_eq!(play_game(&Cli::parse_from(["rps", "rock", "--seed", "13"]), &mut None), GameResult::Tie);
+        assert_eq!(play_game(&Cli::parse_from(["rps", "rock", "--seed", "13", "--no-ties"]), &mut None), GameResult::OpponentWin)
+    }
+
+    /** ==== Seed range ==== **/
+
+    #[test]
+    fn test_parse_seed_range(){
+        assert_eq!(parse_seed_range("1..4"), Ok(1..=3));
+        assert_eq!(parse_seed_range(" 1 ..= 4 "), Ok(1..=4));
+        assert_eq!(parse_seed_range("7..=7"), Ok(7..=7));
+        assert_eq!(parse_seed_range("5..5"), Err(String::from("5..5 is empty")));
+        assert_eq!(parse_seed_range("0..0"), Err(String::from("0..0 is empty")));
+        assert_eq!(parse_seed_range("1..x"), Err(String::from("\"x\" is not a seed")));
+        assert!(parse_seed_range("100").is_err())
+    }
 
-impl Distribution<Move> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Move {
-        match rng.gen_range(0..=2) {
-            0 => Move::Rock,
-            1 => Move::Paper,
-            _ => Move::Scissors,
+    #[test]
+    fn test_seed_sweep_counts(){
+        // ChaCha8 draws Scissors, Paper and Rock first for seeds 0, 1 and 2.
+        let args = Cli::parse_from(["rps", "rock", "--seed-range", "0..=2"]);
+        let rounds = sweep_seeds(&args, &(0..=2));
+        let opponent_moves : Vec<Move> = rounds.iter().map(|round| round.opponent_move).collect();
+        assert_eq!(opponent_moves, [Move::Scissors, Move::Paper, Move::Rock]);
+        let mut tally = Tally::default();
+        for round in &rounds {
+            tally.record(&round.result);
         }
+        assert_eq!(format_seed_sweep(&(0..=2), &tally, Format::Text), "Seeds 0..=2: 1 win, 1 tie, 1 loss")
+    }
+
+    /** ==== Simulate ==== **/
+
+    #[test]
+    fn test_simulation_is_fair(){
+        let tally = simulate(&Cli::parse_from(["rps", "rock", "--seed", "7"]), 30000);
+        assert_eq!(tally.user_wins + tally.opponent_wins + tally.ties, 30000);
+        assert!((tally.win_rate() - 1.0 / 3.0).abs() < 0.01, "win rate {}", tally.win_rate())
+    }
+
+    #[test]
+    fn test_simulation_with_random_user_moves(){
+        let args = Cli::parse_from(["rps", "--simulate", "30000", "--seed", "7", "--variant", "lizard-spock"]);
+        let tally = simulate(&args, 30000);
+        assert!((tally.win_rate() - 0.4).abs() < 0.01, "win rate {}", tally.win_rate())
+    }
+
+    #[test]
+    fn test_simulation_is_reproducible(){
+        let args = Cli::parse_from(["rps", "--simulate", "500", "--seed", "42", "--ai", "win-stay-lose-shift"]);
+        assert_eq!(simulate(&args, 500), simulate(&args, 500))
+    }
+
+    #[test]
+    fn test_simulation_without_delay_does_not_sleep(){
+        let args = Cli::parse_from(["rps", "rock", "--simulate", "3000", "--seed", "7", "--delay-ms", "0"]);
+        let started = std::time::Instant::now();
+        let tally = simulate(&args, 3000);
+        assert!(started.elapsed() < Duration::from_secs(1), "took {:?}", started.elapsed());
+        assert_eq!(tally, simulate(&Cli::parse_from(["rps", "rock", "--simulate", "3000", "--seed", "7"]), 3000));
+        assert_eq!(tally.user_wins + tally.opponent_wins + tally.ties, 3000)
+    }
+
+    #[test]
+    fn test_format_running_total(){
+        let style = Style::plain();
+        let tally = Tally { user_wins: 1, opponent_wins: 2, ties: 0 };
+        assert_eq!(
+            format_running_total(3, &Round::new(Move::Rock, Move::Paper), &tally, &style),
+            "Game 3: you Rock vs Paper — loss. You 1 - Opponent 2 (0 ties)"
+        )
+    }
+
+    #[test]
+    fn test_format_simulation(){
+        let tally = Tally { user_wins: 1, opponent_wins: 2, ties: 1 };
+        assert_eq!(format_simulation(&tally, Format::Text), "Simulated 4 games: 25.0% wins, 50.0% losses, 25.0% ties")
+    }
+
+    /** ==== Nash check ==== **/
+
+    #[test]
+    fn test_uniform_user_against_biased_opponent(){
+        let args = Cli::parse_from(["rps", "--simulate", "30000", "--nash-check", "--seed", "7", "--weights", "3,1,1"]);
+        let expected = expected_simulation(&args);
+        assert!(expected.value().abs() < 1e-9);
+        assert_rates_close(&simulate(&args, 30000), &expected)
+    }
+
+    #[test]
+    fn test_biased_user_against_biased_opponent(){
+        let args = Cli::parse_from(["rps", "--simulate", "30000", "--nash-check", "--seed", "7", "--user-weights", "1,2,0", "--weights", "2,1,1"]);
+        let expected = expected_simulation(&args);
+        assert!(expected.value() > 0.0);
+        assert_rates_close(&simulate(&args, 30000), &expected)
+    }
+
+    #[test]
+    fn test_format_nash_check(){
+        let tally = Tally { user_wins: 1, opponent_wins: 2, ties: 1 };
+        let expected = OutcomeRates { win: 0.25, loss: 0.25, tie: 0.5 };
+        assert_eq!(
+            format_nash_check(&tally, &expected, Format::Text),
+            "Simulated 4 games: 25.0% wins, 50.0% losses, 25.0% ties, expected value -0.250\nExact: 25.0% wins, 25.0% losses, 50.0% ties, expected value +0.000"
+        )
     }
-}
 
-impl PartialOrd for Move {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Move::Rock, Move::Rock) => Some(Ordering::Equal),
-            (Move::Rock, Move::Paper) => Some(Ordering::Less),
-            (Move::Rock, Move::Scissors) => Some(Ordering::Greater),
-            (Move::Paper, Move::Rock) => Some(Ordering::Greater),
-            (Move::Paper, Move::Paper) => Some(Ordering::Equal),
-            (Move::Paper, Move::Scissors) => Some(Ordering::Less),
-            (Move::Scissors, Move::Rock) => Some(Ordering::Less),
-            (Move::Scissors, Move::Paper) => Some(Ordering::Greater),
-            (Move::Scissors, Move::Scissors) => Some(Ordering::Equal),
+    /** ==== List moves ==== **/
+
+    #[test]
+    fn test_list_moves_classic(){
+        assert_eq!(list_moves(Variant::Classic), "rock (r), paper (p), scissors (s)")
+    }
+
+    #[test]
+    fn test_list_moves_lizard_spock(){
+        assert_eq!(list_moves(Variant::LizardSpock), "rock (r), paper (p), scissors, lizard (l), spock (sp, k)")
+    }
+
+    /** ==== Case-insensitive values ==== **/
+
+    #[test]
+    fn test_enum_values_ignore_case(){
+        let args = Cli::parse_from(["rps", "rock", "--ai", "Frequency", "--variant", "Lizard-Spock", "--format", "JSON", "--lang", "ES"]);
+        assert_eq!(args.ai, Strategy::Frequency);
+        assert_eq!(args.variant, Variant::LizardSpock);
+        assert_eq!(args.format, Format::Json);
+        assert_eq!(args.lang, Lang::Es)
+    }
+
+    #[test]
+    fn test_invalid_enum_value_lists_options(){
+        let err = Cli::try_parse_from(["rps", "rock", "--format", "yaml"]).err().unwrap();
+        assert!(err.to_string().contains("[possible values: text, json, csv]"), "{}", err)
+    }
+
+    /** ==== Commit-reveal ==== **/
+
+    #[test]
+    fn test_check_reveals_scores_honest_players(){
+        let values = published(&[&Reveal::new(Move::Rock, String::from("salt1")), &Reveal::new(Move::Scissors, String::from("salt2"))]);
+        assert_eq!(check_reveals(&values, Variant::Classic), Ok(Round::new(Move::Rock, Move::Scissors)))
+    }
+
+    #[test]
+    fn test_check_reveals_catches_a_changed_move(){
+        let honest = Reveal::new(Move::Rock, String::from("salt2"));
+        let commitment = honest.commitment();
+        let tampered = Reveal { chosen: Move::Paper, ..honest };
+        let mut values = published(&[&Reveal::new(Move::Rock, String::from("salt1"))]);
+        values.extend([commitment, tampered.to_string()]);
+        assert_eq!(check_reveals(&values, Variant::Classic), Err(String::from("Player 2: The move doesn't match its commitment")))
+    }
+
+    #[test]
+    fn test_check_reveals_rejects_a_fresh_reveal(){
+        // Player 2 committed to scissors, saw player 1's rock and made a
+        // new reveal for paper. It matches its own commitment, just not
+        // the one player 2 published.
+        let first = Reveal::new(Move::Rock, String::from("salt1"));
+        let second = Reveal::new(Move::Scissors, String::from("salt2"));
+        let fresh = Reveal::new(Move::Paper, String::from("salt3"));
+        let values = [first.commitment(), first.to_string(), second.commitment(), fresh.to_string()];
+        assert_eq!(check_reveals(&values, Variant::Classic), Err(String::from("Player 2: The move doesn't match its commitment")))
+    }
+
+    #[test]
+    fn test_check_reveals_follows_variant(){
+        let values = published(&[&Reveal::new(Move::Spock, String::from("salt1")), &Reveal::new(Move::Rock, String::from("salt2"))]);
+        assert!(check_reveals(&values, Variant::Classic).is_err());
+        assert_eq!(check_reveals(&values, Variant::LizardSpock).map(|round| round.result), Ok(GameResult::UserWin))
+    }
+
+    fn published(reveals : &[&Reveal]) -> Vec<String> {
+        reveals.iter().flat_map(|reveal| [reveal.commitment(), reveal.to_string()]).collect()
+    }
+
+    /** ==== Max rounds ==== **/
+
+    #[test]
+    fn test_max_rounds_stops_the_session(){
+        let input = "rock\nrock\nrock\nrock\nrock\n".as_bytes();
+        let args = Cli::parse_from(["rps", "--interactive", "--max-rounds", "3", "--quiet"]);
+        assert_eq!(play_interactive(input, &args, &mut ConstantOpponent(Move::Scissors), &mut None, &mut None, &AtomicBool::new(false)), Tally { user_wins: 3, opponent_wins: 0, ties: 0 })
+    }
+
+    #[test]
+    fn test_quit_before_max_rounds(){
+        let input = "rock\nquit\nrock\n".as_bytes();
+        let args = Cli::parse_from(["rps", "--interactive", "--max-rounds", "3", "--quiet"]);
+        assert_eq!(play_interactive(input, &args, &mut ConstantOpponent(Move::Scissors), &mut None, &mut None, &AtomicBool::new(false)).played(), 1)
+    }
+
+    #[test]
+    fn test_zero_max_rounds_is_unlimited(){
+        let input = "rock\n".repeat(20);
+        let args = Cli::parse_from(["rps", "--interactive", "--max-rounds", "0", "--quiet"]);
+        assert_eq!(play_interactive(io::Cursor::new(input), &args, &mut ConstantOpponent(Move::Scissors), &mut None, &mut None, &AtomicBool::new(false)).played(), 20)
+    }
+
+    /** ==== Split output ==== **/
+
+    #[test]
+    fn test_split_round(){
+        let style = Style { split: true, ..Style::plain() };
+        let round = Round::new(Move::Rock, Move::Paper);
+        assert_eq!(split_round(&round, &style), (String::from("Opponent's move: Paper."), String::from("You lose!")))
+    }
+
+    #[test]
+    fn test_split_round_verbose_keeps_explanation_apart(){
+        let style = Style { verbose: true, split: true, ..Style::plain() };
+        let (preamble, result) = split_round(&Round::new(Move::Paper, Move::Rock), &style);
+        assert_eq!(preamble, "Your move: Paper. Opponent's move: Rock.\nPaper covers Rock, so you win.");
+        assert_eq!(result, "You win!")
+    }
+
+    /** ==== House edge ==== **/
+
+    #[test]
+    fn test_parse_probability(){
+        assert_eq!(parse_probability("0.2"), Ok(0.2));
+        assert!(parse_probability("1.5").is_err());
+        assert!(parse_probability("lots").is_err())
+    }
+
+    #[test]
+    fn test_full_house_edge_always_wins_for_the_house(){
+        for seed in ["1", "2", "3"] {
+            let args = Cli::parse_from(["rps", "rock", "--house-edge", "1", "--seed", seed, "--quiet"]);
+            assert_eq!(play_game(&args, &mut None), GameResult::OpponentWin)
         }
     }
-}
 
-impl FromStr for Move {
-    type Err = clap::ErrorKind;
+    /** ==== Auto ==== **/
+
+    #[test]
+    fn test_parse_matchup(){
+        assert_eq!(parse_matchup("frequency:Random"), Ok((Strategy::Frequency, Strategy::Random)));
+        assert!(parse_matchup("frequency").is_err());
+        assert!(parse_matchup("frequency:smart").unwrap_err().contains("\"smart\""))
+    }
+
+    #[test]
+    fn test_auto_game_between_fixed_strategies(){
+        let args = Cli::parse_from(["rps", "--auto", "always-paper:always-rock", "--rounds", "4", "--seed", "3", "--quiet"]);
+        assert_eq!(play_auto(&args, (Strategy::AlwaysPaper, Strategy::AlwaysRock)), Tally { user_wins: 4, opponent_wins: 0, ties: 0 })
+    }
+
+    #[test]
+    fn test_auto_sides_learn_from_their_own_view(){
+        // Frequency opens at random, then counters the rock it has seen,
+        // whichever side it plays on.
+        let args = Cli::parse_from(["rps", "--auto", "frequency:always-rock", "--rounds", "5", "--seed", "3", "--quiet"]);
+        assert_eq!(play_auto(&args, (Strategy::Frequency, Strategy::AlwaysRock)), Tally { user_wins: 4, opponent_wins: 0, ties: 1 });
+        assert_eq!(play_auto(&args, (Strategy::AlwaysRock, Strategy::Frequency)), Tally { user_wins: 1, opponent_wins: 4, ties: 0 })
+    }
+
+    #[test]
+    fn test_format_auto_round(){
+        let args = Cli::parse_from(["rps", "--auto", "frequency:random", "--no-color"]);
+        let round = Round::new(Move::Rock, Move::Paper);
+        assert_eq!(
+            format_auto_round(1, (Strategy::Frequency, Strategy::Random), &round, &Style::from_args(&args)),
+            "Round 1: frequency Rock vs random Paper — random wins"
+        )
+    }
+
+    /** ==== Compare ==== **/
+
+    #[test]
+    fn test_compare_always_paper_sweeps_always_rock(){
+        let args = Cli::parse_from(["rps", "--simulate", "1000", "--compare", "always-rock:always-paper", "--seed", "5"]);
+        let tally = compare(&args, (Strategy::AlwaysRock, Strategy::AlwaysPaper), 1000);
+        assert_eq!(tally, Tally { user_wins: 0, opponent_wins: 1000, ties: 0 });
+        assert_eq!(
+            format_comparison((Strategy::AlwaysRock, Strategy::AlwaysPaper), &tally, Format::Text),
+            "always-rock won 0.0% of 1000 games, always-paper 100.0% (0.0% ties)\nalways-paper is ahead by 100.0 ± 0.0 points (95% confidence)"
+        )
+    }
+
+    #[test]
+    fn test_comparison_margin(){
+        // Scores of +1, -1 and 0, 100 games each: a lead of 0 with a
+        // variance of 2/3.
+        let (lead, margin) = comparison_margin(&Tally { user_wins: 100, opponent_wins: 100, ties: 100 });
+        assert!(lead.abs() < 1e-9);
+        assert!((margin - 1.96 * (2.0f64 / 3.0 / 300.0).sqrt() * 100.0).abs() < 1e-9);
+        assert_eq!(comparison_margin(&Tally::default()), (0.0, 0.0))
+    }
+
+    #[test]
+    fn test_close_comparison_is_not_called(){
+        let tally = Tally { user_wins: 34, opponent_wins: 32, ties: 34 };
+        assert!(format_comparison((Strategy::Frequency, Strategy::Random), &tally, Format::Text)
+            .ends_with("Too close to call: frequency leads by 2.0 ± 15.9 points (95% confidence)"))
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_ascii_lowercase().as_str() {
-            "rock" => Ok(Move::Rock),
-            "paper" => Ok(Move::Paper),
-            "scissors" => Ok(Move::Scissors),
-            _ => Err(clap::ErrorKind::InvalidValue),
+    /** ==== Conflicts ==== **/
+
+    #[test]
+    fn test_each_conflicting_pair_is_reported(){
+        for pair in CONFLICTS {
+            let argv = conflicting_argv(pair);
+            let matches = Cli::command().try_get_matches_from(&argv).unwrap_or_else(|err| panic!("{:?}: {}", argv, err));
+            assert_eq!(conflicts(&matches), [*pair], "{:?}", argv)
         }
     }
-}
-#[derive(Debug, PartialEq)]
-enum GameResult {
-    UserWin,
-    OpponentWin,
-    Tie
-}
 
-fn main() {
-    // Initalize cli parser.
-    let args = Cli::parse();
+    #[test]
+    fn test_defaults_do_not_conflict(){
+        // --ai, --rounds and --variant all have defaults.
+        assert!(conflicts(&Cli::command().get_matches_from(["rps", "--two-player"])).is_empty());
+        assert!(conflicts(&Cli::command().get_matches_from(["rps", "rock", "--best-of", "3"])).is_empty());
+        assert!(conflicts(&Cli::command().get_matches_from(["rps", "rock", "--rules", "rules.toml"])).is_empty())
+    }
 
-    match play_game(args) {
-            GameResult::UserWin => println!("You win!"),
-            GameResult::Tie => println!("Tie"),
-            GameResult::OpponentWin => println!("You lose!"),
+    #[test]
+    fn test_format_conflicts_lists_every_pair(){
+        let message = format_conflicts(&[("quiet", "verbose"), ("best-of", "rounds")]);
+        assert_eq!(message, "Conflicting options: --quiet and --verbose; --best-of and --rounds");
+        assert_eq!(format_conflicts(&[("pattern", "user-weights")]), "Conflicting options: a move and --user-weights")
     }
 
-}
+    fn conflicting_argv(pair : &(&str, &str)) -> Vec<String> {
+        let mut ids = vec![pair.0, pair.1];
+        for id in [pair.0, pair.1] {
+            let needed = match id {
+                "nash-check" | "compare" => Some("simulate"),
+                "challenge" | "hotkeys" => Some("interactive"),
+                _ => None,
+            };
+            if let Some(needed) = needed.filter(|needed| !ids.contains(needed)) {
+                ids.push(needed);
+            }
+        }
+        // A move only conflicts with --user-weights, so every case can pass
+        // one to keep clap from asking for it.
+        if !ids.contains(&"pattern") {
+            ids.push("pattern");
+        }
+        let mut argv = vec![String::from("rps")];
+        argv.extend(ids.into_iter().flat_map(example_args));
+        argv
+    }
 
-fn play_game(args : Cli) -> GameResult{
+    fn example_args(id : &str) -> Vec<String> {
+        let values : &[&str] = match id {
+            "pattern" => return vec![String::from("rock")],
+            "seed" => &["1"],
+            "seed-phrase" => &["lucky cat"],
+            "opponent" => &["rock"],
+            "ai" => &["markov"],
+            "sequence" => &["rps"],
+            "best-of" | "first-to" => &["3"],
+            "rounds" => &["5"],
+            "rules" => &["rules.toml"],
+            "variant" => &["classic"],
+            "user-weights" => &["1,1,1"],
+            "auto" | "compare" => &["random:random"],
+            "house-edge" => &["0.2"],
+            "simulate" => &["10"],
+            "replay" => &["in.csv"],
+            "log" => &["game.csv"],
+            "reveal" => &["a", "b", "c", "d"],
+            "opponent-file" => &["moves.txt"],
+            "input-socket" => &["rps.sock"],
+            "seed-range" => &["1..10"],
+            "timeout" => &["5"],
+            "tie-policy" => &["user"],
+            "difficulty" => &["hard"],
+            _ => &[],
+        };
+        let mut args = vec![format!("--{}", id)];
+        args.extend(values.iter().map(|value| value.to_string()));
+        args
+    }
 
-    let our_move : Move = match Move::from_str(&args.pattern) {
-        Ok(x) => x,
-        Err(_) => {
-            eprintln!("Invalid move");
-            process::exit(1)
+    /** ==== Config file ==== **/
+
+    #[test]
+    fn test_config_fills_in_defaults(){
+        let matches = Cli::command().get_matches_from(["rps", "rock"]);
+        let mut args = Cli::from_arg_matches(&matches).unwrap();
+        let (config, _) = Config::parse("variant = \"lizard-spock\"\nai = \"markov\"\nlang = \"es\"\ncolor = false\n");
+        apply_config(&mut args, &matches, &config);
+        assert_eq!(args.variant, Variant::LizardSpock);
+        assert_eq!(args.ai, Strategy::Markov);
+        assert_eq!(args.lang, Lang::Es);
+        assert!(args.no_color)
+    }
+
+    #[test]
+    fn test_flags_override_config(){
+        let matches = Cli::command().get_matches_from(["rps", "rock", "--ai", "frequency", "--variant", "classic"]);
+        let mut args = Cli::from_arg_matches(&matches).unwrap();
+        let (config, _) = Config::parse("variant = \"lizard-spock\"\nai = \"markov\"\n");
+        apply_config(&mut args, &matches, &config);
+        assert_eq!(args.variant, Variant::Classic);
+        assert_eq!(args.ai, Strategy::Frequency)
+    }
+
+    #[test]
+    fn test_config_gives_way_to_conflicting_flags(){
+        let matches = Cli::command().get_matches_from(["rps", "rock", "--opponent", "paper", "--rules", "rules.toml"]);
+        let mut args = Cli::from_arg_matches(&matches).unwrap();
+        let (config, _) = Config::parse("variant = \"lizard-spock\"\nai = \"markov\"\nlang = \"es\"\n");
+        apply_config(&mut args, &matches, &config);
+        assert_eq!((args.variant, args.ai, args.lang), (Variant::Classic, Strategy::Random, Lang::Es))
+    }
+
+    #[test]
+    fn test_config_can_force_color(){
+        let matches = Cli::command().get_matches_from(["rps", "rock"]);
+        let mut args = Cli::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, &matches, &Config::parse("color = true\n").0);
+        assert!(args.force_color && !args.no_color)
+    }
+
+    /** ==== Self-test ==== **/
+
+    #[test]
+    fn test_builtin_rules_pass_self_test(){
+        assert_eq!(self_test(&Cli::parse_from(["rps", "--self-test"])), Vec::<String>::new())
+    }
+
+    /** ==== Version detail ==== **/
+
+    #[test]
+    fn test_version_detail_lists_every_strategy(){
+        let detail = version_detail(Variant::Classic);
+        for strategy in Strategy::value_variants() {
+            assert!(detail.contains(&value_name(strategy)), "{} is missing", value_name(strategy))
         }
-    };
+    }
 
-    // If optional seed provided, use it, else, random from entropy.
-    let mut rng : StdRng = match args.seed {
-        None => StdRng::from_entropy(),
-        Some(x) => StdRng::seed_from_u64(x)
-    };
+    #[test]
+    fn test_version_detail_follows_variant(){
+        let detail = version_detail(Variant::LizardSpock);
+        assert!(detail.contains("Variant: lizard-spock"));
+        assert!(detail.contains("Moves: rock (r), paper (p), scissors, lizard (l), spock (sp, k)"))
+    }
 
-    // Generate a random move.
-    let opponent_move : Move = rng.gen();
-    // Let the user know what the move the opponent generated.
-    print!("Opponent's move: {:?}. " , opponent_move);
-    calculate_winner(our_move, opponent_move)
-}
+    /** ==== Tournament ==== **/
 
-fn calculate_winner(user: Move, opponent:Move) -> GameResult {
-    if user > opponent {
-        return GameResult::UserWin
-    } else if user == opponent {
-        return GameResult::Tie
-    } else {
-        return GameResult::OpponentWin
+    #[test]
+    fn test_play_tournament_with_seed(){
+        let args = Cli::parse_from(["rps", "paper", "--seed", "1", "--rounds", "3", "--tournament"]);
+        // Frequency counters paper from round two on, paper always beats always-rock.
+        assert_eq!(play_tournament(&args), Tally { user_wins: 5, opponent_wins: 2, ties: 2 })
+    }
+
+    #[test]
+    fn test_format_leaderboard_text(){
+        let standings = vec![
+            Standing { name: String::from("always-rock"), tally: Tally { user_wins: 2, opponent_wins: 0, ties: 0 } },
+            Standing { name: String::from("frequency"), tally: Tally { user_wins: 0, opponent_wins: 1, ties: 1 } },
+        ];
+        assert_eq!(
+            format_leaderboard(&standings, Format::Text),
+            "Leaderboard:\n1. always-rock: 100% (You 2 - Opponent 0 (0 ties))\n2. frequency: 0% (You 0 - Opponent 1 (1 tie))"
+        )
+    }
+
+    /** ==== Stats ==== **/
+
+    #[test]
+    fn test_update_stats_accumulates_across_games(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let mut first = StatsWriter::new(path.clone(), 0);
+        first.record(Some(Move::Rock), &Tally { user_wins: 1, opponent_wins: 0, ties: 0 });
+        update_stats(&mut first);
+        let mut second = StatsWriter::new(path.clone(), 0);
+        second.record(None, &Tally { user_wins: 0, opponent_wins: 2, ties: 1 });
+        let stats = update_stats(&mut second);
+        assert_eq!((stats.games, stats.wins, stats.losses, stats.ties), (4, 1, 2, 1));
+        assert_eq!(stats.moves, [stats::MoveStats { chosen: Move::Rock, wins: 1, losses: 0, ties: 0 }])
+    }
+
+    #[test]
+    fn test_flush_every_saves_partial_totals_during_batch(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let mut stats = Some(StatsWriter::new(path.clone(), 2));
+        let args = Cli::parse_from(["rps", "--batch", "--quiet", "--stats-file", path.to_str().unwrap(), "--flush-every", "2"]);
+        let input = io::Cursor::new("rock\npaper\nscissors\nrock\nrock\n");
+        play_batch(input, &args, &mut ConstantOpponent(Move::Scissors), &mut None, &mut stats);
+        // Five rounds were played but the last one is only saved at the end.
+        let saved = Stats::load(&path);
+        assert_eq!((saved.games, saved.wins, saved.losses, saved.ties), (4, 2, 1, 1))
     }
-}
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-        /** ==== Partial ordering ==== **/
-        /* ==== Rocks ==== */
+    #[test]
+    fn test_seeded_batch_keeps_per_move_stats(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let mut stats = Some(StatsWriter::new(path.clone(), 0));
+        let args = Cli::parse_from(["rps", "--batch", "--quiet", "--seed", "9"]);
+        let input = io::Cursor::new("rock\nrock\npaper\nscissors\nrock\npaper\n");
+        let tally = play_batch(input, &args, &mut random_opponent(9), &mut None, &mut stats);
+        update_stats(stats.as_mut().unwrap());
+        let saved = Stats::load(&path);
+        assert_eq!(saved.games, u64::from(tally.played()));
+        assert_eq!(saved.wins, saved.moves.iter().map(|record| record.wins).sum::<u64>());
+        let games = |m| saved.for_move(m).map_or(0, |record| record.games());
+        assert_eq!((games(Move::Rock), games(Move::Paper), games(Move::Scissors)), (3, 2, 1))
+    }
 
     #[test]
-    fn test_partial_ordering_rock_rock(){
-        assert_eq!(Move::Rock, Move::Rock)
+    fn test_fixed_move(){
+        assert_eq!(fixed_move(&Cli::parse_from(["rps", "paper", "--rounds", "3"])), Some(Move::Paper));
+        assert_eq!(fixed_move(&Cli::parse_from(["rps", "--batch"])), None);
+        assert_eq!(fixed_move(&Cli::parse_from(["rps", "--simulate", "10"])), None)
     }
 
     #[test]
-    fn test_partial_ordering_rock_paper(){
-        assert_eq!((Move::Rock < Move::Paper), true)
+    fn test_stats_file_implies_stats(){
+        let args = Cli::parse_from(["rps", "rock", "--stats-file", "/tmp/rps-stats.json"]);
+        assert_eq!(stats_path(&args), Some(PathBuf::from("/tmp/rps-stats.json")))
     }
 
     #[test]
-    fn test_partial_ordering_rock_scissors(){
-        assert_eq!((Move::Rock > Move::Scissors), true)
+    fn test_no_stats_by_default(){
+        assert_eq!(stats_path(&Cli::parse_from(["rps", "rock"])), None)
     }
 
-        /* ==== Paper ==== */
+    /** ==== Batch ==== **/
+
+    #[test]
+    fn test_play_batch_skips_invalid_and_blank_lines(){
+        let input = "rock\nnotreal\n\npaper\nquit\nscissors\n".as_bytes();
+        let tally = play_batch(input, &Cli::parse_from(["rps", "--batch"]), &mut random_opponent(7), &mut None, &mut None);
+        assert_eq!(tally.user_wins + tally.opponent_wins + tally.ties, 3)
+    }
 
     #[test]
-    fn test_partial_ordering_paper_rock(){
-        assert_eq!((Move::Paper > Move::Rock), true)
+    fn test_play_batch_is_deterministic(){
+        let input = "rock\npaper\nscissors\n";
+        let args = Cli::parse_from(["rps", "--batch"]);
+        let first = play_batch(input.as_bytes(), &args, &mut random_opponent(42), &mut None, &mut None);
+        let second = play_batch(input.as_bytes(), &args, &mut random_opponent(42), &mut None, &mut None);
+        assert_eq!(first, second)
     }
 
     #[test]
-    fn test_partial_ordering_paper_paper(){
-        assert_eq!(Move::Paper, Move::Paper)
+    fn test_play_batch_against_scripted_opponent(){
+        let input = "rock\npaper\nscissors\n".as_bytes();
+        let mut opponent = ScriptedOpponent::new(vec![Move::Scissors, Move::Scissors, Move::Rock]);
+        let tally = play_batch(input, &Cli::parse_from(["rps", "--batch"]), &mut opponent, &mut None, &mut None);
+        assert_eq!(tally, Tally { user_wins: 1, opponent_wins: 2, ties: 0 })
     }
 
+    /** ==== Input socket ==== **/
+
     #[test]
-    fn test_partial_ordering_paper_scissors(){
-        assert_eq!((Move::Paper < Move::Scissors), true)
+    fn test_serve_client_writes_results_back(){
+        let mut opponent = ScriptedOpponent::new(vec![Move::Scissors, Move::Paper]);
+        let mut output = Vec::new();
+        let args = Cli::parse_from(["rps", "--input-socket", "rps.sock"]);
+        let tally = serve_client("rock\n\nstone\nrock\n".as_bytes(), &mut output, &args, &mut opponent, &mut None);
+        assert_eq!(tally, Tally { user_wins: 1, opponent_wins: 1, ties: 0 });
+        assert_eq!(String::from_utf8(output).unwrap(), "Opponent's move: Scissors. You win!\nLine 3: Invalid move: \"stone\"\nOpponent's move: Paper. You lose!\n")
     }
 
-        /* ==== Scissors ==== */
+    #[test]
+    fn test_serve_client_reports_a_round_that_cannot_be_played(){
+        let mut output = Vec::new();
+        let args = Cli::parse_from(["rps", "--input-socket", "rps.sock", "--no-ties"]);
+        let tally = serve_client("rock
+scissors
+".as_bytes(), &mut output, &args, &mut ConstantOpponent(Move::Rock), &mut None);
+        assert_eq!(tally, Tally { user_wins: 0, opponent_wins: 1, ties: 0 });
+        assert_eq!(String::from_utf8(output).unwrap(), "Line 1: No decisive outcome is possible: every re-roll was a tie\nOpponent's move: Rock. You lose!\n")
+    }
 
     #[test]
-    fn test_partial_ordering_scissors_rock(){
-        assert_eq!((Move::Scissors < Move::Rock), true)
+    fn test_serve_client_sends_the_countdown(){
+        let mut output = Vec::new();
+        let args = Cli::parse_from(["rps", "--input-socket", "rps.sock", "--dramatic", "--countdown-ms", "0"]);
+        serve_client("rock
+".as_bytes(), &mut output, &args, &mut ConstantOpponent(Move::Scissors), &mut None);
+        assert_eq!(String::from_utf8(output).unwrap(), "Rock... Paper... Scissors... Shoot!\nOpponent's move: Scissors. You win!\n")
     }
 
+    /** ==== Two player ==== **/
+
     #[test]
-    fn test_partial_ordering_scissors_paper(){
-        assert_eq!((Move::Scissors > Move::Paper), true)
+    fn test_two_player_first_wins(){
+        let round = play_two_player("rock\nscissors\n".as_bytes(), &Cli::parse_from(["rps", "--two-player"])).unwrap();
+        assert_eq!(round, Round::new(Move::Rock, Move::Scissors));
+        assert_eq!(round.result, GameResult::UserWin)
     }
 
     #[test]
-    fn test_partial_ordering_scissors_scissors(){
-        assert_eq!(Move::Scissors, Move::Scissors)
+    fn test_two_player_second_wins(){
+        let round = play_two_player("rock\npaper\n".as_bytes(), &Cli::parse_from(["rps", "--two-player"])).unwrap();
+        assert_eq!(round.result, GameResult::OpponentWin)
     }
 
-        /** ==== Calculate winner==== **/
+    #[test]
+    fn test_two_player_reprompts_on_invalid_input(){
+        let input = "rock\nnotreal\nspock\nrock\n".as_bytes();
+        let round = play_two_player(input, &Cli::parse_from(["rps", "--two-player"])).unwrap();
+        assert_eq!(round.result, GameResult::Tie)
+    }
 
     #[test]
-    fn test_calculate_winner_userwin(){
-        assert_eq!(calculate_winner(Move::Rock, Move::Scissors), GameResult::UserWin)
+    fn test_two_player_missing_second_move(){
+        assert_eq!(play_two_player("rock\n".as_bytes(), &Cli::parse_from(["rps", "--two-player"])), None)
     }
 
+    /** ==== Interactive ==== **/
+
     #[test]
-    fn test_calculate_winner_tie(){
-        assert_eq!(calculate_winner(Move::Rock, Move::Rock), GameResult::Tie)
+    fn test_hotkeys_turn_keys_into_lines(){
+        let press = |code, modifiers| key_entry(&KeyEvent::new(code, modifiers));
+        assert_eq!(press(KeyCode::Char('r'), KeyModifiers::NONE), Some(Entry::Line(String::from("r"))));
+        assert_eq!(press(KeyCode::Char('P'), KeyModifiers::SHIFT), Some(Entry::Line(String::from("P"))));
+        assert_eq!(press(KeyCode::Esc, KeyModifiers::NONE), Some(Entry::Line(String::from("quit"))));
+        assert_eq!(press(KeyCode::Char('d'), KeyModifiers::CONTROL), Some(Entry::Line(String::from("quit"))));
+        assert_eq!(press(KeyCode::Char('c'), KeyModifiers::CONTROL), Some(Entry::Interrupted));
+        assert_eq!(press(KeyCode::Char('x'), KeyModifiers::CONTROL), None);
+        assert_eq!(press(KeyCode::Up, KeyModifiers::NONE), None)
     }
 
     #[test]
-    fn test_calculate_winner_opponentwin(){
-        assert_eq!(calculate_winner(Move::Rock, Move::Paper), GameResult::OpponentWin)
+    fn test_challenge_opponent_ignores_how_you_play(){
+        let args = Cli::parse_from(["rps", "--interactive", "--challenge", "--seed", "7", "--quiet", "--ai", "frequency"]);
+        let opponent_moves = |input : &'static str| {
+            let mut opponent = ChallengeOpponent::new(&args, 7);
+            let mut history = Vec::new();
+            for line in input.lines() {
+                let round = play_round(&args, &mut opponent, line.parse().unwrap(), &history);
+                history.push(round);
+            }
+            history.iter().map(|round| round.opponent_move).collect::<Vec<Move>>()
+        };
+        let expected : Vec<Move> = (0..5)
+            .map(|i| Strategy::Frequency.opponent(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(round_seed(7, i))).next_move(&[]))
+            .collect();
+        assert_eq!(opponent_moves("rock\nrock\nrock\nrock\nrock"), expected);
+        assert_eq!(opponent_moves("paper\nscissors\nrock\npaper\npaper"), expected)
     }
 
-        /** ==== FromStr ==== **/
+    #[test]
+    fn test_challenge_session_against_a_fixed_seed(){
+        // Round i is seeded with 0 ^ i, and seeds 0, 1 and 2 open with
+        // Scissors, Paper and Rock.
+        let args = Cli::parse_from(["rps", "--interactive", "--challenge", "--seed", "0", "--quiet"]);
+        for (input, tally) in [("rock\nrock\nrock\n", Tally { user_wins: 1, opponent_wins: 1, ties: 1 }), ("scissors\npaper\nrock\n", Tally { user_wins: 0, opponent_wins: 0, ties: 3 })] {
+            let mut opponent = ChallengeOpponent::new(&args, 0);
+            assert_eq!(play_interactive(input.as_bytes(), &args, &mut opponent, &mut None, &mut None, &AtomicBool::new(false)), tally);
+        }
+    }
 
     #[test]
-    fn test_fromstr_rock(){
-        assert_eq!(Move::from_str("rock"), Ok(Move::Rock))
+    fn test_challenge_opponent_follows_the_weights(){
+        let args = Cli::parse_from(["rps", "--interactive", "--challenge", "--seed", "3", "--weights", "0,0,1"]);
+        let mut opponent = ChallengeOpponent::new(&args, 3);
+        let mut history = Vec::new();
+        for _ in 0..5 {
+            let round = play_round(&args, &mut opponent, Move::Rock, &history);
+            history.push(round);
+        }
+        assert!(history.iter().all(|round| round.opponent_move == Move::Scissors))
+    }
+
+    /**
+     * Input that gives out its lines, then sets `interrupted` as Ctrl-C
+     * would and holds back one more line until `release` says so. A session
+     * that keeps waiting for input after the interrupt gets to play it.
+     */
+    struct InterruptedInput {
+        lines: io::Cursor<&'static str>,
+        interrupted: std::sync::Arc<AtomicBool>,
+        release: Receiver<()>
+    }
+
+    impl io::Read for InterruptedInput {
+        fn read(&mut self, buf : &mut [u8]) -> io::Result<usize> {
+            let n = self.lines.read(buf)?;
+            if n > 0 || self.interrupted.swap(true, Ordering::SeqCst) {
+                return Ok(n);
+            }
+            let _ = self.release.recv();
+            let late = b"paper\n";
+            buf[..late.len()].copy_from_slice(late);
+            Ok(late.len())
+        }
     }
 
     #[test]
-    fn test_fromstr_paper(){
-        assert_eq!(Move::from_str("paper"), Ok(Move::Paper))
+    fn test_play_interactive_stops_when_interrupted(){
+        let interrupted = std::sync::Arc::new(AtomicBool::new(false));
+        let (release, held) = mpsc::channel();
+        let input = io::BufReader::new(InterruptedInput { lines: io::Cursor::new("rock\nrock\n"), interrupted: interrupted.clone(), release: held });
+        let args = Cli::parse_from(["rps", "--interactive", "--quiet"]);
+        let tally = play_interactive(input, &args, &mut ConstantOpponent(Move::Scissors), &mut None, &mut None, &interrupted);
+        // Releasing the late line only now shows the session didn't wait for it.
+        release.send(()).unwrap();
+        assert_eq!(tally, Tally { user_wins: 2, opponent_wins: 0, ties: 0 })
     }
 
     #[test]
-    fn test_fromstr_scissors(){
-        assert_eq!(Move::from_str("scissors"), Ok(Move::Scissors))
+    fn test_moves_read_before_an_interrupt_are_played(){
+        let (sender, receiver) = mpsc::channel();
+        sender.send(String::from("rock")).unwrap();
+        let mut source = MoveSource::Threaded(receiver, None);
+        let interrupted = AtomicBool::new(true);
+        assert_eq!(source.next_entry(&interrupted), Entry::Line(String::from("rock")));
+        assert_eq!(source.next_entry(&interrupted), Entry::Interrupted)
     }
 
     #[test]
-    fn test_fromstr_error(){
-        assert_eq!(Move::from_str("notreal"), Err(clap::ErrorKind::InvalidValue))
+    fn test_play_interactive_interrupted_before_a_move(){
+        // Nothing has been typed yet when Ctrl-C is pressed.
+        let (_sender, receiver) = mpsc::channel::<String>();
+        let args = Cli::parse_from(["rps", "--interactive", "--quiet"]);
+        let tally = play_session(MoveSource::Threaded(receiver, None), &args, &mut ConstantOpponent(Move::Scissors), &mut None, &mut None, &AtomicBool::new(true));
+        assert_eq!(tally.played(), 0)
     }
 
-    /** ==== Seeded Random Completeness ==== **/
+    #[test]
+    fn test_interrupt_check_leaves_timeouts_alone(){
+        let (_sender, receiver) = mpsc::channel::<String>();
+        let mut source = MoveSource::Threaded(receiver, Some(Duration::from_millis(250)));
+        assert!(matches!(source.next_entry(&AtomicBool::new(false)), Entry::TimedOut))
+    }
 
     #[test]
-    fn test_distribution_rock(){
-        let mut seed = StdRng::seed_from_u64(2);
-        let seeded_move : Move = seed.gen();
-        assert_eq!(seeded_move, Move::Rock)
+    fn test_play_interactive_until_quit(){
+        let input = "rock\npaper\nscissors\nquit\nrock\n".as_bytes();
+        let tally = play_interactive(input, &Cli::parse_from(["rps", "--interactive"]), &mut random_opponent(7), &mut None, &mut None, &AtomicBool::new(false));
+        assert_eq!(tally.user_wins + tally.opponent_wins + tally.ties, 3)
     }
-    
+
     #[test]
-    fn test_distribution_paper(){
-        let mut seed = StdRng::seed_from_u64(7);
-        let seeded_move : Move = seed.gen();
-        assert_eq!(seeded_move, Move::Paper)
+    fn test_play_interactive_skips_invalid_lines(){
+        let input = "rock\nnotreal\n\nlizard\npaper".as_bytes();
+        let tally = play_interactive(input, &Cli::parse_from(["rps", "--interactive"]), &mut random_opponent(7), &mut None, &mut None, &AtomicBool::new(false));
+        assert_eq!(tally.user_wins + tally.opponent_wins + tally.ties, 2)
     }
 
     #[test]
-    fn test_distribution_scissors(){
-        let mut seed = StdRng::seed_from_u64(1);
-        let seeded_move : Move = seed.gen();
-        assert_eq!(seeded_move, Move::Scissors)
+    fn test_play_interactive_with_seed(){
+        let input = "rock\nrock\nrock\nrock\nrock\n".as_bytes();
+        let tally = play_interactive(input, &Cli::parse_from(["rps", "--interactive"]), &mut random_opponent(23), &mut None, &mut None, &AtomicBool::new(false));
+        assert_eq!(tally, Tally { user_wins: 0, opponent_wins: 1, ties: 4 })
     }
 
-    /** ==== Play Game ==== **/
+    #[test]
+    fn test_play_interactive_without_timeout(){
+        let input = "rock\nrock\nrock\nrock\nrock\n".as_bytes();
+        let args = Cli::parse_from(["rps", "--interactive", "--timeout", "0"]);
+        let tally = play_interactive(input, &args, &mut random_opponent(23), &mut None, &mut None, &AtomicBool::new(false));
+        assert_eq!(tally, Tally { user_wins: 0, opponent_wins: 1, ties: 4 })
+    }
 
     #[test]
-    fn test_play_game_with_seed(){
-        let args = Cli { pattern: String::from("Scissors"), seed: Some(7) };
-        assert_eq!(play_game(args), GameResult::UserWin)
+    fn test_play_interactive_with_timeout_and_prompt_input(){
+        // Moves that are already waiting are never late.
+        let input = "rock\nrock\nrock\nrock\nrock\n".as_bytes();
+        let args = Cli::parse_from(["rps", "--interactive", "--timeout", "5"]);
+        let tally = play_interactive(input, &args, &mut random_opponent(23), &mut None, &mut None, &AtomicBool::new(false));
+        assert_eq!(tally, Tally { user_wins: 0, opponent_wins: 1, ties: 4 })
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_slow_move_is_forfeited(){
+        // Nothing is ever typed and no time is allowed, so both rounds
+        // are forfeited without waiting.
+        let (_sender, receiver) = mpsc::channel::<String>();
+        let source = MoveSource::Threaded(receiver, Some(Duration::ZERO));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.csv");
+        let mut transcript = Some(Transcript::open(&path, TiePolicy::Tie).unwrap());
+        let args = Cli::parse_from(["rps", "--interactive", "--max-rounds", "2", "--seed", "4", "--quiet"]);
+        let tally = play_session(source, &args, &mut ConstantOpponent(Move::Rock), &mut transcript, &mut None, &AtomicBool::new(false));
+        assert_eq!(tally, Tally { user_wins: 0, opponent_wins: 2, ties: 0 });
+        let rows = fs::read_to_string(&path).unwrap();
+        assert_eq!(rows.lines().skip(1).collect::<Vec<_>>(), [",,OpponentWin,4,tie", ",,OpponentWin,4,tie"])
+    }
+
+    #[test]
+    fn test_format_round_as_csv(){
+        let style = Style { format: Format::Csv, color: true, emoji: true, verbose: true, lang: Lang::Es, ..Style::plain() };
+        // Csv ignores colors, emoji, --verbose and the language.
+        assert_eq!(format_round(&Round::new(Move::Rock, Move::Paper), &style), "rock,paper,OpponentWin")
+    }
+
+    #[test]
+    fn test_coaching_names_the_counter(){
+        let style = Style::plain();
+        assert_eq!(format_coaching(&Round::new(Move::Scissors, Move::Rock), &style).as_deref(), Some("You should have played Paper"));
+        assert_eq!(format_coaching(&Round::new(Move::Lizard, Move::Lizard), &style).as_deref(), Some("You should have played Rock"))
+    }
+
+    #[test]
+    fn test_no_coaching_after_a_win(){
+        let style = Style::plain();
+        assert_eq!(format_coaching(&Round::new(Move::Paper, Move::Rock), &style), None)
+    }
+
+    #[test]
+    fn test_format_forfeit(){
+        let style = Style::plain();
+        assert_eq!(format_forfeit(&style), "Time's up! The opponent wins the round.");
+        let quiet = Style { quiet: true, ..style };
+        assert_eq!(format_forfeit(&quiet), "OpponentWin")
+    }
+
+    #[test]
+    fn test_play_interactive_against_scripted_opponent(){
+        // The invalid line doesn't use up a scripted move.
+        let input = "paper\nnotreal\npaper\nquit\npaper\n".as_bytes();
+        let mut opponent = ScriptedOpponent::new(vec![Move::Rock, Move::Paper, Move::Scissors]);
+        let tally = play_interactive(input, &Cli::parse_from(["rps", "--interactive"]), &mut opponent, &mut None, &mut None, &AtomicBool::new(false));
+        assert_eq!(tally, Tally { user_wins: 1, opponent_wins: 0, ties: 1 })
+    }
+
+    #[test]
+    fn test_tie_policy_reinterprets_a_forced_tie(){
+        let cases = [("tie", GameResult::Tie), ("user", GameResult::UserWin), ("opponent", GameResult::OpponentWin)];
+        for (policy, result) in cases {
+            let args = Cli::parse_from(["rps", "rock", "--tie-policy", policy]);
+            let round = play_round(&args, &mut ConstantOpponent(Move::Rock), Move::Rock, &[]);
+            assert_eq!(round, Round { opponent_move: Move::Rock, user_move: Move::Rock, result })
+        }
+    }
+
+    #[test]
+    fn test_tie_policy_replay_rerolls_a_tie(){
+        let args = Cli::parse_from(["rps", "rock", "--tie-policy", "replay"]);
+        let mut opponent = ScriptedOpponent::new(vec![Move::Rock, Move::Rock, Move::Scissors]);
+        assert_eq!(play_round(&args, &mut opponent, Move::Rock, &[]), Round::new(Move::Rock, Move::Scissors))
+    }
+
+    #[test]
+    fn test_no_ties_against_scripted_opponent(){
+        let args = Cli::parse_from(["rps", "rock", "--no-ties"]);
+        let mut opponent = ScriptedOpponent::new(vec![Move::Rock, Move::Rock, Move::Paper]);
+        assert_eq!(play_round(&args, &mut opponent, Move::Rock, &[]), Round::new(Move::Rock, Move::Paper))
+    }
+
+    impl Style {
+        fn plain() -> Style {
+            Style { format: Format::Text, color: false, emoji: false, quiet: false, verbose: false, split: false, banner: false, ascii: false, lang: Lang::En }
+        }
+    }
+}
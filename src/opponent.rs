@@ -0,0 +1,1269 @@
+/*!
+ * How the computer picks its moves.
+ */
+
+use std::{error::Error, fmt};
+
+use clap::ValueEnum;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng, SeedableRng,
+};
+
+use serde::Serialize;
+
+use crate::{GameResult, Move, ParseMoveError, Round, Tally, Variant};
+
+/**
+ * How far `Strategy::Adaptive` moves its odds toward the counter of a move
+ * that just beat it, as a share of the way there.
+ */
+pub const ADAPTATION_RATE: f64 = 0.25;
+
+/**
+ * The strategy the computer opponent plays with.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Strategy {
+    /// Every move is equally likely.
+    Random,
+    /// Counter the move the user has thrown most often so far.
+    Frequency,
+    /// Repeat the last move after a win or tie, otherwise switch to the move
+    /// that beats the user's last throw.
+    WinStayLoseShift,
+    /// Counter the move the user has most often thrown after the move they
+    /// threw last, learning their habits as the game goes on.
+    Markov,
+    /// Start from the usual odds, and after every user win shift them toward
+    /// the counter of the winning move. The more the user wins, the harder
+    /// it gets.
+    Adaptive,
+    /// Draw from a shuffled bag holding every move once, refilling it when
+    /// it runs out, so each move comes up exactly once per bag.
+    ShuffleBag,
+    /// Always throw rock.
+    AlwaysRock,
+    /// Always throw paper.
+    AlwaysPaper,
+    /// Always throw scissors.
+    AlwaysScissors
+}
+
+impl Strategy {
+    /**
+     * Picks the opponent's next move given the rounds played so far. Falls
+     * back to a random move drawn from `weights` when the strategy has
+     * nothing to go on.
+     */
+    pub fn next_move<R: Rng + ?Sized>(&self, weights: &MoveWeights, history: &[Round], rng: &mut R) -> Move {
+        match self {
+            Strategy::Random => rng.sample(weights),
+            Strategy::Frequency => match most_frequent_user_move(weights.variant(), history) {
+                Some(favourite) => favourite.counters(weights.variant())[0],
+                None => rng.sample(weights),
+            },
+            Strategy::Markov => match likely_next_user_move(weights.variant(), history) {
+                Some(predicted) => predicted.counters(weights.variant())[0],
+                None => rng.sample(weights),
+            },
+            Strategy::Adaptive => {
+                let odds = adaptive_distribution(weights, history);
+                let index = WeightedIndex::new(odds.iter().map(|(_, p)| p)).expect("adapted odds sum to one");
+                odds[index.sample(rng)].0
+            }
+            Strategy::WinStayLoseShift => match history.last() {
+                Some(last) if last.result == GameResult::UserWin => last.user_move.counters(weights.variant())[0],
+                Some(last) => last.opponent_move,
+                None => rng.sample(weights),
+            },
+            Strategy::ShuffleBag => {
+                let bag = left_in_bag(weights.variant(), history);
+                bag[rng.gen_range(0..bag.len())]
+            }
+            Strategy::AlwaysRock | Strategy::AlwaysPaper | Strategy::AlwaysScissors => self.constant_move().expect("constant strategy"),
+        }
+    }
+
+    /**
+     * The move an always-X strategy plays, `None` for the others.
+     */
+    pub fn constant_move(&self) -> Option<Move> {
+        match self {
+            Strategy::AlwaysRock => Some(Move::Rock),
+            Strategy::AlwaysPaper => Some(Move::Paper),
+            Strategy::AlwaysScissors => Some(Move::Scissors),
+            Strategy::Random | Strategy::Frequency | Strategy::WinStayLoseShift | Strategy::Markov | Strategy::Adaptive | Strategy::ShuffleBag => None,
+        }
+    }
+
+    /**
+     * An opponent that plays this strategy, drawing from `weights` with `rng`
+     * whenever it needs a random move.
+     */
+    pub fn opponent<R: Rng + 'static>(&self, weights: MoveWeights, rng: R) -> Box<dyn Opponent> {
+        match self {
+            Strategy::Random => Box::new(RandomOpponent::new(weights, rng)),
+            Strategy::Frequency => Box::new(FrequencyOpponent::new(weights, rng)),
+            Strategy::WinStayLoseShift => Box::new(WinStayLoseShiftOpponent::new(weights, rng)),
+            Strategy::Markov => Box::new(MarkovOpponent::new(weights, rng)),
+            Strategy::Adaptive => Box::new(AdaptiveOpponent::new(weights, rng)),
+            Strategy::ShuffleBag => Box::new(ShuffleBagOpponent::new(weights, rng)),
+            Strategy::AlwaysRock | Strategy::AlwaysPaper | Strategy::AlwaysScissors => {
+                Box::new(ConstantOpponent(self.constant_move().expect("constant strategy")))
+            }
+        }
+    }
+
+    /**
+     * How likely each move of the variant is to be picked by `next_move`
+     * given the same history.
+     */
+    pub fn move_distribution(&self, weights: &MoveWeights, history: &[Round]) -> MoveDistribution {
+        let certain = |chosen: Move| weights.variant().moves().iter().map(|m| (*m, if *m == chosen { 1.0 } else { 0.0 })).collect();
+        match self {
+            Strategy::Random => weights.probabilities(),
+            Strategy::Frequency => match most_frequent_user_move(weights.variant(), history) {
+                Some(favourite) => certain(favourite.counters(weights.variant())[0]),
+                None => weights.probabilities(),
+            },
+            Strategy::Markov => match likely_next_user_move(weights.variant(), history) {
+                Some(predicted) => certain(predicted.counters(weights.variant())[0]),
+                None => weights.probabilities(),
+            },
+            Strategy::Adaptive => adaptive_distribution(weights, history),
+            Strategy::WinStayLoseShift => match history.last() {
+                Some(last) if last.result == GameResult::UserWin => certain(last.user_move.counters(weights.variant())[0]),
+                Some(last) => certain(last.opponent_move),
+                None => weights.probabilities(),
+            },
+            Strategy::ShuffleBag => {
+                let bag = left_in_bag(weights.variant(), history);
+                let share = 1.0 / bag.len() as f64;
+                weights.variant().moves().iter().map(|m| (*m, if bag.contains(m) { share } else { 0.0 })).collect()
+            }
+            Strategy::AlwaysRock | Strategy::AlwaysPaper | Strategy::AlwaysScissors => {
+                certain(self.constant_move().expect("constant strategy"))
+            }
+        }
+    }
+}
+
+/**
+ * How hard the computer is to beat, as a mix of strategies.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Difficulty {
+    /// Play at random.
+    Easy,
+    /// Play at random half the time, otherwise counter the user's favourite
+    /// move.
+    Medium,
+    /// Mostly predict the user's next move from their habits, otherwise
+    /// adapt to whatever keeps beating it.
+    Hard
+}
+
+impl Difficulty {
+    /**
+     * The strategies this difficulty mixes, with the share of rounds each
+     * one plays.
+     */
+    pub fn mix(&self) -> &'static [(Strategy, f64)] {
+        match self {
+            Difficulty::Easy => &[(Strategy::Random, 1.0)],
+            Difficulty::Medium => &[(Strategy::Random, 0.5), (Strategy::Frequency, 0.5)],
+            Difficulty::Hard => &[(Strategy::Markov, 0.75), (Strategy::Adaptive, 0.25)],
+        }
+    }
+
+    /**
+     * An opponent that plays this difficulty's mix. Each strategy gets its own
+     * generator seeded from `rng`, so a single strategy plays just like
+     * `Strategy::opponent` would.
+     */
+    pub fn opponent<R: Rng + SeedableRng + 'static>(&self, weights: MoveWeights, mut rng: R) -> Box<dyn Opponent> {
+        if let [(strategy, _)] = self.mix() {
+            return strategy.opponent(weights, rng);
+        }
+        let parts = self
+            .mix()
+            .iter()
+            .map(|(strategy, share)| (*share, strategy.opponent(weights.clone(), R::seed_from_u64(rng.gen()))))
+            .collect();
+        Box::new(BlendedOpponent::new(parts, rng))
+    }
+}
+
+/**
+ * The probability of each move, as (move, probability) pairs summing to one.
+ */
+pub type MoveDistribution = Vec<(Move, f64)>;
+
+/**
+ * The chance of each of the variant's moves beating an opponent whose next
+ * move follows `opponent`.
+ */
+pub fn win_probabilities(variant: Variant, opponent: &[(Move, f64)]) -> MoveDistribution {
+    variant
+        .moves()
+        .iter()
+        .map(|ours| {
+            let chance = opponent.iter().filter(|(theirs, _)| ours.beats(theirs)).map(|(_, p)| p).sum();
+            (*ours, chance)
+        })
+        .collect()
+}
+
+/**
+ * The move with the best expected score (wins minus losses) against an
+ * opponent whose next move follows `opponent`. `None` when several moves are
+ * equally good, as against a uniformly random opponent.
+ */
+pub fn best_reply(variant: Variant, opponent: &[(Move, f64)]) -> Option<Move> {
+    let score = |ours: &Move| -> f64 {
+        opponent
+            .iter()
+            .map(|(theirs, p)| if ours.beats(theirs) { *p } else if theirs.beats(ours) { -p } else { 0.0 })
+            .sum()
+    };
+    let scores : Vec<(Move, f64)> = variant.moves().iter().map(|m| (*m, score(m))).collect();
+    let best = scores.iter().map(|(_, s)| *s).fold(f64::NEG_INFINITY, f64::max);
+    let mut top = scores.iter().filter(|(_, s)| (best - s).abs() < 1e-9);
+    match (top.next(), top.next()) {
+        (Some((m, _)), None) => Some(*m),
+        _ => None,
+    }
+}
+
+/**
+ * How often the user wins, loses and ties, as shares between 0 and 1.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct OutcomeRates {
+    pub win: f64,
+    pub loss: f64,
+    pub tie: f64
+}
+
+impl OutcomeRates {
+    /**
+     * The rates actually seen over a series. All zero when nothing was played.
+     */
+    pub fn from_tally(tally: &Tally) -> OutcomeRates {
+        let games = f64::from(tally.user_wins + tally.opponent_wins + tally.ties);
+        let share = |n: u32| if games == 0.0 { 0.0 } else { f64::from(n) / games };
+        OutcomeRates { win: share(tally.user_wins), loss: share(tally.opponent_wins), tie: share(tally.ties) }
+    }
+
+    /**
+     * The user's average score per round, counting a win as 1 and a loss
+     * as -1.
+     */
+    pub fn value(&self) -> f64 {
+        self.win - self.loss
+    }
+}
+
+/**
+ * The outcome rates when the user's moves follow `user` and the opponent's
+ * follow `opponent`, both drawn independently. Whatever the opponent does,
+ * a uniform `user` wins exactly as often as it loses, which is what makes it
+ * the equilibrium.
+ */
+pub fn expected_rates(user: &[(Move, f64)], opponent: &[(Move, f64)]) -> OutcomeRates {
+    let mut rates = OutcomeRates { win: 0.0, loss: 0.0, tie: 0.0 };
+    for (ours, p) in user {
+        for (theirs, q) in opponent {
+            let slot = if ours.beats(theirs) { &mut rates.win } else if theirs.beats(ours) { &mut rates.loss } else { &mut rates.tie };
+            *slot += p * q;
+        }
+    }
+    rates
+}
+
+/**
+ * A computer player. Each opponent owns whatever state it needs, such as its
+ * random number generator, so several can play side by side.
+ */
+pub trait Opponent {
+    /**
+     * Picks the next move given the rounds played against this opponent so far.
+     */
+    fn next_move(&mut self, history: &[Round]) -> Move;
+
+    /**
+     * How likely each move is to come next, for opponents that can tell.
+     */
+    fn move_distribution(&self, _history: &[Round]) -> Option<MoveDistribution> {
+        None
+    }
+
+    /**
+     * How many more moves the opponent has, for opponents that can run out.
+     * Games check it before asking for a move.
+     */
+    fn moves_left(&self) -> Option<usize> {
+        None
+    }
+}
+
+/**
+ * Plays a random move from its weights every round.
+ */
+pub struct RandomOpponent<R> {
+    weights: MoveWeights,
+    rng: R
+}
+
+impl<R: Rng> RandomOpponent<R> {
+    pub fn new(weights: MoveWeights, rng: R) -> RandomOpponent<R> {
+        RandomOpponent { weights, rng }
+    }
+}
+
+impl<R: Rng> Opponent for RandomOpponent<R> {
+    fn next_move(&mut self, _history: &[Round]) -> Move {
+        self.rng.sample(&self.weights)
+    }
+
+    fn move_distribution(&self, _history: &[Round]) -> Option<MoveDistribution> {
+        Some(self.weights.probabilities())
+    }
+}
+
+/**
+ * Counters the user's most common move, see `Strategy::Frequency`.
+ */
+pub struct FrequencyOpponent<R> {
+    weights: MoveWeights,
+    rng: R
+}
+
+impl<R: Rng> FrequencyOpponent<R> {
+    pub fn new(weights: MoveWeights, rng: R) -> FrequencyOpponent<R> {
+        FrequencyOpponent { weights, rng }
+    }
+}
+
+impl<R: Rng> Opponent for FrequencyOpponent<R> {
+    fn next_move(&mut self, history: &[Round]) -> Move {
+        Strategy::Frequency.next_move(&self.weights, history, &mut self.rng)
+    }
+
+    fn move_distribution(&self, history: &[Round]) -> Option<MoveDistribution> {
+        Some(Strategy::Frequency.move_distribution(&self.weights, history))
+    }
+}
+
+/**
+ * Sticks with a winning move and abandons a losing one, see
+ * `Strategy::WinStayLoseShift`.
+ */
+pub struct WinStayLoseShiftOpponent<R> {
+    weights: MoveWeights,
+    rng: R
+}
+
+impl<R: Rng> WinStayLoseShiftOpponent<R> {
+    pub fn new(weights: MoveWeights, rng: R) -> WinStayLoseShiftOpponent<R> {
+        WinStayLoseShiftOpponent { weights, rng }
+    }
+}
+
+impl<R: Rng> Opponent for WinStayLoseShiftOpponent<R> {
+    fn next_move(&mut self, history: &[Round]) -> Move {
+        Strategy::WinStayLoseShift.next_move(&self.weights, history, &mut self.rng)
+    }
+
+    fn move_distribution(&self, history: &[Round]) -> Option<MoveDistribution> {
+        Some(Strategy::WinStayLoseShift.move_distribution(&self.weights, history))
+    }
+}
+
+/**
+ * Counters the user's most likely next move given their last one, see
+ * `Strategy::Markov`.
+ */
+pub struct MarkovOpponent<R> {
+    weights: MoveWeights,
+    rng: R
+}
+
+impl<R: Rng> MarkovOpponent<R> {
+    pub fn new(weights: MoveWeights, rng: R) -> MarkovOpponent<R> {
+        MarkovOpponent { weights, rng }
+    }
+}
+
+impl<R: Rng> Opponent for MarkovOpponent<R> {
+    fn next_move(&mut self, history: &[Round]) -> Move {
+        Strategy::Markov.next_move(&self.weights, history, &mut self.rng)
+    }
+
+    fn move_distribution(&self, history: &[Round]) -> Option<MoveDistribution> {
+        Some(Strategy::Markov.move_distribution(&self.weights, history))
+    }
+}
+
+/**
+ * Leans further toward countering the user each time they win, see
+ * `Strategy::Adaptive`.
+ */
+pub struct AdaptiveOpponent<R> {
+    weights: MoveWeights,
+    rng: R
+}
+
+impl<R: Rng> AdaptiveOpponent<R> {
+    pub fn new(weights: MoveWeights, rng: R) -> AdaptiveOpponent<R> {
+        AdaptiveOpponent { weights, rng }
+    }
+}
+
+impl<R: Rng> Opponent for AdaptiveOpponent<R> {
+    fn next_move(&mut self, history: &[Round]) -> Move {
+        Strategy::Adaptive.next_move(&self.weights, history, &mut self.rng)
+    }
+
+    fn move_distribution(&self, history: &[Round]) -> Option<MoveDistribution> {
+        Some(Strategy::Adaptive.move_distribution(&self.weights, history))
+    }
+}
+
+/**
+ * Deals every move once per bag, see `Strategy::ShuffleBag`. It ignores the
+ * weights, since an even bag is the point.
+ */
+pub struct ShuffleBagOpponent<R> {
+    weights: MoveWeights,
+    rng: R
+}
+
+impl<R: Rng> ShuffleBagOpponent<R> {
+    pub fn new(weights: MoveWeights, rng: R) -> ShuffleBagOpponent<R> {
+        ShuffleBagOpponent { weights, rng }
+    }
+}
+
+impl<R: Rng> Opponent for ShuffleBagOpponent<R> {
+    fn next_move(&mut self, history: &[Round]) -> Move {
+        Strategy::ShuffleBag.next_move(&self.weights, history, &mut self.rng)
+    }
+
+    fn move_distribution(&self, history: &[Round]) -> Option<MoveDistribution> {
+        Some(Strategy::ShuffleBag.move_distribution(&self.weights, history))
+    }
+}
+
+/**
+ * Plays the same move every round.
+ */
+pub struct ConstantOpponent(pub Move);
+
+impl Opponent for ConstantOpponent {
+    fn next_move(&mut self, _history: &[Round]) -> Move {
+        self.0
+    }
+
+    fn move_distribution(&self, _history: &[Round]) -> Option<MoveDistribution> {
+        Some(vec![(self.0, 1.0)])
+    }
+}
+
+/**
+ * A casino-style opponent that knows the user's move in advance: with
+ * probability `edge` it plays the counter, otherwise a random move from its
+ * weights. This only makes sense when the move is fixed before the opponent
+ * draws, as in a single game given on the command line.
+ */
+pub struct HouseEdgeOpponent<R> {
+    user_move: Move,
+    edge: f64,
+    weights: MoveWeights,
+    rng: R
+}
+
+impl<R: Rng> HouseEdgeOpponent<R> {
+    /**
+     * Panics unless `edge` is a probability, between 0 and 1.
+     */
+    pub fn new(user_move: Move, edge: f64, weights: MoveWeights, rng: R) -> HouseEdgeOpponent<R> {
+        assert!((0.0..=1.0).contains(&edge), "the house edge is a probability");
+        HouseEdgeOpponent { user_move, edge, weights, rng }
+    }
+
+    fn counter(&self) -> Move {
+        self.user_move.counters(self.weights.variant())[0]
+    }
+}
+
+impl<R: Rng> Opponent for HouseEdgeOpponent<R> {
+    fn next_move(&mut self, _history: &[Round]) -> Move {
+        if self.rng.gen_bool(self.edge) {
+            self.counter()
+        } else {
+            self.rng.sample(&self.weights)
+        }
+    }
+
+    fn move_distribution(&self, _history: &[Round]) -> Option<MoveDistribution> {
+        let counter = self.counter();
+        let odds = self
+            .weights
+            .probabilities()
+            .into_iter()
+            .map(|(m, p)| (m, (1.0 - self.edge) * p + if m == counter { self.edge } else { 0.0 }))
+            .collect();
+        Some(odds)
+    }
+}
+
+/**
+ * Hands each round to one of several opponents, picked at random by its
+ * share. Every opponent sees the whole history, not just the rounds it
+ * played.
+ */
+pub struct BlendedOpponent<R> {
+    parts: Vec<(f64, Box<dyn Opponent>)>,
+    pick: WeightedIndex<f64>,
+    rng: R
+}
+
+impl<R: Rng> BlendedOpponent<R> {
+    /**
+     * Panics unless the shares are non-negative and add up to more than zero.
+     */
+    pub fn new(parts: Vec<(f64, Box<dyn Opponent>)>, rng: R) -> BlendedOpponent<R> {
+        let pick = WeightedIndex::new(parts.iter().map(|(share, _)| *share)).expect("a blend needs positive shares");
+        BlendedOpponent { parts, pick, rng }
+    }
+}
+
+impl<R: Rng> Opponent for BlendedOpponent<R> {
+    fn next_move(&mut self, history: &[Round]) -> Move {
+        let index = self.pick.sample(&mut self.rng);
+        self.parts[index].1.next_move(history)
+    }
+
+    fn move_distribution(&self, history: &[Round]) -> Option<MoveDistribution> {
+        let total : f64 = self.parts.iter().map(|(share, _)| share).sum();
+        let mut blended : MoveDistribution = Vec::new();
+        for (share, part) in &self.parts {
+            for (m, p) in part.move_distribution(history)? {
+                match blended.iter_mut().find(|(seen, _)| *seen == m) {
+                    Some((_, sum)) => *sum += share / total * p,
+                    None => blended.push((m, share / total * p)),
+                }
+            }
+        }
+        Some(blended)
+    }
+}
+
+/**
+ * Plays a fixed sequence of moves, starting over once it runs out. Mostly
+ * useful for tests that need to know exactly what the opponent will do. It
+ * reports no move distribution: every draw changes what comes next.
+ */
+pub struct ScriptedOpponent {
+    moves: Vec<Move>,
+    next: usize
+}
+
+impl ScriptedOpponent {
+    /**
+     * Panics if `moves` is empty, since there would be nothing to play.
+     */
+    pub fn new(moves: Vec<Move>) -> ScriptedOpponent {
+        assert!(!moves.is_empty(), "a scripted opponent needs at least one move");
+        ScriptedOpponent { moves, next: 0 }
+    }
+
+    /**
+     * Reads the script from one character per move, e.g. "rpsr", using the
+     * same characters as `Move::try_from(char)`. Every move must belong to
+     * `variant`, and the script can't be empty. As with `Variant::parse_move`,
+     * a bare "s" is rejected as ambiguous under lizard-spock.
+     */
+    pub fn parse(variant: Variant, sequence: &str) -> Result<ScriptedOpponent, ParseMoveError> {
+        let moves = sequence
+            .chars()
+            .map(|c| match (variant.ambiguous(&c.to_string()), Move::try_from(c)) {
+                (Some(candidates), _) => Err(ParseMoveError { input: c.to_string(), candidates: candidates.to_vec() }),
+                (None, Ok(m)) if variant.moves().contains(&m) => Ok(m),
+                _ => Err(ParseMoveError::new(c.to_string())),
+            })
+            .collect::<Result<Vec<Move>, ParseMoveError>>()?;
+        if moves.is_empty() {
+            return Err(ParseMoveError::new(sequence.to_string()));
+        }
+        Ok(ScriptedOpponent::new(moves))
+    }
+}
+
+impl Opponent for ScriptedOpponent {
+    fn next_move(&mut self, _history: &[Round]) -> Move {
+        let chosen = self.moves[self.next];
+        self.next = (self.next + 1) % self.moves.len();
+        chosen
+    }
+
+    fn move_distribution(&self, _history: &[Round]) -> Option<MoveDistribution> {
+        Some(vec![(self.moves[self.next], 1.0)])
+    }
+}
+
+/**
+ * How likely the opponent is to pick each move of a variant when it plays
+ * randomly. Uniform unless weights are given.
+ */
+#[derive(Debug, Clone)]
+pub struct MoveWeights {
+    variant: Variant,
+    index: Option<WeightedIndex<f64>>,
+    weights: Option<Vec<f64>>
+}
+
+impl MoveWeights {
+    /**
+     * Every move of the variant is equally likely.
+     */
+    pub fn uniform(variant: Variant) -> MoveWeights {
+        MoveWeights { variant, index: None, weights: None }
+    }
+
+    /**
+     * One weight per move of the variant, in the order of `Variant::moves`.
+     * Weights must be non-negative and at least one must be positive.
+     */
+    pub fn new(variant: Variant, weights: &[f64]) -> Result<MoveWeights, WeightsError> {
+        let expected = variant.moves().len();
+        if weights.len() != expected {
+            return Err(WeightsError::WrongCount { expected, found: weights.len() });
+        }
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err(WeightsError::Negative);
+        }
+        let index = WeightedIndex::new(weights).map_err(|_| WeightsError::AllZero)?;
+        Ok(MoveWeights { variant, index: Some(index), weights: Some(weights.to_vec()) })
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /**
+     * The chance of each move of the variant being drawn.
+     */
+    pub fn probabilities(&self) -> MoveDistribution {
+        let moves = self.variant.moves();
+        let weights = match &self.weights {
+            None => vec![1.0; moves.len()],
+            Some(weights) => weights.clone(),
+        };
+        let total : f64 = weights.iter().sum();
+        moves.iter().zip(weights).map(|(m, w)| (*m, w / total)).collect()
+    }
+}
+
+impl Distribution<Move> for MoveWeights {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Move {
+        match &self.index {
+            None => self.variant.sample(rng),
+            Some(index) => self.variant.moves()[index.sample(rng)],
+        }
+    }
+}
+
+/**
+ * Returned when opponent weights don't describe a usable distribution.
+ */
+#[derive(Debug, PartialEq)]
+pub enum WeightsError {
+    /// There must be exactly one weight per move.
+    WrongCount { expected: usize, found: usize },
+    /// A weight was negative or not a number.
+    Negative,
+    /// Every weight was zero, so no move could ever be picked.
+    AllZero
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::WrongCount { expected, found } => {
+                write!(f, "Invalid weights: expected {} weights, found {}", expected, found)
+            }
+            WeightsError::Negative => write!(f, "Invalid weights: weights must be non-negative numbers"),
+            WeightsError::AllZero => write!(f, "Invalid weights: at least one weight must be positive"),
+        }
+    }
+}
+
+impl Error for WeightsError {}
+
+/**
+ * The move the user has played most often, preferring the earlier move in
+ * the variant's order on a draw. `None` if there is no history yet.
+ */
+fn most_frequent_user_move(variant: Variant, history: &[Round]) -> Option<&'static Move> {
+    if history.is_empty() {
+        return None;
+    }
+    let count = |m: &Move| history.iter().filter(|round| &round.user_move == m).count();
+    variant.moves().iter().fold(None, |best: Option<&Move>, m| match best {
+        Some(b) if count(b) >= count(m) => Some(b),
+        _ => Some(m),
+    })
+}
+
+/**
+ * The move the user has most often followed their last move with, by
+ * counting the transitions between consecutive rounds: a first-order Markov
+ * model of their play. Prefers the earlier move in the variant's order on a
+ * draw. `None` until the user's last move has been followed by anything.
+ */
+fn likely_next_user_move(variant: Variant, history: &[Round]) -> Option<&'static Move> {
+    let last = history.last()?.user_move;
+    let followers: Vec<Move> = history.windows(2).filter(|pair| pair[0].user_move == last).map(|pair| pair[1].user_move).collect();
+    if followers.is_empty() {
+        return None;
+    }
+    let count = |m: &Move| followers.iter().filter(|follower| *follower == m).count();
+    variant.moves().iter().fold(None, |best: Option<&Move>, m| match best {
+        Some(b) if count(b) >= count(m) => Some(b),
+        _ => Some(m),
+    })
+}
+
+/**
+ * The moves still in `Strategy::ShuffleBag`'s current bag. The bag is worked
+ * out from the opponent's past moves rather than kept, so it survives the
+ * opponent being rebuilt every round: a new bag starts every
+ * `variant.moves().len()` rounds, and a draw without replacement from what is
+ * left plays out the same as shuffling the bag up front.
+ */
+fn left_in_bag(variant: Variant, history: &[Round]) -> Vec<Move> {
+    let drawn = &history[history.len() - history.len() % variant.moves().len()..];
+    variant.moves().iter().filter(|m| !drawn.iter().any(|round| round.opponent_move == **m)).copied().collect()
+}
+
+/**
+ * The odds `Strategy::Adaptive` plays with: `weights`, moved
+ * `ADAPTATION_RATE` of the way toward the counter of the user's move after
+ * each round the user won. Later wins count for more than earlier ones.
+ */
+fn adaptive_distribution(weights: &MoveWeights, history: &[Round]) -> MoveDistribution {
+    let mut odds = weights.probabilities();
+    for round in history.iter().filter(|round| round.result == GameResult::UserWin) {
+        let counter = round.user_move.counters(weights.variant())[0];
+        for (m, p) in odds.iter_mut() {
+            let target = if *m == counter { 1.0 } else { 0.0 };
+            *p += ADAPTATION_RATE * (target - *p);
+        }
+    }
+    odds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use crate::GameRng;
+
+    fn history(user_moves: &[Move]) -> Vec<Round> {
+        user_moves.iter().map(|m| Round::new(*m, Move::Rock)).collect()
+    }
+
+    fn assert_close(actual: &[(Move, f64)], expected: &[(Move, f64)]){
+        assert_eq!(actual.len(), expected.len());
+        for ((m, p), (em, ep)) in actual.iter().zip(expected) {
+            assert_eq!(m, em);
+            assert!((p - ep).abs() < 1e-9, "{}: {} != {}", m, p, ep)
+        }
+    }
+
+    #[test]
+    fn test_random_matches_variant_sampling(){
+        let mut rng = GameRng::seed_from_u64(1);
+        let chosen = Strategy::Random.next_move(&MoveWeights::uniform(Variant::Classic), &[], &mut rng);
+        assert_eq!(chosen, Move::Paper)
+    }
+
+    #[test]
+    fn test_frequency_without_history_is_random(){
+        let mut rng = GameRng::seed_from_u64(1);
+        let chosen = Strategy::Frequency.next_move(&MoveWeights::uniform(Variant::Classic), &[], &mut rng);
+        assert_eq!(chosen, Move::Paper)
+    }
+
+    #[test]
+    fn test_frequency_counters_rock_heavy_history(){
+        let mut rng = GameRng::seed_from_u64(7);
+        let past = history(&[Move::Rock, Move::Scissors, Move::Rock, Move::Paper, Move::Rock]);
+        assert_eq!(Strategy::Frequency.next_move(&MoveWeights::uniform(Variant::Classic), &past, &mut rng), Move::Paper)
+    }
+
+    #[test]
+    fn test_frequency_counters_scissors_heavy_history(){
+        let mut rng = GameRng::seed_from_u64(7);
+        let past = history(&[Move::Scissors, Move::Scissors, Move::Paper]);
+        assert_eq!(Strategy::Frequency.next_move(&MoveWeights::uniform(Variant::Classic), &past, &mut rng), Move::Rock)
+    }
+
+    #[test]
+    fn test_frequency_counters_within_variant(){
+        let mut rng = GameRng::seed_from_u64(7);
+        let past = history(&[Move::Spock, Move::Spock, Move::Rock]);
+        let chosen = Strategy::Frequency.next_move(&MoveWeights::uniform(Variant::LizardSpock), &past, &mut rng);
+        assert!(chosen.beats(&Move::Spock))
+    }
+
+    /** ==== Win-stay, lose-shift ==== **/
+
+    #[test]
+    fn test_win_stay_lose_shift_scripted_sequence(){
+        let mut opponent = WinStayLoseShiftOpponent::new(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(1));
+        let user_moves = [Move::Scissors, Move::Rock, Move::Rock, Move::Paper, Move::Paper];
+        // Round one is random (Paper for this seed). The user's scissors win, so
+        // the opponent shifts to rock; rock against rock ties twice, so it
+        // stays; paper then beats it, so it shifts to scissors.
+        let expected = [Move::Paper, Move::Rock, Move::Rock, Move::Rock, Move::Scissors];
+        let mut history = Vec::new();
+        for (user_move, expected_move) in user_moves.iter().zip(expected.iter()) {
+            let chosen = opponent.next_move(&history);
+            assert_eq!(&chosen, expected_move);
+            history.push(Round::new(*user_move, chosen));
+        }
+    }
+
+    #[test]
+    fn test_win_stay_after_opponent_win(){
+        let mut rng = GameRng::seed_from_u64(7);
+        let past = [Round::new(Move::Rock, Move::Paper)];
+        assert_eq!(Strategy::WinStayLoseShift.next_move(&MoveWeights::uniform(Variant::Classic), &past, &mut rng), Move::Paper)
+    }
+
+    #[test]
+    fn test_lose_shift_within_variant(){
+        let mut rng = GameRng::seed_from_u64(7);
+        let past = [Round::new(Move::Spock, Move::Rock)];
+        let chosen = Strategy::WinStayLoseShift.next_move(&MoveWeights::uniform(Variant::LizardSpock), &past, &mut rng);
+        assert!(chosen.beats(&Move::Spock))
+    }
+
+    /** ==== Opponents ==== **/
+
+    #[test]
+    fn test_random_opponent_matches_strategy(){
+        let mut opponent = RandomOpponent::new(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(1));
+        assert_eq!(opponent.next_move(&[]), Move::Paper)
+    }
+
+    #[test]
+    fn test_frequency_opponent_counters_history(){
+        let mut opponent = FrequencyOpponent::new(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(7));
+        assert_eq!(opponent.next_move(&history(&[Move::Rock, Move::Rock])), Move::Paper)
+    }
+
+    #[test]
+    fn test_constant_opponent(){
+        let mut opponent = ConstantOpponent(Move::Rock);
+        assert_eq!(opponent.next_move(&[]), Move::Rock);
+        assert_eq!(opponent.next_move(&history(&[Move::Paper])), Move::Rock)
+    }
+
+    #[test]
+    fn test_house_edge_counters_at_expected_rate(){
+        // The counter comes from the edge or from a fair draw: 0.2 + 0.8 / 3.
+        let mut opponent = HouseEdgeOpponent::new(Move::Rock, 0.2, MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(7));
+        let trials = 20_000;
+        let counters = (0..trials).filter(|_| opponent.next_move(&[]) == Move::Paper).count();
+        let rate = counters as f64 / f64::from(trials);
+        assert!((rate - (0.2 + 0.8 / 3.0)).abs() < 0.01, "{}", rate)
+    }
+
+    #[test]
+    fn test_house_edge_distribution(){
+        let opponent = HouseEdgeOpponent::new(Move::Rock, 0.4, MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(7));
+        let odds = opponent.move_distribution(&[]).unwrap();
+        assert!((odds[1].1 - 0.6).abs() < 1e-9);
+        assert!((odds[0].1 - 0.2).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_house_edge_of_one_always_counters(){
+        let mut opponent = HouseEdgeOpponent::new(Move::Spock, 1.0, MoveWeights::uniform(Variant::LizardSpock), GameRng::seed_from_u64(7));
+        assert!((0..50).all(|_| opponent.next_move(&[]).beats(&Move::Spock)))
+    }
+
+    /** ==== Weights ==== **/
+
+    #[test]
+    fn test_weights_skew_the_distribution(){
+        let weights = MoveWeights::new(Variant::Classic, &[3.0, 1.0, 1.0]).unwrap();
+        let mut rng = GameRng::seed_from_u64(7);
+        let samples : Vec<Move> = (0..10_000).map(|_| rng.sample(&weights)).collect();
+        let rocks = samples.iter().filter(|m| **m == Move::Rock).count() as f64;
+        let papers = samples.iter().filter(|m| **m == Move::Paper).count() as f64;
+        assert!((rocks / 10_000.0 - 0.6).abs() < 0.02);
+        assert!((papers / 10_000.0 - 0.2).abs() < 0.02)
+    }
+
+    #[test]
+    fn test_zero_weight_is_never_picked(){
+        let weights = MoveWeights::new(Variant::LizardSpock, &[1.0, 1.0, 1.0, 1.0, 0.0]).unwrap();
+        let mut rng = GameRng::seed_from_u64(7);
+        assert!((0..1_000).all(|_| rng.sample(&weights) != Move::Spock))
+    }
+
+    #[test]
+    fn test_uniform_weights_match_variant_sampling(){
+        let weights = MoveWeights::uniform(Variant::Classic);
+        for seed in 0..20 {
+            let expected : Move = GameRng::seed_from_u64(seed).sample(Variant::Classic);
+            assert_eq!(GameRng::seed_from_u64(seed).sample(&weights), expected)
+        }
+    }
+
+    #[test]
+    fn test_weights_wrong_count(){
+        assert_eq!(MoveWeights::new(Variant::Classic, &[1.0, 1.0]).unwrap_err(), WeightsError::WrongCount { expected: 3, found: 2 });
+        assert_eq!(MoveWeights::new(Variant::LizardSpock, &[1.0, 1.0, 1.0]).unwrap_err(), WeightsError::WrongCount { expected: 5, found: 3 })
+    }
+
+    #[test]
+    fn test_weights_negative(){
+        assert_eq!(MoveWeights::new(Variant::Classic, &[1.0, -1.0, 1.0]).unwrap_err(), WeightsError::Negative)
+    }
+
+    #[test]
+    fn test_weights_all_zero(){
+        assert_eq!(MoveWeights::new(Variant::Classic, &[0.0, 0.0, 0.0]).unwrap_err(), WeightsError::AllZero)
+    }
+
+    #[test]
+    fn test_always_strategies_ignore_history(){
+        let weights = MoveWeights::uniform(Variant::Classic);
+        let mut rng = GameRng::seed_from_u64(7);
+        let cases = [(Strategy::AlwaysRock, Move::Rock), (Strategy::AlwaysPaper, Move::Paper), (Strategy::AlwaysScissors, Move::Scissors)];
+        for (strategy, expected) in cases {
+            let mut opponent = strategy.opponent(weights.clone(), GameRng::seed_from_u64(7));
+            let mut past = Vec::new();
+            for user_move in [Move::Rock, Move::Paper, Move::Scissors, Move::Paper] {
+                assert_eq!(strategy.next_move(&weights, &past, &mut rng), expected);
+                let chosen = opponent.next_move(&past);
+                assert_eq!(chosen, expected);
+                past.push(Round::new(user_move, chosen));
+            }
+        }
+    }
+
+    #[test]
+    fn test_always_strategy_distribution(){
+        let distribution = Strategy::AlwaysPaper.move_distribution(&MoveWeights::uniform(Variant::Classic), &[]);
+        assert_eq!(best_reply(Variant::Classic, &distribution), Some(Move::Scissors))
+    }
+
+    #[test]
+    fn test_scripted_opponent_repeats_its_script(){
+        let mut opponent = ScriptedOpponent::new(vec![Move::Rock, Move::Scissors]);
+        let moves : Vec<Move> = (0..5).map(|_| opponent.next_move(&[])).collect();
+        assert_eq!(moves, [Move::Rock, Move::Scissors, Move::Rock, Move::Scissors, Move::Rock])
+    }
+
+    #[test]
+    fn test_scripted_opponent_distribution_is_its_next_move(){
+        let mut opponent = ScriptedOpponent::new(vec![Move::Rock, Move::Scissors]);
+        opponent.next_move(&[]);
+        let distribution = opponent.move_distribution(&[]).unwrap();
+        assert_eq!(best_reply(Variant::Classic, &distribution), Some(Move::Rock))
+    }
+
+    #[test]
+    fn test_scripted_opponent_from_sequence(){
+        let mut opponent = ScriptedOpponent::parse(Variant::Classic, "rpsR").unwrap();
+        let moves : Vec<Move> = (0..5).map(|_| opponent.next_move(&[])).collect();
+        assert_eq!(moves, [Move::Rock, Move::Paper, Move::Scissors, Move::Rock, Move::Rock])
+    }
+
+    #[test]
+    fn test_invalid_sequence(){
+        assert_eq!(ScriptedOpponent::parse(Variant::Classic, "rpx").err(), Some(ParseMoveError::new(String::from("x"))));
+        assert_eq!(ScriptedOpponent::parse(Variant::Classic, "r🦎").err(), Some(ParseMoveError::new(String::from("🦎"))));
+        assert_eq!(ScriptedOpponent::parse(Variant::Classic, "").err(), Some(ParseMoveError::new(String::new())))
+    }
+
+    #[test]
+    fn test_sequence_s_is_ambiguous_under_lizard_spock(){
+        let err = ScriptedOpponent::parse(Variant::LizardSpock, "rsk").err().unwrap();
+        assert_eq!(err.input, "s");
+        assert_eq!(err.candidates, [Move::Scissors, Move::Spock]);
+        let mut opponent = ScriptedOpponent::parse(Variant::LizardSpock, "r✌k").unwrap();
+        let moves : Vec<Move> = (0..3).map(|_| opponent.next_move(&[])).collect();
+        assert_eq!(moves, [Move::Rock, Move::Scissors, Move::Spock])
+    }
+
+    #[test]
+    fn test_strategy_opponent_matches_strategy(){
+        for strategy in Strategy::value_variants() {
+            let weights = MoveWeights::uniform(Variant::Classic);
+            let past = history(&[Move::Scissors, Move::Scissors]);
+            let mut opponent = strategy.opponent(weights.clone(), GameRng::seed_from_u64(7));
+            let expected = strategy.next_move(&weights, &past, &mut GameRng::seed_from_u64(7));
+            assert_eq!(opponent.next_move(&past), expected)
+        }
+    }
+
+    /** ==== Predictions ==== **/
+
+    #[test]
+    fn test_uniform_opponent_win_probabilities(){
+        let opponent = RandomOpponent::new(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(7));
+        let distribution = opponent.move_distribution(&[]).unwrap();
+        let third = 1.0 / 3.0;
+        assert_close(&win_probabilities(Variant::Classic, &distribution), &[(Move::Rock, third), (Move::Paper, third), (Move::Scissors, third)])
+    }
+
+    #[test]
+    fn test_biased_opponent_win_probabilities(){
+        // Rock three times as likely: paper wins 60% of the time.
+        let weights = MoveWeights::new(Variant::Classic, &[3.0, 1.0, 1.0]).unwrap();
+        let opponent = RandomOpponent::new(weights, GameRng::seed_from_u64(7));
+        let distribution = opponent.move_distribution(&[]).unwrap();
+        assert_close(&win_probabilities(Variant::Classic, &distribution), &[(Move::Rock, 0.2), (Move::Paper, 0.6), (Move::Scissors, 0.2)])
+    }
+
+    #[test]
+    fn test_frequency_opponent_win_probabilities(){
+        let opponent = FrequencyOpponent::new(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(7));
+        let distribution = opponent.move_distribution(&history(&[Move::Rock, Move::Rock])).unwrap();
+        assert_close(&distribution, &[(Move::Rock, 0.0), (Move::Paper, 1.0), (Move::Scissors, 0.0)]);
+        assert_close(&win_probabilities(Variant::Classic, &distribution), &[(Move::Rock, 0.0), (Move::Paper, 0.0), (Move::Scissors, 1.0)])
+    }
+
+    #[test]
+    fn test_lizard_spock_uniform_win_probabilities(){
+        let distribution = Strategy::Random.move_distribution(&MoveWeights::uniform(Variant::LizardSpock), &[]);
+        assert!(win_probabilities(Variant::LizardSpock, &distribution).iter().all(|(_, p)| (p - 0.4).abs() < 1e-9))
+    }
+
+    /** ==== Hints ==== **/
+
+    #[test]
+    fn test_no_hint_against_uniform_opponent(){
+        let distribution = Strategy::Random.move_distribution(&MoveWeights::uniform(Variant::Classic), &[]);
+        assert_eq!(best_reply(Variant::Classic, &distribution), None)
+    }
+
+    #[test]
+    fn test_hint_counters_biased_opponent(){
+        let weights = MoveWeights::new(Variant::Classic, &[1.0, 1.0, 3.0]).unwrap();
+        let distribution = Strategy::Random.move_distribution(&weights, &[]);
+        assert_eq!(best_reply(Variant::Classic, &distribution), Some(Move::Scissors.counter()))
+    }
+
+    #[test]
+    fn test_hint_counters_frequency_prediction(){
+        let past = history(&[Move::Paper, Move::Paper, Move::Rock]);
+        let distribution = Strategy::Frequency.move_distribution(&MoveWeights::uniform(Variant::Classic), &past);
+        // The opponent will throw scissors to beat the user's paper.
+        assert_eq!(best_reply(Variant::Classic, &distribution), Some(Move::Rock))
+    }
+
+    #[test]
+    fn test_markov_counters_patterned_user(){
+        // Rock is always followed by paper, so after rock expect paper and
+        // play scissors.
+        let past = history(&[Move::Rock, Move::Paper, Move::Scissors, Move::Rock, Move::Paper, Move::Scissors, Move::Rock]);
+        let mut rng = GameRng::seed_from_u64(7);
+        assert_eq!(Strategy::Markov.next_move(&MoveWeights::uniform(Variant::Classic), &past, &mut rng), Move::Scissors)
+    }
+
+    #[test]
+    fn test_markov_follows_most_common_transition(){
+        // After paper the user threw rock twice and scissors once.
+        let past = history(&[Move::Paper, Move::Rock, Move::Paper, Move::Scissors, Move::Paper, Move::Rock, Move::Paper]);
+        let opponent = MarkovOpponent::new(MoveWeights::uniform(Variant::Classic), GameRng::seed_from_u64(7));
+        let distribution = opponent.move_distribution(&past).unwrap();
+        assert_close(&distribution, &[(Move::Rock, 0.0), (Move::Paper, 1.0), (Move::Scissors, 0.0)])
+    }
+
+    #[test]
+    fn test_markov_without_transitions_is_random(){
+        // Scissors has never been followed by anything yet.
+        let past = history(&[Move::Rock, Move::Rock, Move::Scissors]);
+        let weights = MoveWeights::uniform(Variant::Classic);
+        let distribution = Strategy::Markov.move_distribution(&weights, &past);
+        assert_close(&distribution, &weights.probabilities());
+        let mut markov_rng = GameRng::seed_from_u64(7);
+        let mut random_rng = GameRng::seed_from_u64(7);
+        assert_eq!(
+            Strategy::Markov.next_move(&weights, &past, &mut markov_rng),
+            Strategy::Random.next_move(&weights, &past, &mut random_rng)
+        )
+    }
+
+    #[test]
+    fn test_adaptive_without_wins_keeps_its_weights(){
+        let weights = MoveWeights::new(Variant::Classic, &[3.0, 1.0, 1.0]).unwrap();
+        let past = vec![Round::new(Move::Rock, Move::Paper), Round::new(Move::Rock, Move::Rock)];
+        assert_close(&Strategy::Adaptive.move_distribution(&weights, &past), &weights.probabilities())
+    }
+
+    #[test]
+    fn test_adaptive_shifts_toward_counter_of_winning_streak(){
+        // The user keeps winning with rock, so paper should keep gaining.
+        let weights = MoveWeights::uniform(Variant::Classic);
+        let mut past = Vec::new();
+        let mut paper = 1.0 / 3.0;
+        for _ in 0..5 {
+            past.push(Round::new(Move::Rock, Move::Scissors));
+            let odds = Strategy::Adaptive.move_distribution(&weights, &past);
+            let expected = paper + ADAPTATION_RATE * (1.0 - paper);
+            assert!((odds[1].1 - expected).abs() < 1e-9, "paper {} != {}", odds[1].1, expected);
+            assert!((odds.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+            paper = expected;
+        }
+        assert_eq!(best_reply(Variant::Classic, &Strategy::Adaptive.move_distribution(&weights, &past)), Some(Move::Scissors))
+    }
+
+    #[test]
+    fn test_adaptive_opponent_reports_its_odds(){
+        // Always paper, until scissors beats it and rock starts to appear.
+        let weights = MoveWeights::new(Variant::Classic, &[0.0, 1.0, 0.0]).unwrap();
+        let past = vec![Round::new(Move::Scissors, Move::Paper)];
+        let mut opponent = AdaptiveOpponent::new(weights, GameRng::seed_from_u64(7));
+        let odds = opponent.move_distribution(&past).unwrap();
+        assert_close(&odds, &[(Move::Rock, ADAPTATION_RATE), (Move::Paper, 1.0 - ADAPTATION_RATE), (Move::Scissors, 0.0)]);
+        assert!([Move::Rock, Move::Paper].contains(&opponent.next_move(&past)))
+    }
+
+    /**
+     * Plays `rounds` rounds against a shuffle bag seeded with `seed`, the user
+     * always throwing rock.
+     */
+    fn shuffle_bag_moves(variant: Variant, seed: u64, rounds: usize) -> Vec<Move> {
+        let mut opponent = ShuffleBagOpponent::new(MoveWeights::uniform(variant), GameRng::seed_from_u64(seed));
+        let mut past = Vec::new();
+        for _ in 0..rounds {
+            let m = opponent.next_move(&past);
+            past.push(Round::new(Move::Rock, m));
+        }
+        past.iter().map(|round| round.opponent_move).collect()
+    }
+
+    #[test]
+    fn test_shuffle_bag_deals_every_move_per_bag(){
+        for seed in 0..20 {
+            for bag in shuffle_bag_moves(Variant::Classic, seed, 30).chunks(3) {
+                assert!(Variant::Classic.moves().iter().all(|m| bag.contains(m)))
+            }
+        }
+    }
+
+    #[test]
+    fn test_shuffle_bag_never_clumps(){
+        // Across a refill a move can repeat once, but never three times.
+        for seed in 0..20 {
+            let moves = shuffle_bag_moves(Variant::LizardSpock, seed, 50);
+            assert!(moves.chunks(5).all(|bag| Variant::LizardSpock.moves().iter().all(|m| bag.contains(m))));
+            assert!(moves.windows(3).all(|w| w[0] != w[1] || w[1] != w[2]))
+        }
+    }
+
+    #[test]
+    fn test_shuffle_bag_is_seeded(){
+        assert_eq!(shuffle_bag_moves(Variant::Classic, 7, 12), shuffle_bag_moves(Variant::Classic, 7, 12))
+    }
+
+    #[test]
+    fn test_shuffle_bag_odds_follow_the_bag(){
+        let past = vec![Round::new(Move::Rock, Move::Paper)];
+        let odds = Strategy::ShuffleBag.move_distribution(&MoveWeights::uniform(Variant::Classic), &past);
+        assert_close(&odds, &[(Move::Rock, 0.5), (Move::Paper, 0.0), (Move::Scissors, 0.5)])
+    }
+
+    #[test]
+    fn test_uniform_user_breaks_even(){
+        let user = MoveWeights::uniform(Variant::Classic).probabilities();
+        let opponent = MoveWeights::new(Variant::Classic, &[3.0, 1.0, 1.0]).unwrap().probabilities();
+        let rates = expected_rates(&user, &opponent);
+        assert!((rates.win - rates.loss).abs() < 1e-9);
+        assert!(rates.value().abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_expected_rates_of_biased_strategies(){
+        let user = MoveWeights::new(Variant::Classic, &[0.0, 1.0, 0.0]).unwrap().probabilities();
+        let opponent = MoveWeights::new(Variant::Classic, &[3.0, 1.0, 1.0]).unwrap().probabilities();
+        let rates = expected_rates(&user, &opponent);
+        assert!((rates.win - 0.6).abs() < 1e-9);
+        assert!((rates.loss - 0.2).abs() < 1e-9);
+        assert!((rates.tie - 0.2).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_outcome_rates_from_tally(){
+        let rates = OutcomeRates::from_tally(&Tally { user_wins: 1, opponent_wins: 2, ties: 1 });
+        assert_eq!(rates, OutcomeRates { win: 0.25, loss: 0.5, tie: 0.25 });
+        assert_eq!(rates.value(), -0.25);
+        assert_eq!(OutcomeRates::from_tally(&Tally::default()).value(), 0.0)
+    }
+
+    /** How many of `rounds` a player cycling rock, paper, scissors loses to `opponent`. */
+    fn losses_of_cycling_player(opponent: &mut dyn Opponent, rounds: usize) -> usize {
+        let cycle = [Move::Rock, Move::Paper, Move::Scissors];
+        let mut past = Vec::new();
+        for i in 0..rounds {
+            let their_move = opponent.next_move(&past);
+            past.push(Round::new(cycle[i % 3], their_move));
+        }
+        past.iter().filter(|round| round.result == GameResult::OpponentWin).count()
+    }
+
+    #[test]
+    fn test_easy_difficulty_is_random(){
+        let weights = MoveWeights::uniform(Variant::Classic);
+        let mut easy = Difficulty::Easy.opponent(weights.clone(), GameRng::seed_from_u64(1));
+        let mut random = Strategy::Random.opponent(weights, GameRng::seed_from_u64(1));
+        let moves : Vec<Move> = (0..20).map(|_| easy.next_move(&[])).collect();
+        assert_eq!(moves, (0..20).map(|_| random.next_move(&[])).collect::<Vec<Move>>())
+    }
+
+    #[test]
+    fn test_hard_difficulty_counters_patterns_more_than_easy(){
+        let weights = MoveWeights::uniform(Variant::Classic);
+        let easy = losses_of_cycling_player(Difficulty::Easy.opponent(weights.clone(), GameRng::seed_from_u64(7)).as_mut(), 300);
+        let medium = losses_of_cycling_player(Difficulty::Medium.opponent(weights.clone(), GameRng::seed_from_u64(7)).as_mut(), 300);
+        let hard = losses_of_cycling_player(Difficulty::Hard.opponent(weights, GameRng::seed_from_u64(7)).as_mut(), 300);
+        assert!(easy < 130, "easy won {} of 300", easy);
+        assert!(hard > 200, "hard won {} of 300", hard);
+        assert!(medium < hard, "medium won {}, hard {}", medium, hard)
+    }
+
+    #[test]
+    fn test_medium_difficulty_distribution_blends_its_strategies(){
+        let weights = MoveWeights::uniform(Variant::Classic);
+        let past = history(&[Move::Rock, Move::Rock]);
+        let medium = Difficulty::Medium.opponent(weights, GameRng::seed_from_u64(7));
+        let third = 1.0 / 3.0;
+        let expected = [(Move::Rock, third / 2.0), (Move::Paper, third / 2.0 + 0.5), (Move::Scissors, third / 2.0)];
+        assert_close(&medium.move_distribution(&past).unwrap(), &expected)
+    }
+
+    #[test]
+    #[should_panic(expected = "a blend needs positive shares")]
+    fn test_blend_needs_a_share(){
+        BlendedOpponent::new(vec![(0.0, Box::new(ConstantOpponent(Move::Rock)))], GameRng::seed_from_u64(7));
+    }
+
+    #[test]
+    fn test_hint_with_two_likely_moves(){
+        // Rock or paper: paper wins or ties, so it beats the alternatives.
+        let weights = MoveWeights::new(Variant::Classic, &[1.0, 1.0, 0.0]).unwrap();
+        let distribution = Strategy::Random.move_distribution(&weights, &[]);
+        assert_eq!(best_reply(Variant::Classic, &distribution), Some(Move::Paper))
+    }
+}
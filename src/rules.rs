@@ -0,0 +1,574 @@
+/*!
+ * Custom rule sets: any number of moves and an arbitrary "beats" relation,
+ * loaded from a TOML file.
+ *
+ * ```toml
+ * moves = ["Rock", "Paper", "Scissors", "Well"]
+ * ties = [["Rock", "Scissors"]]
+ *
+ * [beats]
+ * Rock = []
+ * Paper = ["Rock", "Well"]
+ * Scissors = ["Paper"]
+ * Well = ["Rock", "Scissors"]
+ * ```
+ *
+ * Every pair of distinct moves must be settled, either by one of them beating
+ * the other or by being listed under `ties`.
+ */
+
+use std::{cmp::Ordering, collections::BTreeMap, error::Error, fmt, fs, path::Path, sync::atomic::{self, AtomicUsize}};
+
+use rand::{distributions::Distribution, Rng};
+use serde::Deserialize;
+
+use crate::{GameResult, ParseMoveError, Tally, Variant};
+
+/**
+ * A move of a `Ruleset`, identified by its position in the rule set's list
+ * of moves. Only meaningful together with the rule set it came from, which
+ * it remembers so no other rule set will score it.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynamicMove {
+    ruleset: usize,
+    index: usize
+}
+
+impl DynamicMove {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/**
+ * A validated set of moves and who beats whom.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ruleset {
+    // Stamped on every move, so no other rule set scores it. Clones share it.
+    id: usize,
+    names: Vec<String>,
+    // relation[a][b] is Greater when move a beats move b.
+    relation: Vec<Vec<Ordering>>
+}
+
+/** The id the next rule set is given. */
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Deserialize)]
+struct RulesFile {
+    moves: Vec<String>,
+    #[serde(default)]
+    beats: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    ties: Vec<[String; 2]>
+}
+
+impl Ruleset {
+    /**
+     * Builds a rule set from its moves, the (winner, loser) pairs and the
+     * pairs that tie, checking that every matchup is settled exactly once.
+     */
+    pub fn new(names: Vec<String>, beats: &[(String, String)], ties: &[(String, String)]) -> Result<Ruleset, RulesError> {
+        if names.len() < 2 {
+            return Err(RulesError::TooFewMoves);
+        }
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].iter().any(|other| other.eq_ignore_ascii_case(name)) {
+                return Err(RulesError::DuplicateMove(name.clone()));
+            }
+        }
+        let index = |name: &str| {
+            names
+                .iter()
+                .position(|n| n.eq_ignore_ascii_case(name))
+                .ok_or_else(|| RulesError::UnknownMove(name.to_string()))
+        };
+
+        let mut relation : Vec<Vec<Option<Ordering>>> = vec![vec![None; names.len()]; names.len()];
+        for (i, row) in relation.iter_mut().enumerate() {
+            row[i] = Some(Ordering::Equal);
+        }
+        for (winner, loser) in beats {
+            let (w, l) = (index(winner)?, index(loser)?);
+            if w == l {
+                return Err(RulesError::SelfBeat(names[w].clone()));
+            }
+            settle(&mut relation, &names, w, l, Ordering::Greater)?;
+        }
+        for (a, b) in ties {
+            let (a, b) = (index(a)?, index(b)?);
+            if a != b {
+                settle(&mut relation, &names, a, b, Ordering::Equal)?;
+            }
+        }
+
+        let mut settled = Vec::with_capacity(names.len());
+        for (a, row) in relation.iter().enumerate() {
+            let mut settled_row = Vec::with_capacity(names.len());
+            for (b, cell) in row.iter().enumerate() {
+                match cell {
+                    Some(ordering) => settled_row.push(*ordering),
+                    None => return Err(RulesError::Unresolved(names[a].clone(), names[b].clone())),
+                }
+            }
+            settled.push(settled_row);
+        }
+        Ok(Ruleset::with_relation(names, settled))
+    }
+
+    fn with_relation(names: Vec<String>, relation: Vec<Vec<Ordering>>) -> Ruleset {
+        Ruleset { id: NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed), names, relation }
+    }
+
+    /**
+     * Parses and validates a rule set written in TOML.
+     */
+    pub fn parse(source: &str) -> Result<Ruleset, RulesError> {
+        let file : RulesFile = toml::from_str(source).map_err(|err| RulesError::Syntax(err.message().to_string()))?;
+        let beats : Vec<(String, String)> = file
+            .beats
+            .into_iter()
+            .flat_map(|(winner, losers)| losers.into_iter().map(move |loser| (winner.clone(), loser)))
+            .collect();
+        let ties : Vec<(String, String)> = file.ties.into_iter().map(|[a, b]| (a, b)).collect();
+        Ruleset::new(file.moves, &beats, &ties)
+    }
+
+    /**
+     * Reads a rule set from a TOML file, see `Ruleset::parse`.
+     */
+    pub fn load(path: &Path) -> Result<Ruleset, RulesError> {
+        let source = fs::read_to_string(path).map_err(|err| RulesError::Io(format!("{}: {}", path.display(), err)))?;
+        Ruleset::parse(&source)
+    }
+
+    /**
+     * The rules of one of the built-in variants.
+     */
+    pub fn from_variant(variant: Variant) -> Ruleset {
+        let moves = variant.moves();
+        let names = moves.iter().map(|m| m.to_string()).collect();
+        let relation = moves
+            .iter()
+            .map(|a| {
+                moves
+                    .iter()
+                    .map(|b| if a.beats(b) { Ordering::Greater } else if b.beats(a) { Ordering::Less } else { Ordering::Equal })
+                    .collect()
+            })
+            .collect();
+        Ruleset::with_relation(names, relation)
+    }
+
+    /**
+     * Every move, in the order they were declared.
+     */
+    pub fn moves(&self) -> impl Iterator<Item = DynamicMove> {
+        let ruleset = self.id;
+        (0..self.names.len()).map(move |index| DynamicMove { ruleset, index })
+    }
+
+    pub fn name(&self, m: DynamicMove) -> &str {
+        &self.names[m.index]
+    }
+
+    /**
+     * Finds a move by name, ignoring case.
+     */
+    pub fn parse_move(&self, s: &str) -> Result<DynamicMove, ParseMoveError> {
+        self.names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(s.trim()))
+            .map(|index| DynamicMove { ruleset: self.id, index })
+            .ok_or_else(|| ParseMoveError::new(s.to_string()))
+    }
+
+    pub fn beats(&self, a: DynamicMove, b: DynamicMove) -> bool {
+        self.relation[a.index][b.index] == Ordering::Greater
+    }
+
+    /**
+     * The whole beats relation in declaration order, e.g. "Rock beats
+     * Scissors; Paper beats Rock; Scissors beats Paper".
+     */
+    pub fn describe(&self) -> String {
+        let pairs: Vec<String> = self
+            .moves()
+            .flat_map(|a| self.moves().filter(move |b| self.beats(a, *b)).map(move |b| (a, b)))
+            .map(|(a, b)| format!("{} beats {}", self.name(a), self.name(b)))
+            .collect();
+        pairs.join("; ")
+    }
+
+    /**
+     * Why the round ended the way it did, e.g. "Well beats Rock, so you win."
+     */
+    pub fn explain(&self, user: DynamicMove, opponent: DynamicMove) -> String {
+        match self.outcome(user, opponent) {
+            GameResult::UserWin => format!("{} beats {}, so you win.", self.name(user), self.name(opponent)),
+            GameResult::OpponentWin => format!("{} beats {}, so you lose.", self.name(opponent), self.name(user)),
+            GameResult::Tie if user == opponent => format!("You both played {}, so it's a tie.", self.name(user)),
+            GameResult::Tie => format!("{} ties with {}, so it's a tie.", self.name(user), self.name(opponent)),
+        }
+    }
+
+    /**
+     * Scores a round from the user's point of view. Panics if either move
+     * comes from another rule set, see `calculate_winner`.
+     */
+    pub fn outcome(&self, user: DynamicMove, opponent: DynamicMove) -> GameResult {
+        self.calculate_winner(user, opponent).expect("both moves belong to this rule set")
+    }
+
+    /**
+     * Scores a round from the user's point of view. Every matchup between
+     * this rule set's own moves is settled, so the only error is a move
+     * taken from a different rule set, even one whose number this rule set
+     * also has.
+     */
+    pub fn calculate_winner(&self, user: DynamicMove, opponent: DynamicMove) -> Result<GameResult, RulesError> {
+        let ordering = self
+            .relation
+            .get(self.own(user)?)
+            .ok_or(RulesError::ForeignMove(user.index))?
+            .get(self.own(opponent)?)
+            .ok_or(RulesError::ForeignMove(opponent.index))?;
+        Ok(match ordering {
+            Ordering::Greater => GameResult::UserWin,
+            Ordering::Less => GameResult::OpponentWin,
+            Ordering::Equal => GameResult::Tie,
+        })
+    }
+
+    /**
+     * The index of `m`, if it is one of this rule set's moves.
+     */
+    fn own(&self, m: DynamicMove) -> Result<usize, RulesError> {
+        if m.ruleset == self.id {
+            Ok(m.index)
+        } else {
+            Err(RulesError::ForeignMove(m.index))
+        }
+    }
+
+    /**
+     * Plays every move against every move and checks that the relation holds
+     * together: each move ties with itself, and every other matchup has
+     * exactly one winner or is a tie both ways round. Returns a description
+     * of each problem found.
+     */
+    pub fn self_test(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for a in self.moves() {
+            for b in self.moves() {
+                let (forward, backward) = match (self.calculate_winner(a, b), self.calculate_winner(b, a)) {
+                    (Ok(forward), Ok(backward)) => (forward, backward),
+                    (Err(err), _) | (_, Err(err)) => {
+                        problems.push(format!("{} against {} can't be scored: {}", self.name(a), self.name(b), err));
+                        continue;
+                    }
+                };
+                if a == b && forward != GameResult::Tie {
+                    problems.push(format!("{} doesn't tie with itself", self.name(a)));
+                }
+                let mirrored = matches!(
+                    (&forward, &backward),
+                    (GameResult::UserWin, GameResult::OpponentWin) | (GameResult::OpponentWin, GameResult::UserWin) | (GameResult::Tie, GameResult::Tie)
+                );
+                if a.index < b.index && !mirrored {
+                    problems.push(format!(
+                        "{} against {} is {} but {} against {} is {}",
+                        self.name(a),
+                        self.name(b),
+                        forward.name(),
+                        self.name(b),
+                        self.name(a),
+                        backward.name()
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /**
+     * Scores a series of (user, opponent) rounds, stopping at the first one
+     * that can't be resolved.
+     */
+    pub fn calculate_series(&self, rounds: &[(DynamicMove, DynamicMove)]) -> Result<Tally, RulesError> {
+        let mut tally = Tally::default();
+        for (user, opponent) in rounds {
+            tally.record(&self.calculate_winner(*user, *opponent)?);
+        }
+        Ok(tally)
+    }
+}
+
+/**
+ * Records the outcome of `a` against `b` (and the mirror image), refusing to
+ * overwrite a different outcome.
+ */
+fn settle(relation: &mut [Vec<Option<Ordering>>], names: &[String], a: usize, b: usize, ordering: Ordering) -> Result<(), RulesError> {
+    match relation[a][b] {
+        Some(existing) if existing != ordering => Err(RulesError::Contradiction(names[a].clone(), names[b].clone())),
+        _ => {
+            relation[a][b] = Some(ordering);
+            relation[b][a] = Some(ordering.reverse());
+            Ok(())
+        }
+    }
+}
+
+/**
+ * Every move of the rule set is equally likely.
+ */
+impl Distribution<DynamicMove> for Ruleset {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> DynamicMove {
+        DynamicMove { ruleset: self.id, index: rng.gen_range(0..self.names.len()) }
+    }
+}
+
+/**
+ * Why a rule set couldn't be loaded.
+ */
+#[derive(Debug, PartialEq)]
+pub enum RulesError {
+    /// The file couldn't be read.
+    Io(String),
+    /// The file isn't valid TOML, or is missing the list of moves.
+    Syntax(String),
+    /// A game needs at least two moves.
+    TooFewMoves,
+    /// The same move was declared twice.
+    DuplicateMove(String),
+    /// A rule mentions a move that wasn't declared.
+    UnknownMove(String),
+    /// A move was said to beat itself.
+    SelfBeat(String),
+    /// Two rules disagree about the outcome of the same matchup.
+    Contradiction(String, String),
+    /// No rule says what happens when these two moves meet.
+    Unresolved(String, String),
+    /// A move, by its number, that was taken from another rule set.
+    ForeignMove(usize)
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RulesError::Io(err) => write!(f, "Could not read rules: {}", err),
+            RulesError::Syntax(err) => write!(f, "Invalid rules file: {}", err),
+            RulesError::TooFewMoves => write!(f, "Invalid rules: at least two moves are needed"),
+            RulesError::DuplicateMove(name) => write!(f, "Invalid rules: {} is declared twice", name),
+            RulesError::UnknownMove(name) => write!(f, "Invalid rules: {} is not one of the moves", name),
+            RulesError::SelfBeat(name) => write!(f, "Invalid rules: {} can't beat itself", name),
+            RulesError::Contradiction(a, b) => write!(f, "Invalid rules: conflicting outcomes for {} against {}", a, b),
+            RulesError::Unresolved(a, b) => write!(f, "Invalid rules: nothing says what happens when {} meets {}", a, b),
+            RulesError::ForeignMove(index) => write!(f, "Invalid rules: move #{} is not part of this rule set", index + 1),
+        }
+    }
+}
+
+impl Error for RulesError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use crate::GameRng;
+
+    const WELL : &str = r#"
+        moves = ["Rock", "Paper", "Scissors", "Well"]
+        ties = [["Rock", "Scissors"]]
+
+        [beats]
+        Paper = ["Rock", "Well"]
+        Scissors = ["Paper"]
+        Well = ["Rock", "Scissors"]
+    "#;
+
+    #[test]
+    fn test_parse_valid_ruleset(){
+        let rules = Ruleset::parse(WELL).unwrap();
+        let well = rules.parse_move("well").unwrap();
+        let rock = rules.parse_move("ROCK").unwrap();
+        let paper = rules.parse_move("Paper").unwrap();
+        let scissors = rules.parse_move("scissors").unwrap();
+        assert_eq!(rules.name(well), "Well");
+        assert_eq!(rules.outcome(well, rock), GameResult::UserWin);
+        assert_eq!(rules.outcome(well, paper), GameResult::OpponentWin);
+        assert_eq!(rules.outcome(rock, scissors), GameResult::Tie);
+        assert_eq!(rules.outcome(well, well), GameResult::Tie)
+    }
+
+    #[test]
+    fn test_explain_custom_rules(){
+        let rules = Ruleset::parse(WELL).unwrap();
+        let well = rules.parse_move("well").unwrap();
+        let rock = rules.parse_move("rock").unwrap();
+        let scissors = rules.parse_move("scissors").unwrap();
+        assert_eq!(rules.explain(well, rock), "Well beats Rock, so you win.");
+        assert_eq!(rules.explain(rock, well), "Well beats Rock, so you lose.");
+        assert_eq!(rules.explain(rock, scissors), "Rock ties with Scissors, so it's a tie.")
+    }
+
+    #[test]
+    fn test_parse_move_unknown(){
+        let rules = Ruleset::parse(WELL).unwrap();
+        assert_eq!(rules.parse_move("lizard"), Err(ParseMoveError::new(String::from("lizard"))))
+    }
+
+    #[test]
+    fn test_reject_contradiction(){
+        let source = r#"
+            moves = ["a", "b"]
+            [beats]
+            a = ["b"]
+            b = ["a"]
+        "#;
+        assert_eq!(Ruleset::parse(source), Err(RulesError::Contradiction(String::from("b"), String::from("a"))))
+    }
+
+    #[test]
+    fn test_reject_beat_and_tie(){
+        let source = r#"
+            moves = ["a", "b"]
+            ties = [["a", "b"]]
+            [beats]
+            a = ["b"]
+        "#;
+        assert_eq!(Ruleset::parse(source), Err(RulesError::Contradiction(String::from("a"), String::from("b"))))
+    }
+
+    #[test]
+    fn test_reject_unresolved(){
+        let source = r#"
+            moves = ["a", "b", "c"]
+            [beats]
+            a = ["b"]
+            b = ["c"]
+        "#;
+        assert_eq!(Ruleset::parse(source), Err(RulesError::Unresolved(String::from("a"), String::from("c"))))
+    }
+
+    #[test]
+    fn test_reject_unknown_move(){
+        let source = r#"
+            moves = ["a", "b"]
+            [beats]
+            a = ["z"]
+        "#;
+        assert_eq!(Ruleset::parse(source), Err(RulesError::UnknownMove(String::from("z"))))
+    }
+
+    #[test]
+    fn test_reject_self_beat_and_duplicates(){
+        let self_beat = "moves = [\"a\", \"b\"]\n[beats]\na = [\"a\"]";
+        assert_eq!(Ruleset::parse(self_beat), Err(RulesError::SelfBeat(String::from("a"))));
+        let duplicate = "moves = [\"a\", \"A\"]";
+        assert_eq!(Ruleset::parse(duplicate), Err(RulesError::DuplicateMove(String::from("A"))));
+        assert_eq!(Ruleset::parse("moves = [\"a\"]"), Err(RulesError::TooFewMoves))
+    }
+
+    #[test]
+    fn test_reject_bad_syntax(){
+        assert!(matches!(Ruleset::parse("moves = "), Err(RulesError::Syntax(_))))
+    }
+
+    #[test]
+    fn test_from_variant_matches_builtin_rules(){
+        let rules = Ruleset::from_variant(Variant::LizardSpock);
+        for (a, ma) in rules.moves().zip(Variant::LizardSpock.moves()) {
+            for (b, mb) in rules.moves().zip(Variant::LizardSpock.moves()) {
+                assert_eq!(rules.beats(a, b), ma.beats(mb))
+            }
+        }
+    }
+
+    #[test]
+    fn test_describe_classic(){
+        assert_eq!(Ruleset::from_variant(Variant::Classic).describe(), "Rock beats Scissors; Paper beats Rock; Scissors beats Paper")
+    }
+
+    #[test]
+    fn test_describe_custom_rules(){
+        let rules = Ruleset::parse(WELL).unwrap();
+        assert_eq!(rules.describe(), "Paper beats Rock; Paper beats Well; Scissors beats Paper; Well beats Rock; Well beats Scissors")
+    }
+
+    #[test]
+    fn test_calculate_winner_resolves(){
+        let rules = Ruleset::parse(WELL).unwrap();
+        let well = rules.parse_move("well").unwrap();
+        let rock = rules.parse_move("rock").unwrap();
+        let paper = rules.parse_move("paper").unwrap();
+        assert_eq!(rules.calculate_winner(well, rock), Ok(GameResult::UserWin));
+        let tally = rules.calculate_series(&[(well, rock), (well, paper), (rock, rock)]).unwrap();
+        assert_eq!(tally, Tally { user_wins: 1, opponent_wins: 1, ties: 1 })
+    }
+
+    #[test]
+    fn test_move_the_ruleset_cannot_resolve(){
+        // Two moves can't settle a matchup involving Well.
+        let rules = Ruleset::parse("moves = [\"Rock\", \"Paper\"]\n[beats]\nPaper = [\"Rock\"]").unwrap();
+        let well = Ruleset::parse(WELL).unwrap().parse_move("well").unwrap();
+        let rock = rules.parse_move("rock").unwrap();
+        assert_eq!(rules.calculate_winner(rock, well), Err(RulesError::ForeignMove(3)));
+        assert_eq!(rules.calculate_series(&[(rock, rock), (well, rock)]), Err(RulesError::ForeignMove(3)));
+        assert_eq!(RulesError::ForeignMove(3).to_string(), "Invalid rules: move #4 is not part of this rule set")
+    }
+
+    #[test]
+    fn test_move_from_a_ruleset_of_the_same_size(){
+        // Rock is move #1 of both, but only belongs to one.
+        let rules = Ruleset::parse(WELL).unwrap();
+        let other = Ruleset::parse(WELL).unwrap();
+        let rock = other.parse_move("rock").unwrap();
+        let well = rules.parse_move("well").unwrap();
+        assert_eq!(rules.calculate_winner(well, rock), Err(RulesError::ForeignMove(0)));
+        // A clone is the same rule set, so it keeps scoring the moves.
+        assert_eq!(rules.clone().calculate_winner(well, rules.parse_move("rock").unwrap()), Ok(GameResult::UserWin))
+    }
+
+    #[test]
+    fn test_builtin_rules_pass_self_test(){
+        for variant in [Variant::Classic, Variant::LizardSpock] {
+            assert_eq!(Ruleset::from_variant(variant).self_test(), Vec::<String>::new());
+        }
+        assert!(Ruleset::parse(WELL).unwrap().self_test().is_empty())
+    }
+
+    #[test]
+    fn test_broken_rules_fail_self_test(){
+        // Validation would never let this through: Rock and Paper both beat
+        // each other, and Scissors beats itself.
+        let names = vec![String::from("Rock"), String::from("Paper"), String::from("Scissors")];
+        let relation = vec![
+            vec![Ordering::Equal, Ordering::Greater, Ordering::Greater],
+            vec![Ordering::Greater, Ordering::Equal, Ordering::Less],
+            vec![Ordering::Less, Ordering::Greater, Ordering::Greater],
+        ];
+        let problems = Ruleset::with_relation(names, relation).self_test();
+        assert_eq!(problems, [
+            "Rock against Paper is UserWin but Paper against Rock is UserWin",
+            "Scissors doesn't tie with itself",
+        ])
+    }
+
+    #[test]
+    fn test_self_test_reports_a_short_relation(){
+        let names = vec![String::from("Rock"), String::from("Paper")];
+        let relation = vec![vec![Ordering::Equal, Ordering::Less], vec![Ordering::Greater]];
+        assert!(Ruleset::with_relation(names, relation).self_test().iter().any(|problem| problem.starts_with("Paper against Paper can't be scored")))
+    }
+
+    #[test]
+    fn test_sampling_covers_every_move(){
+        let rules = Ruleset::parse(WELL).unwrap();
+        let mut rng = GameRng::seed_from_u64(7);
+        let samples : Vec<DynamicMove> = (0..100).map(|_| rng.sample(&rules)).collect();
+        assert!(rules.moves().all(|m| samples.contains(&m)))
+    }
+}
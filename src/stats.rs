@@ -0,0 +1,400 @@
+/*!
+ * A lifetime win/loss record kept on disk between runs, overall and for
+ * each move played.
+ */
+
+use std::{fmt, fs, io, path::{Path, PathBuf}};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{Move, Tally};
+
+/**
+ * Totals over every game recorded so far. `moves` breaks them down by the
+ * move played, in the order of `Move::all`; files written before it existed
+ * load with no breakdown, which then only covers the games from there on.
+ */
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub games: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub ties: u64,
+    #[serde(default)]
+    pub moves: Vec<MoveStats>
+}
+
+/**
+ * How the rounds played with one move went.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveStats {
+    #[serde(rename = "move")]
+    pub chosen: Move,
+    pub wins: u64,
+    pub losses: u64,
+    pub ties: u64
+}
+
+impl MoveStats {
+    pub fn games(&self) -> u64 {
+        self.wins + self.losses + self.ties
+    }
+
+    /**
+     * Share of the move's rounds that were won, between 0 and 1.
+     */
+    pub fn win_rate(&self) -> f64 {
+        if self.games() == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.games() as f64
+        }
+    }
+}
+
+impl fmt::Display for MoveStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.0}% win rate over {} ({}, {}, {})",
+            self.chosen,
+            self.win_rate() * 100.0,
+            count(self.games(), "game", "games"),
+            count(self.wins, "win", "wins"),
+            count(self.losses, "loss", "losses"),
+            count(self.ties, "tie", "ties")
+        )
+    }
+}
+
+/**
+ * `n` followed by the singular or plural noun, e.g. "1 win" or "2 wins".
+ */
+fn count(n: u64, one: &str, many: &str) -> String {
+    format!("{} {}", n, if n == 1 { one } else { many })
+}
+
+impl Stats {
+    /**
+     * Reads the stats at `path`. A missing or unreadable file starts a fresh
+     * record rather than failing.
+     */
+    pub fn load(path: &Path) -> Stats {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /**
+     * Writes the stats to `path`, creating its directory if needed.
+     */
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(self).expect("stats always serialize");
+        fs::write(path, contents)
+    }
+
+    /**
+     * Adds every round of a finished game to the record.
+     */
+    pub fn add(&mut self, tally: &Tally) {
+        self.wins += u64::from(tally.user_wins);
+        self.losses += u64::from(tally.opponent_wins);
+        self.ties += u64::from(tally.ties);
+        self.games += u64::from(tally.user_wins + tally.opponent_wins + tally.ties);
+    }
+
+    /**
+     * Adds rounds that were all played with `chosen` to the record, and to
+     * that move's breakdown.
+     */
+    pub fn add_move(&mut self, chosen: Move, tally: &Tally) {
+        self.add(tally);
+        let record = self.entry(chosen);
+        record.wins += u64::from(tally.user_wins);
+        record.losses += u64::from(tally.opponent_wins);
+        record.ties += u64::from(tally.ties);
+    }
+
+    /**
+     * Adds another record, breakdown and all, to this one.
+     */
+    pub fn merge(&mut self, other: &Stats) {
+        self.games += other.games;
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.ties += other.ties;
+        for theirs in &other.moves {
+            let record = self.entry(theirs.chosen);
+            record.wins += theirs.wins;
+            record.losses += theirs.losses;
+            record.ties += theirs.ties;
+        }
+    }
+
+    /**
+     * The breakdown for `chosen`, if it has ever been played.
+     */
+    pub fn for_move(&self, chosen: Move) -> Option<&MoveStats> {
+        self.moves.iter().find(|record| record.chosen == chosen)
+    }
+
+    fn entry(&mut self, chosen: Move) -> &mut MoveStats {
+        let index = match self.moves.iter().position(|record| record.chosen == chosen) {
+            Some(index) => index,
+            None => {
+                self.moves.push(MoveStats { chosen, wins: 0, losses: 0, ties: 0 });
+                self.moves.sort_by_key(|record| u8::from(record.chosen));
+                self.moves.iter().position(|record| record.chosen == chosen).expect("just added")
+            }
+        };
+        &mut self.moves[index]
+    }
+}
+
+/**
+ * One line per move played, then the overall record, whose line always
+ * comes last.
+ */
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for record in &self.moves {
+            writeln!(f, "{}", record)?;
+        }
+        write!(
+            f,
+            "Lifetime: {}, {}, {}, {}",
+            count(self.games, "game", "games"),
+            count(self.wins, "win", "wins"),
+            count(self.losses, "loss", "losses"),
+            count(self.ties, "tie", "ties")
+        )
+    }
+}
+
+/**
+ * Keeps the record at a path up to date over a long session. The rounds not
+ * saved yet are saved every `every` rounds, so a crash loses at most that
+ * many. Every save reads the file again and adds only those rounds, so games
+ * saved by other runs in the meantime are kept and no round counts twice.
+ */
+pub struct StatsWriter {
+    path: PathBuf,
+    saved: Stats,
+    unsaved: Stats,
+    every: u64
+}
+
+impl StatsWriter {
+    /**
+     * Starts a session on top of the record at `path`. With `every` at 0 it
+     * is only saved when asked to.
+     */
+    pub fn new(path: PathBuf, every: u32) -> StatsWriter {
+        let saved = Stats::load(&path);
+        StatsWriter { path, saved, unsaved: Stats::default(), every: u64::from(every) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /**
+     * Adds rounds to the session, under the move they were played with when
+     * there was one.
+     */
+    pub fn record(&mut self, chosen: Option<Move>, tally: &Tally) {
+        match chosen {
+            Some(chosen) => self.unsaved.add_move(chosen, tally),
+            None => self.unsaved.add(tally),
+        }
+    }
+
+    /**
+     * The record as last read from the file, plus the rounds not saved yet.
+     */
+    pub fn totals(&self) -> Stats {
+        let mut stats = self.saved.clone();
+        stats.merge(&self.unsaved);
+        stats
+    }
+
+    /**
+     * Saves the record if `every` more rounds have been recorded since the
+     * last save.
+     */
+    pub fn update(&mut self) -> io::Result<()> {
+        if self.every > 0 && self.unsaved.games >= self.every {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Adds the rounds not saved yet to the record on disk as it is now.
+     */
+    pub fn save(&mut self) -> io::Result<()> {
+        let mut stats = Stats::load(&self.path);
+        stats.merge(&self.unsaved);
+        stats.save(&self.path)?;
+        self.saved = stats;
+        self.unsaved = Stats::default();
+        Ok(())
+    }
+}
+
+/**
+ * Where stats live by default, e.g. `~/.config/rps/stats.json` on Linux.
+ */
+pub fn default_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rps").map(|dirs| dirs.config_dir().join("stats.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_file_starts_fresh(){
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Stats::load(&dir.path().join("stats.json")), Stats::default())
+    }
+
+    #[test]
+    fn test_corrupt_file_starts_fresh(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(Stats::load(&path), Stats::default())
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("stats.json");
+        let mut stats = Stats::default();
+        stats.add(&Tally { user_wins: 3, opponent_wins: 1, ties: 2 });
+        stats.save(&path).unwrap();
+        assert_eq!(Stats::load(&path), Stats { games: 6, wins: 3, losses: 1, ties: 2, moves: Vec::new() })
+    }
+
+    #[test]
+    fn test_add_accumulates(){
+        let mut stats = Stats { games: 1, wins: 1, losses: 0, ties: 0, moves: Vec::new() };
+        stats.add(&Tally { user_wins: 0, opponent_wins: 1, ties: 0 });
+        assert_eq!(stats, Stats { games: 2, wins: 1, losses: 1, ties: 0, moves: Vec::new() })
+    }
+
+    #[test]
+    fn test_writer_saves_partial_totals(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        Stats { games: 1, wins: 0, losses: 0, ties: 1, moves: Vec::new() }.save(&path).unwrap();
+        let mut writer = StatsWriter::new(path.clone(), 2);
+        writer.record(None, &Tally { user_wins: 1, opponent_wins: 0, ties: 0 });
+        writer.update().unwrap();
+        assert_eq!(Stats::load(&path), Stats { games: 1, wins: 0, losses: 0, ties: 1, moves: Vec::new() });
+        writer.record(None, &Tally { user_wins: 0, opponent_wins: 1, ties: 0 });
+        writer.update().unwrap();
+        assert_eq!(Stats::load(&path), Stats { games: 3, wins: 1, losses: 1, ties: 1, moves: Vec::new() });
+        writer.record(None, &Tally { user_wins: 1, opponent_wins: 0, ties: 0 });
+        writer.update().unwrap();
+        assert_eq!(Stats::load(&path), Stats { games: 3, wins: 1, losses: 1, ties: 1, moves: Vec::new() })
+    }
+
+    #[test]
+    fn test_writer_never_counts_a_round_twice(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let mut writer = StatsWriter::new(path.clone(), 1);
+        writer.record(None, &Tally { user_wins: 1, opponent_wins: 0, ties: 0 });
+        writer.update().unwrap();
+        writer.save().unwrap();
+        assert_eq!(Stats::load(&path), Stats { games: 1, wins: 1, losses: 0, ties: 0, moves: Vec::new() })
+    }
+
+    #[test]
+    fn test_writers_keep_each_others_games(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let mut first = StatsWriter::new(path.clone(), 1);
+        let mut second = StatsWriter::new(path.clone(), 1);
+        first.record(None, &Tally { user_wins: 1, opponent_wins: 0, ties: 0 });
+        first.update().unwrap();
+        second.record(None, &Tally { user_wins: 0, opponent_wins: 1, ties: 0 });
+        second.update().unwrap();
+        first.record(None, &Tally { user_wins: 0, opponent_wins: 0, ties: 1 });
+        first.save().unwrap();
+        assert_eq!(Stats::load(&path), Stats { games: 3, wins: 1, losses: 1, ties: 1, moves: Vec::new() });
+        assert_eq!(first.totals(), Stats::load(&path))
+    }
+
+    #[test]
+    fn test_writer_without_interval_saves_only_when_asked(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let mut writer = StatsWriter::new(path.clone(), 0);
+        writer.record(None, &Tally { user_wins: 5, opponent_wins: 0, ties: 0 });
+        writer.update().unwrap();
+        assert!(!path.exists())
+    }
+
+    #[test]
+    fn test_per_move_stats_are_kept_between_sessions(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let mut first = StatsWriter::new(path.clone(), 0);
+        first.record(Some(Move::Rock), &Tally { user_wins: 2, opponent_wins: 1, ties: 0 });
+        first.save().unwrap();
+        let mut second = StatsWriter::new(path.clone(), 0);
+        second.record(Some(Move::Paper), &Tally { user_wins: 0, opponent_wins: 0, ties: 1 });
+        second.record(Some(Move::Rock), &Tally { user_wins: 0, opponent_wins: 2, ties: 0 });
+        second.save().unwrap();
+        let stats = Stats::load(&path);
+        assert_eq!(stats.games, 6);
+        assert_eq!(stats.moves, [
+            MoveStats { chosen: Move::Rock, wins: 2, losses: 3, ties: 0 },
+            MoveStats { chosen: Move::Paper, wins: 0, losses: 0, ties: 1 },
+        ])
+    }
+
+    #[test]
+    fn test_flat_stats_files_still_load(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        fs::write(&path, r#"{ "games": 3, "wins": 1, "losses": 1, "ties": 1 }"#).unwrap();
+        let mut stats = Stats::load(&path);
+        assert_eq!(stats, Stats { games: 3, wins: 1, losses: 1, ties: 1, moves: Vec::new() });
+        stats.add_move(Move::Scissors, &Tally { user_wins: 1, opponent_wins: 0, ties: 0 });
+        assert_eq!(stats.games, 4);
+        assert_eq!(stats.for_move(Move::Scissors), Some(&MoveStats { chosen: Move::Scissors, wins: 1, losses: 0, ties: 0 }))
+    }
+
+    #[test]
+    fn test_move_stats_display(){
+        let record = MoveStats { chosen: Move::Rock, wins: 2, losses: 2, ties: 1 };
+        assert_eq!(record.to_string(), "Rock: 40% win rate over 5 games (2 wins, 2 losses, 1 tie)")
+    }
+
+    #[test]
+    fn test_display(){
+        let stats = Stats { games: 6, wins: 3, losses: 1, ties: 2, moves: Vec::new() };
+        assert_eq!(stats.to_string(), "Lifetime: 6 games, 3 wins, 1 loss, 2 ties")
+    }
+
+    #[test]
+    fn test_display_lists_moves_before_the_total(){
+        let mut stats = Stats::default();
+        stats.add_move(Move::Paper, &Tally { user_wins: 1, opponent_wins: 0, ties: 0 });
+        stats.add_move(Move::Rock, &Tally { user_wins: 0, opponent_wins: 1, ties: 0 });
+        assert_eq!(
+            stats.to_string(),
+            "Rock: 0% win rate over 1 game (0 wins, 1 loss, 0 ties)\nPaper: 100% win rate over 1 game (1 win, 0 losses, 0 ties)\nLifetime: 2 games, 1 win, 1 loss, 0 ties"
+        )
+    }
+}
@@ -0,0 +1,58 @@
+/*!
+ * Playing the user against several opponents and ranking the results.
+ */
+
+use std::cmp::Ordering;
+
+use crate::Tally;
+
+/**
+ * How the user fared against one opponent.
+ */
+#[derive(Debug, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub tally: Tally
+}
+
+/**
+ * Ranks opponents by the user's win rate against them, best first. Opponents
+ * with the same win rate keep the order they were played in.
+ */
+pub fn leaderboard(mut standings: Vec<Standing>) -> Vec<Standing> {
+    standings.sort_by(|a, b| {
+        b.tally.win_rate().partial_cmp(&a.tally.win_rate()).unwrap_or(Ordering::Equal)
+    });
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standing(name: &str, user_wins: u32, opponent_wins: u32, ties: u32) -> Standing {
+        Standing { name: name.to_string(), tally: Tally { user_wins, opponent_wins, ties } }
+    }
+
+    #[test]
+    fn test_leaderboard_ranks_by_win_rate(){
+        let ranked = leaderboard(vec![
+            standing("random", 1, 1, 1),
+            standing("frequency", 0, 3, 0),
+            standing("always-rock", 3, 0, 0),
+        ]);
+        let names : Vec<&str> = ranked.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["always-rock", "random", "frequency"])
+    }
+
+    #[test]
+    fn test_leaderboard_keeps_order_on_ties(){
+        let ranked = leaderboard(vec![standing("first", 1, 1, 0), standing("second", 2, 2, 0)]);
+        assert_eq!(ranked[0].name, "first")
+    }
+
+    #[test]
+    fn test_leaderboard_empty(){
+        assert_eq!(leaderboard(Vec::new()), Vec::new())
+    }
+}
@@ -0,0 +1,238 @@
+/*!
+ * A CSV record of every round played, for analysing games outside the program.
+ */
+
+use std::{fs::{File, OpenOptions}, io::{self, Write}, path::Path, str::FromStr};
+
+use clap::ValueEnum;
+
+use crate::{calculate_winner, GameResult, Move, Round, TiePolicy};
+
+/** The first line of every transcript. */
+pub const HEADER: &str = "user_move,opponent_move,result,seed,tie_policy";
+
+/** The header of transcripts written before the tie policy was recorded. */
+pub const SEED_HEADER: &str = "user_move,opponent_move,result,seed";
+
+/** The header for rows written by `round_row`, without a seed column. */
+pub const ROUND_HEADER: &str = "user_move,opponent_move,result";
+
+/**
+ * A round as a CSV row in the order of `ROUND_HEADER`, e.g.
+ * "rock,paper,OpponentWin". None of the names need quoting.
+ */
+pub fn round_row(round: &Round) -> String {
+    format!("{},{},{}", round.user_move.name(), round.opponent_move.name(), round.result.name())
+}
+
+/**
+ * A round forfeited to --timeout as a CSV row in the order of
+ * `ROUND_HEADER`: neither move was played, so both columns are empty.
+ */
+pub fn forfeit_row() -> String {
+    format!(",,{}", GameResult::OpponentWin.name())
+}
+
+/** A row read back by `parse_row`. */
+#[derive(Debug, PartialEq)]
+pub enum Row {
+    Played(Round),
+    /** A round lost to --timeout before the user threw anything. */
+    Forfeit
+}
+
+/**
+ * Checks the first line of a transcript being read back: `HEADER`,
+ * `SEED_HEADER` or, for output saved from --format csv, `ROUND_HEADER`.
+ */
+pub fn check_header(line: &str) -> Result<(), String> {
+    let line = line.trim_end();
+    if line == HEADER || line == SEED_HEADER || line == ROUND_HEADER {
+        Ok(())
+    } else {
+        Err(format!("expected the header {:?}, found {:?}", HEADER, line))
+    }
+}
+
+/**
+ * Reads back a row written by `Transcript::record`, `round_row` or
+ * `forfeit_row`. The result has to agree with the moves under the row's tie
+ * policy, or `tie_policy` for rows that don't record one, and the seed, if
+ * any, has to be a number, so a hand-edited row can't claim a win it didn't
+ * earn. A row without a user move is a forfeit, which is always the
+ * opponent's win.
+ */
+pub fn parse_row(line: &str, tie_policy: TiePolicy) -> Result<Row, String> {
+    let columns : Vec<&str> = line.trim_end().split(',').collect();
+    let (user, opponent, result) = match columns.as_slice() {
+        [user, opponent, result] | [user, opponent, result, _] | [user, opponent, result, _, _] => (user, opponent, result),
+        _ => return Err(format!("expected 3 to 5 columns, found {}", columns.len())),
+    };
+    if let Some(seed) = columns.get(3).filter(|seed| !seed.is_empty()) {
+        u64::from_str(seed).map_err(|_| format!("invalid seed: {:?}", seed))?;
+    }
+    let tie_policy = match columns.get(4) {
+        Some(policy) => TiePolicy::from_str(policy, false).map_err(|_| format!("invalid tie policy: {:?}", policy))?,
+        None => tie_policy,
+    };
+    if user.is_empty() {
+        if !opponent.is_empty() {
+            return Err(format!("a forfeit has no opponent move, found {:?}", opponent));
+        }
+        if *result != GameResult::OpponentWin.name() {
+            return Err(format!("a forfeit is {}, not {:?}", GameResult::OpponentWin.name(), result));
+        }
+        return Ok(Row::Forfeit);
+    }
+    let user_move = Move::from_str(user).map_err(|err| err.to_string())?;
+    let opponent_move = Move::from_str(opponent).map_err(|err| err.to_string())?;
+    let expected = tie_policy.resolve(calculate_winner(user_move, opponent_move))
+        .ok_or_else(|| format!("{} vs {} is a tie, which the {} tie policy never records", user_move.name(), opponent_move.name(), tie_policy.name()))?;
+    if expected.name() != *result {
+        return Err(format!("{} vs {} is {}, not {:?}", user_move.name(), opponent_move.name(), expected.name(), result));
+    }
+    Ok(Row::Played(Round { user_move, opponent_move, result: expected }))
+}
+
+/**
+ * Appends rounds to a CSV file, one row per round. Every row records the
+ * tie policy its result was scored under, so it reads back the same way.
+ */
+pub struct Transcript<W> {
+    out: W,
+    tie_policy: TiePolicy
+}
+
+impl Transcript<File> {
+    /**
+     * Opens `path` for appending, writing the header first if the file is
+     * new or empty.
+     */
+    pub fn open(path: &Path, tie_policy: TiePolicy) -> io::Result<Transcript<File>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_new = file.metadata()?.len() == 0;
+        Transcript::new(file, is_new, tie_policy)
+    }
+}
+
+impl<W: Write> Transcript<W> {
+    pub fn new(mut out: W, write_header: bool, tie_policy: TiePolicy) -> io::Result<Transcript<W>> {
+        if write_header {
+            writeln!(out, "{}", HEADER)?;
+        }
+        Ok(Transcript { out, tie_policy })
+    }
+
+    /**
+     * Adds a row for `round`. `seed` is the seed the round was played with,
+     * left empty when it isn't known.
+     */
+    pub fn record(&mut self, round: &Round, seed: Option<u64>) -> io::Result<()> {
+        self.write_row(&round_row(round), seed)
+    }
+
+    /**
+     * Adds a `forfeit_row` for a round lost to --timeout.
+     */
+    pub fn record_forfeit(&mut self, seed: Option<u64>) -> io::Result<()> {
+        self.write_row(&forfeit_row(), seed)
+    }
+
+    fn write_row(&mut self, row: &str, seed: Option<u64>) -> io::Result<()> {
+        let seed = seed.map(|s| s.to_string()).unwrap_or_default();
+        writeln!(self.out, "{},{},{}", row, seed, self.tie_policy.name())?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_record_rows(){
+        let mut transcript = Transcript::new(Vec::new(), true, TiePolicy::Tie).unwrap();
+        transcript.record(&Round::new(Move::Rock, Move::Paper), Some(7)).unwrap();
+        transcript.record(&Round::new(Move::Spock, Move::Spock), None).unwrap();
+        let text = String::from_utf8(transcript.out).unwrap();
+        assert_eq!(text, "user_move,opponent_move,result,seed,tie_policy\nrock,paper,OpponentWin,7,tie\nspock,spock,Tie,,tie\n")
+    }
+
+    #[test]
+    fn test_round_row_matches_header(){
+        let row = round_row(&Round::new(Move::Scissors, Move::Paper));
+        assert_eq!(row, "scissors,paper,UserWin");
+        assert_eq!(row.split(',').count(), ROUND_HEADER.split(',').count())
+    }
+
+    #[test]
+    fn test_rows_read_back(){
+        let mut transcript = Transcript::new(Vec::new(), true, TiePolicy::Tie).unwrap();
+        transcript.record(&Round::new(Move::Rock, Move::Paper), Some(7)).unwrap();
+        transcript.record(&Round::new(Move::Lizard, Move::Spock), None).unwrap();
+        let text = String::from_utf8(transcript.out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(check_header(lines.next().unwrap()), Ok(()));
+        let rows : Vec<Row> = lines.map(|line| parse_row(line, TiePolicy::Tie).unwrap()).collect();
+        assert_eq!(rows, [Row::Played(Round::new(Move::Rock, Move::Paper)), Row::Played(Round::new(Move::Lizard, Move::Spock))])
+    }
+
+    #[test]
+    fn test_forfeits_read_back(){
+        let mut transcript = Transcript::new(Vec::new(), false, TiePolicy::Tie).unwrap();
+        transcript.record_forfeit(Some(7)).unwrap();
+        let text = String::from_utf8(transcript.out).unwrap();
+        assert_eq!(text, ",,OpponentWin,7,tie\n");
+        assert_eq!(parse_row(&text, TiePolicy::Tie), Ok(Row::Forfeit));
+        assert_eq!(parse_row(&forfeit_row(), TiePolicy::Tie), Ok(Row::Forfeit));
+        assert_eq!(parse_row(",rock,OpponentWin", TiePolicy::Tie), Err(String::from("a forfeit has no opponent move, found \"rock\"")));
+        assert_eq!(parse_row(",,UserWin", TiePolicy::Tie), Err(String::from("a forfeit is OpponentWin, not \"UserWin\"")))
+    }
+
+    #[test]
+    fn test_tie_policy_is_read_back(){
+        let mut transcript = Transcript::new(Vec::new(), true, TiePolicy::User).unwrap();
+        let tie = Round::new(Move::Rock, Move::Rock);
+        transcript.record(&Round { result: TiePolicy::User.resolve(tie.result).unwrap(), ..tie }, Some(7)).unwrap();
+        let text = String::from_utf8(transcript.out).unwrap();
+        let row = text.lines().nth(1).unwrap();
+        assert_eq!(row, "rock,rock,UserWin,7,user");
+        // The row's own policy wins over the one the replay was started with.
+        assert_eq!(parse_row(row, TiePolicy::Tie), Ok(Row::Played(Round { result: GameResult::UserWin, ..tie })))
+    }
+
+    #[test]
+    fn test_rows_without_a_policy_use_the_given_one(){
+        let tie = Round::new(Move::Rock, Move::Rock);
+        assert_eq!(parse_row("rock,rock,OpponentWin", TiePolicy::Opponent), Ok(Row::Played(Round { result: GameResult::OpponentWin, ..tie })));
+        assert_eq!(parse_row("rock,rock,OpponentWin,3", TiePolicy::Tie), Err(String::from("rock vs rock is Tie, not \"OpponentWin\"")));
+        assert_eq!(parse_row("rock,rock,Tie", TiePolicy::Replay), Err(String::from("rock vs rock is a tie, which the replay tie policy never records")))
+    }
+
+    #[test]
+    fn test_older_headers_are_accepted(){
+        assert_eq!(check_header(SEED_HEADER), Ok(()));
+        assert_eq!(check_header(ROUND_HEADER), Ok(()));
+        assert!(check_header("move,result").is_err())
+    }
+
+    #[test]
+    fn test_malformed_rows_are_rejected(){
+        assert_eq!(parse_row("rock,paper", TiePolicy::Tie), Err(String::from("expected 3 to 5 columns, found 2")));
+        assert_eq!(parse_row("rock,stone,Tie", TiePolicy::Tie), Err(String::from("Invalid move: \"stone\"")));
+        assert_eq!(parse_row("rock,paper,UserWin,7", TiePolicy::Tie), Err(String::from("rock vs paper is OpponentWin, not \"UserWin\"")));
+        assert_eq!(parse_row("rock,paper,OpponentWin,x", TiePolicy::Tie), Err(String::from("invalid seed: \"x\"")));
+        assert_eq!(parse_row("rock,rock,UserWin,7,house", TiePolicy::Tie), Err(String::from("invalid tie policy: \"house\"")))
+    }
+
+    #[test]
+    fn test_header_written_once(){
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transcript.csv");
+        Transcript::open(&path, TiePolicy::Tie).unwrap().record(&Round::new(Move::Rock, Move::Scissors), Some(1)).unwrap();
+        Transcript::open(&path, TiePolicy::Tie).unwrap().record(&Round::new(Move::Paper, Move::Scissors), Some(2)).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "user_move,opponent_move,result,seed,tie_policy\nrock,scissors,UserWin,1,tie\npaper,scissors,OpponentWin,2,tie\n")
+    }
+}
@@ -0,0 +1,1037 @@
+use assert_cmd::Command;
+
+fn rps() -> Command {
+    let mut cmd = Command::cargo_bin("rock_paper_scissors").unwrap();
+    // Keep the developer's environment from leaking into the games: their
+    // RPS_MOVE, and any rps.toml in the current or config directory.
+    let empty = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("empty-home");
+    std::fs::create_dir_all(&empty).unwrap();
+    cmd.env_remove("RPS_MOVE")
+        .env_remove("NO_COLOR")
+        .env("HOME", &empty)
+        .env("XDG_CONFIG_HOME", empty.join(".config"))
+        .current_dir(&empty);
+    cmd
+}
+
+    /** ==== Exit codes ==== **/
+
+#[test]
+fn test_exit_code_user_win(){
+    // Seed 7 draws Paper.
+    rps().args(["scissors", "--seed", "25"]).assert().code(0);
+}
+
+#[test]
+fn test_exit_code_tie(){
+    rps().args(["paper", "--seed", "25"]).assert().code(1);
+}
+
+#[test]
+fn test_exit_code_opponent_win(){
+    rps().args(["rock", "--seed", "25"]).assert().code(2);
+}
+
+#[test]
+fn test_exit_code_invalid_move(){
+    rps().args(["notreal", "--seed", "25"]).assert().code(3).stderr("error: Invalid move: \"notreal\"\n\nUSAGE:\n    rock_paper_scissors [OPTIONS] [--] [PATTERN]\n\nFor more information try --help\n");
+}
+
+#[test]
+fn test_exit_code_empty_move(){
+    rps().args(["", "--seed", "25"]).assert().code(4).stderr(predicates::str::starts_with("error: No move provided — expected rock, paper, or scissors\n\nUSAGE:"));
+}
+
+    /** ==== Weights ==== **/
+
+#[test]
+fn test_weights_only_rock(){
+    rps().args(["paper", "--weights", "1,0,0"]).assert().code(0);
+}
+
+#[test]
+fn test_weights_wrong_count_is_rejected(){
+    rps().args(["paper", "--weights", "1,1"]).assert().code(3);
+}
+
+#[test]
+fn test_weights_negative_is_rejected(){
+    rps().args(["paper", "--weights", "1,-1,1"]).assert().code(3);
+}
+
+    /** ==== Color ==== **/
+
+#[test]
+fn test_no_color_output_has_no_escapes(){
+    let output = rps().args(["scissors", "--seed", "25", "--no-color"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "Opponent's move: Paper. You win!\n");
+    assert!(!stdout.contains('\x1b'))
+}
+
+    /** ==== Stats ==== **/
+
+#[test]
+fn test_stats_are_kept_between_runs(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stats.json");
+    let path = path.to_str().unwrap();
+    rps().args(["scissors", "--seed", "25", "--stats-file", path]).assert().code(0);
+    let output = rps().args(["rock", "--seed", "25", "--stats-file", path]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.ends_with("Lifetime: 2 games, 1 win, 1 loss, 0 ties\n"))
+}
+
+#[test]
+fn test_stats_break_down_by_move(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stats.json");
+    let path = path.to_str().unwrap();
+    rps().args(["rock", "--seed", "25", "--stats-file", path]).assert().code(2);
+    rps().args(["scissors", "--seed", "25", "--stats-file", path]).assert().code(0);
+    rps().args(["rock", "--seed", "1", "--stats-file", path])
+        .assert()
+        .code(2)
+        .stdout(predicates::str::contains("Rock: 0% win rate over 2 games (0 wins, 2 losses, 0 ties)\nScissors: 100% win rate over 1 game (1 win"));
+    let saved : serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(saved["moves"][0], serde_json::json!({ "move": "rock", "wins": 0, "losses": 2, "ties": 0 }));
+}
+
+#[test]
+fn test_flush_every_needs_stats(){
+    rps().args(["--batch", "--flush-every", "2"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--stats|--stats-file"));
+}
+
+    /** ==== Quiet ==== **/
+
+#[test]
+fn test_quiet_prints_only_the_result(){
+    let output = rps().args(["scissors", "--seed", "25", "-q"]).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "UserWin\n")
+}
+
+#[test]
+fn test_quiet_rounds_skip_the_tally(){
+    let output = rps().args(["rock", "--seed", "25", "--rounds", "3", "--quiet"]).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "OpponentWin\nTie\nTie\n")
+}
+
+    /** ==== RPS_MOVE ==== **/
+
+#[test]
+fn test_move_from_environment(){
+    rps().args(["--seed", "25"]).env("RPS_MOVE", "scissors").assert().code(0);
+}
+
+#[test]
+fn test_argument_overrides_environment(){
+    rps().args(["rock", "--seed", "25"]).env("RPS_MOVE", "scissors").assert().code(2);
+}
+
+#[test]
+fn test_missing_move_is_an_error(){
+    rps().args(["--seed", "25"]).assert().failure().stderr(predicates::str::contains("PATTERN"));
+}
+
+    /** ==== Batch ==== **/
+
+#[test]
+fn test_batch_plays_one_game_per_line(){
+    let output = rps()
+        .args(["--batch", "--seed", "25", "--quiet"])
+        .write_stdin("scissors\nnotreal\nrock\nrock\n")
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "UserWin\nTie\nTie\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "Line 2: Invalid move: \"notreal\"\n")
+}
+
+    /** ==== Custom rules ==== **/
+
+#[test]
+fn test_custom_rules_are_played(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rules.toml");
+    std::fs::write(&path, "moves = [\"Sun\", \"Cloud\"]\n[beats]\nCloud = [\"Sun\"]\n").unwrap();
+    let path = path.to_str().unwrap();
+    rps().args(["cloud", "--rules", path, "--quiet", "--rounds", "4", "--seed", "25"])
+        .assert()
+        .code(0)
+        .stdout(predicates::str::is_match("^((UserWin|Tie)\n){4}$").unwrap());
+    rps().args(["moon", "--rules", path]).assert().code(3).stderr(predicates::str::starts_with("error: Invalid move: \"moon\"\n"));
+}
+
+#[test]
+fn test_inconsistent_rules_are_rejected(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rules.toml");
+    std::fs::write(&path, "moves = [\"Sun\", \"Cloud\", \"Rain\"]\n[beats]\nCloud = [\"Sun\"]\n").unwrap();
+    rps().args(["cloud", "--rules", path.to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("nothing says what happens when Sun meets Rain"));
+}
+
+    /** ==== Self-test ==== **/
+
+#[test]
+fn test_self_test_passes_for_builtin_rules(){
+    rps().arg("--self-test").assert().code(0).stdout("Self-test passed: every matchup is consistent\n");
+}
+
+#[test]
+fn test_self_test_of_custom_rules(){
+    let dir = tempfile::tempdir().unwrap();
+    let good = dir.path().join("good.toml");
+    std::fs::write(&good, "moves = [\"Sun\", \"Cloud\"]\n[beats]\nCloud = [\"Sun\"]\n").unwrap();
+    rps().args(["--self-test", "--rules", good.to_str().unwrap()]).assert().code(0);
+    let broken = dir.path().join("broken.toml");
+    std::fs::write(&broken, "moves = [\"Sun\", \"Cloud\"]\n[beats]\nCloud = [\"Sun\"]\nSun = [\"Cloud\"]\n").unwrap();
+    // The loader already refuses the file, so that is what gets reported.
+    rps().args(["--self-test", "--rules", broken.to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("conflicting outcomes"));
+}
+
+    /** ==== Predict ==== **/
+
+#[test]
+fn test_predict_against_weighted_opponent(){
+    rps().args(["paper", "--seed", "25", "--weights", "3,1,1", "--predict", "--quiet"])
+        .assert()
+        .stdout(predicates::str::starts_with("Chance of winning: Rock 20%, Paper 60%, Scissors 20%\n"));
+}
+
+#[test]
+fn test_hint_against_frequency_opponent(){
+    // After two rounds of rock the frequency AI will throw paper.
+    rps().args(["rock", "--seed", "25", "--rounds", "3", "--ai", "frequency", "--hint", "--quiet"])
+        .assert()
+        .stdout(predicates::str::ends_with("Hint: play Scissors\nOpponentWin\n"));
+}
+
+#[test]
+fn test_hint_against_a_sequence(){
+    rps().args(["rock", "--sequence", "ps", "--rounds", "2", "--hint", "--quiet"])
+        .assert()
+        .stdout("Hint: play Scissors\nOpponentWin\nHint: play Rock\nUserWin\n");
+}
+
+    /** ==== Dramatic ==== **/
+
+#[test]
+fn test_dramatic_countdown_without_delay_when_piped(){
+    let start = std::time::Instant::now();
+    rps().args(["scissors", "--seed", "25", "--dramatic", "--countdown-ms", "5000", "--no-color"])
+        .assert()
+        .code(0)
+        .stdout("Rock... Paper... Scissors... Shoot!\nOpponent's move: Paper. You win!\n");
+    assert!(start.elapsed() < std::time::Duration::from_secs(5))
+}
+
+    /** ==== No ties ==== **/
+
+#[test]
+fn test_no_ties_reports_rerolls(){
+    rps().args(["rock", "--seed", "27", "--no-ties", "--no-color"])
+        .assert()
+        .stdout("(re-rolled 2 ties)\nOpponent's move: Paper. You lose!\n");
+}
+
+#[test]
+fn test_no_ties_against_only_ties_is_an_error(){
+    rps().args(["rock", "--weights", "1,0,0", "--no-ties"])
+        .assert()
+        .code(3)
+        .stderr("No decisive outcome is possible: every re-roll was a tie\n");
+}
+
+    /** ==== Verbose ==== **/
+
+#[test]
+fn test_verbose_explains_the_result(){
+    rps().args(["rock", "--seed", "25", "-v", "--no-color"])
+        .assert()
+        .code(2)
+        .stdout("Your move: Rock. Opponent's move: Paper. You lose!\nPaper covers Rock, so you lose.\n");
+}
+
+    /** ==== Transcript ==== **/
+
+#[test]
+fn test_log_appends_rounds_as_csv(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("transcript.csv");
+    let log = path.to_str().unwrap();
+    rps().args(["rock", "--seed", "25", "--rounds", "2", "--log", log]).assert().code(2);
+    rps().args(["scissors", "--seed", "25", "--log", log]).assert().code(0);
+    let rows = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(rows, "user_move,opponent_move,result,seed,tie_policy\nrock,paper,OpponentWin,25,tie\nrock,rock,Tie,24,tie\nscissors,paper,UserWin,25,tie\n")
+}
+
+#[test]
+fn test_log_under_a_tie_policy_replays(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("transcript.csv");
+    let log = path.to_str().unwrap();
+    rps().args(["rock", "--seed", "2", "--tie-policy", "user", "--log", log]).assert().code(0);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "user_move,opponent_move,result,seed,tie_policy\nrock,rock,UserWin,2,user\n");
+    rps().args(["--replay", log, "--quiet"])
+        .assert()
+        .code(0)
+        .stdout("UserWin\n")
+        .stderr("");
+}
+
+#[test]
+fn test_log_that_cannot_be_opened_is_an_error(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing").join("transcript.csv");
+    rps().args(["rock", "--log", path.to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::starts_with("Could not open transcript"));
+}
+
+    /** ==== Languages ==== **/
+
+#[test]
+fn test_spanish_game(){
+    rps().args(["tijera", "--seed", "25", "--lang", "es", "--no-color"])
+        .assert()
+        .code(0)
+        .stdout("Jugada del rival: Papel. ¡Ganaste!\n");
+}
+
+    /** ==== Best of ==== **/
+
+#[test]
+fn test_best_of_reports_the_clinch(){
+    rps().args(["rock", "--seed", "8", "--best-of", "7", "--no-color"])
+        .assert()
+        .code(2)
+        .stdout(predicates::str::ends_with("The opponent clinched the series 4-1\n"));
+}
+
+#[test]
+fn test_first_to_reports_the_clinching_score(){
+    rps().args(["rock", "--seed", "4", "--first-to", "3", "--no-color"])
+        .assert()
+        .code(0)
+        .stdout(predicates::str::contains("Longest streaks: 1 win, 1 loss, 4 ties"))
+        .stdout(predicates::str::ends_with("You clinched the series 3-2\n"));
+}
+
+#[test]
+fn test_first_to_against_endless_ties_stops(){
+    rps().args(["rock", "--opponent", "rock", "--first-to", "1", "--quiet"])
+        .assert()
+        .code(3)
+        .stdout(predicates::str::starts_with("Tie\nTie\n"))
+        .stderr("Neither side reached 1 win in 1000 rounds\n");
+}
+
+    /** ==== Sequence ==== **/
+
+#[test]
+fn test_opponent_follows_sequence(){
+    rps().args(["paper", "--rounds", "4", "--sequence", "rsp", "--quiet"])
+        .assert()
+        .stdout("UserWin\nOpponentWin\nTie\nUserWin\n");
+}
+
+#[test]
+fn test_invalid_sequence_is_rejected_before_playing(){
+    rps().args(["paper", "--rounds", "4", "--sequence", "rsq"])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr("Invalid sequence \"rsq\": Invalid move: \"q\"\n");
+}
+
+    /** ==== Scoreboard ==== **/
+
+#[test]
+fn test_scoreboard_after_seeded_rounds(){
+    rps().args(["rock", "--seed", "25", "--rounds", "3", "--scoreboard", "--quiet"])
+        .assert()
+        .stdout("OpponentWin\nTie\nTie\nRound 1: you Rock vs Paper — loss\nRound 2: you Rock vs Rock — tie\nRound 3: you Rock vs Rock — tie\nMargin: the opponent by 1\n");
+}
+
+    /** ==== Version detail ==== **/
+
+#[test]
+fn test_version_detail_exits_without_playing(){
+    rps().arg("--version-detail")
+        .assert()
+        .code(0)
+        .stdout(predicates::str::contains("AI strategies: random, frequency, win-stay-lose-shift"));
+}
+
+    /** ==== Conflicts ==== **/
+
+#[test]
+fn test_conflicting_options_are_rejected(){
+    rps().args(["rock", "--quiet", "--verbose", "--best-of", "3", "--rounds", "5"])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr("Conflicting options: --quiet and --verbose; --best-of and --rounds\n");
+}
+
+    /** ==== Explain rules ==== **/
+
+#[test]
+fn test_explain_classic_rules(){
+    rps().arg("--explain-rules")
+        .assert()
+        .code(0)
+        .stdout("Rock beats Scissors; Paper beats Rock; Scissors beats Paper\n");
+}
+
+#[test]
+fn test_explain_rules_file(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rules.toml");
+    std::fs::write(&path, "moves = [\"Fire\", \"Water\"]\n[beats]\nWater = [\"Fire\"]\n").unwrap();
+    rps().args(["--explain-rules", "--rules", path.to_str().unwrap()])
+        .assert()
+        .code(0)
+        .stdout("Water beats Fire\n");
+}
+
+    /** ==== Dry run ==== **/
+
+#[test]
+fn test_valid_dry_run(){
+    rps().args(["rock", "--weights", "3,1,1", "--ai", "frequency", "--dry-run"])
+        .assert()
+        .code(0)
+        .stdout("Dry run: everything is valid\n");
+}
+
+#[test]
+fn test_dry_run_with_bad_weights(){
+    rps().args(["rock", "--weights", "1,2", "--dry-run"])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr("Invalid weights: expected 3 weights, found 2\n");
+    rps().args(["rock", "--weights", "1,x", "--dry-run"]).assert().code(2).stdout("");
+}
+
+    /** ==== Case-insensitive values ==== **/
+
+#[test]
+fn test_mixed_case_strategy(){
+    rps().args(["paper", "--ai", "Always-Rock"]).assert().code(0);
+}
+
+#[test]
+fn test_misspelt_strategy_lists_options(){
+    rps().args(["paper", "--ai", "frequnecy"])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("[possible values: random, frequency, win-stay-lose-shift, markov"))
+        .stderr(predicates::str::contains("Did you mean \"frequency\"?"));
+}
+
+    /** ==== Paced simulation ==== **/
+
+#[test]
+fn test_paced_simulation_shows_running_totals(){
+    // Piped output is never paused, so this doesn't wait.
+    rps().args(["rock", "--simulate", "3", "--delay-ms", "500", "--ai", "always-paper"])
+        .assert()
+        .code(2)
+        .stdout(concat!(
+            "Game 1: you Rock vs Paper — loss. You 0 - Opponent 1 (0 ties)\n",
+            "Game 2: you Rock vs Paper — loss. You 0 - Opponent 2 (0 ties)\n",
+            "Game 3: you Rock vs Paper — loss. You 0 - Opponent 3 (0 ties)\n",
+            "Simulated 3 games: 0.0% wins, 100.0% losses, 0.0% ties\n",
+        ));
+}
+
+    /** ==== CSV ==== **/
+
+#[test]
+fn test_csv_single_game(){
+    let output = rps().args(["rock", "--seed", "25", "--format", "csv", "--csv-header"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("user_move,opponent_move,result"));
+    let fields : Vec<&str> = lines.next().unwrap().split(',').collect();
+    assert_eq!(fields, ["rock", "paper", "OpponentWin"]);
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn test_csv_without_header(){
+    rps().args(["scissors", "--seed", "25", "--format", "csv"]).assert().code(0).stdout("scissors,paper,UserWin\n");
+}
+
+    /** ==== Practice ==== **/
+
+#[test]
+fn test_practice_coaches_after_a_loss(){
+    rps().args(["--interactive", "--practice", "--sequence", "rp", "--no-color"])
+        .write_stdin("scissors\npaper\nquit\n")
+        .assert()
+        .stdout(predicates::str::contains("Opponent's move: Rock. You lose!\nYou should have played Paper\n"))
+        .stdout(predicates::str::contains("You should have played Scissors\n"));
+}
+
+    /** ==== Forced opponent ==== **/
+
+#[test]
+fn test_forced_opponent_move(){
+    rps().args(["rock", "--opponent", "scissors", "--quiet"]).assert().code(0).stdout("UserWin\n");
+}
+
+#[test]
+fn test_forced_opponent_conflicts_with_seed(){
+    rps().args(["rock", "--opponent", "scissors", "--seed", "25"])
+        .assert()
+        .code(3)
+        .stderr("Conflicting options: --seed and --opponent\n");
+}
+
+#[test]
+fn test_invalid_forced_opponent_move(){
+    rps().args(["rock", "--opponent", "lizard"])
+        .assert()
+        .code(3)
+        .stderr("Invalid opponent move \"lizard\": Invalid move: \"lizard\"\n");
+}
+
+    /** ==== Config file ==== **/
+
+#[test]
+fn test_config_file_sets_the_strategy(){
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("rps.toml"), "ai = \"always-paper\"\n").unwrap();
+    rps().current_dir(dir.path()).args(["rock", "--quiet"]).assert().code(2).stdout("OpponentWin\n");
+}
+
+#[test]
+fn test_flag_overrides_config_file(){
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("rps.toml"), "ai = \"always-paper\"\n").unwrap();
+    rps().current_dir(dir.path()).args(["rock", "--quiet", "--ai", "always-scissors"]).assert().code(0);
+}
+
+#[test]
+fn test_unknown_config_key_only_warns(){
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("rps.toml"), "ai = \"always-paper\"\nsound = true\n").unwrap();
+    rps().current_dir(dir.path())
+        .args(["rock", "--quiet"])
+        .assert()
+        .code(2)
+        .stderr("Warning: rps.toml: unknown key \"sound\"\n");
+}
+
+#[test]
+fn test_config_file_can_turn_color_on(){
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("rps.toml"), "color = true\n").unwrap();
+    let colored = rps().current_dir(dir.path()).args(["scissors", "--seed", "25"]).output().unwrap();
+    assert!(String::from_utf8(colored.stdout).unwrap().contains('\x1b'));
+    let plain = rps().current_dir(dir.path()).args(["scissors", "--seed", "25", "--no-color"]).output().unwrap();
+    assert!(!String::from_utf8(plain.stdout).unwrap().contains('\x1b'))
+}
+
+#[test]
+fn test_config_file_in_the_config_directory(){
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("rps")).unwrap();
+    std::fs::write(dir.path().join("rps").join("rps.toml"), "ai = \"always-paper\"\n").unwrap();
+    rps().env("XDG_CONFIG_HOME", dir.path()).args(["rock", "--quiet"]).assert().code(2).stdout("OpponentWin\n");
+}
+
+    /** ==== Streaks ==== **/
+
+#[test]
+fn test_streaks_after_rounds(){
+    rps().args(["rock", "--rounds", "6", "--sequence", "sssrrp"])
+        .assert()
+        .code(0)
+        .stdout(predicates::str::contains("Longest streaks: 3 wins, 1 loss, 2 ties\nFinal: You 3 - Opponent 1 (2 ties)\n"));
+}
+
+    /** ==== Move counts ==== **/
+
+#[test]
+fn test_count_after_seeded_rounds(){
+    rps().args(["rock", "--seed", "25", "--rounds", "3", "--count", "--quiet"])
+        .assert()
+        .stdout("OpponentWin\nTie\nTie\nOpponent: Rock×2 Paper×1 Scissors×0\n");
+}
+
+    /** ==== Lizard-spock abbreviations ==== **/
+
+#[test]
+fn test_spock_abbreviation(){
+    rps().args(["k", "--variant", "lizard-spock", "--opponent", "scissors"]).assert().code(0);
+}
+
+#[test]
+fn test_bare_s_is_ambiguous_under_lizard_spock(){
+    rps().args(["s", "--variant", "lizard-spock"])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::starts_with("error: Ambiguous move: \"s\" could be scissors or spock; spell it out\n"));
+}
+
+    /** ==== Auto ==== **/
+
+#[test]
+fn test_auto_plays_both_sides(){
+    rps().args(["--auto", "always-paper:always-rock", "--rounds", "2", "--no-color"])
+        .assert()
+        .code(0)
+        .stdout("Round 1: always-paper Paper vs always-rock Rock — always-paper wins\nRound 2: always-paper Paper vs always-rock Rock — always-paper wins\nFinal: always-paper 2 - always-rock 0 (0 ties)\n");
+}
+
+#[test]
+fn test_auto_rejects_bad_matchup(){
+    rps().args(["--auto", "random"])
+        .assert()
+        .code(3)
+        .stderr("Invalid matchup \"random\": expected two strategies, e.g. frequency:random\n");
+}
+
+    /** ==== Compare ==== **/
+
+#[test]
+fn test_compare_takes_a_matchup_like_auto(){
+    rps().args(["--simulate", "100", "--compare", "always-rock:always-paper", "--no-color"])
+        .assert()
+        .code(2)
+        .stdout("always-rock won 0.0% of 100 games, always-paper 100.0% (0.0% ties)\nalways-paper is ahead by 100.0 ± 0.0 points (95% confidence)\n");
+}
+
+#[test]
+fn test_quiet_compare_prints_only_the_result(){
+    rps().args(["--simulate", "100", "--compare", "always-paper:always-rock", "--quiet"])
+        .assert()
+        .code(0)
+        .stdout("UserWin\n");
+}
+
+    /** ==== House edge ==== **/
+
+#[test]
+fn test_full_house_edge_always_loses(){
+    rps().args(["paper", "--house-edge", "1", "--quiet"]).assert().code(2).stdout("OpponentWin\n");
+}
+
+#[test]
+fn test_house_edge_is_single_game_only(){
+    rps().args(["paper", "--house-edge", "0.2", "--rounds", "3"])
+        .assert()
+        .code(3)
+        .stderr("Conflicting options: --house-edge and --rounds\n");
+}
+
+    /** ==== Split output ==== **/
+
+#[test]
+fn test_split_output_separates_moves_from_result(){
+    let output = rps().args(["scissors", "--opponent", "paper", "--split-output"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "You win!\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "Opponent's move: Paper.\n")
+}
+
+#[test]
+fn test_output_is_combined_by_default(){
+    rps().args(["scissors", "--opponent", "paper"])
+        .assert()
+        .stdout("Opponent's move: Paper. You win!\n")
+        .stderr("");
+}
+
+    /** ==== Replay ==== **/
+
+#[test]
+fn test_replay_round_trips_a_logged_game(){
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("game.csv");
+    let log = log.to_str().unwrap();
+    let played = rps().args(["rock", "--seed", "25", "--rounds", "3", "--log", log, "--no-color"]).output().unwrap();
+    let replayed = rps().args(["--replay", log, "--no-color"]).output().unwrap();
+    assert_eq!(replayed.status.code(), played.status.code());
+    let rounds = "Opponent's move: Paper. You lose!\nOpponent's move: Rock. Tie\nOpponent's move: Rock. Tie\n";
+    assert!(String::from_utf8(played.stdout).unwrap().starts_with(rounds));
+    assert_eq!(String::from_utf8(replayed.stdout).unwrap(), format!("{}Final: You 0 - Opponent 1 (2 ties)\n", rounds))
+}
+
+#[test]
+fn test_replay_reports_malformed_rows(){
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("game.csv");
+    std::fs::write(&log, "user_move,opponent_move,result,seed\nrock,paper,UserWin,1\npaper,rock,UserWin,2\n").unwrap();
+    rps().args(["--replay", log.to_str().unwrap(), "--quiet"])
+        .assert()
+        .code(0)
+        .stdout("UserWin\n")
+        .stderr("Line 2: rock vs paper is OpponentWin, not \"UserWin\"\n");
+}
+
+#[test]
+fn test_replay_reads_a_forfeit(){
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("game.csv");
+    std::fs::write(&log, "user_move,opponent_move,result,seed,tie_policy\n,,OpponentWin,4,tie\nrock,scissors,UserWin,4,tie\n").unwrap();
+    rps().args(["--replay", log.to_str().unwrap(), "--no-color"])
+        .assert()
+        .code(1)
+        .stdout("Time's up! The opponent wins the round.\nOpponent's move: Scissors. You win!\nFinal: You 1 - Opponent 1 (0 ties)\n");
+}
+
+#[test]
+fn test_replay_rejects_a_file_that_is_not_a_transcript(){
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("notes.csv");
+    std::fs::write(&log, "date,score\n").unwrap();
+    rps().args(["--replay", log.to_str().unwrap()]).assert().code(3);
+}
+
+    /** ==== Max rounds ==== **/
+
+#[test]
+fn test_max_rounds_ends_interactive_session(){
+    rps().args(["--interactive", "--max-rounds", "2", "--opponent", "scissors", "--no-color"])
+        .write_stdin("rock\npaper\nrock\nrock\n")
+        .assert()
+        .code(1)
+        .stdout(predicates::str::contains("Reached the limit of 2 rounds.\n"))
+        .stdout(predicates::str::ends_with("Final: You 1 - Opponent 1 (0 ties)\n"));
+}
+
+    /** ==== Output file ==== **/
+
+#[test]
+fn test_output_file_gets_the_results(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("results.txt");
+    std::fs::write(&path, "left over from an earlier run\n").unwrap();
+    rps().args(["scissors", "--seed", "25", "--rounds", "2", "--output-file", path.to_str().unwrap()])
+        .assert()
+        .code(1)
+        .stdout("");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "Opponent's move: Paper. You win!\nOpponent's move: Rock. You lose!\nLongest streaks: 1 win, 1 loss, 0 ties\nFinal: You 1 - Opponent 1 (0 ties)\n"
+    );
+}
+
+#[test]
+fn test_output_file_that_cannot_be_created(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing").join("results.txt");
+    rps().args(["rock", "--output-file", path.to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr(predicates::str::starts_with("Could not open output file "));
+}
+
+    /** ==== Challenge ==== **/
+
+#[test]
+fn test_challenge_plays_the_seeds_moves_whatever_you_play(){
+    for input in ["rock\nrock\nrock\n", "paper\nlizard\nscissors\nscissors\n"] {
+        rps().args(["--interactive", "--challenge", "--seed", "0", "--no-color"])
+            .write_stdin(input)
+            .assert()
+            .stdout(predicates::str::is_match("(?s)Opponent's move: Scissors.*Opponent's move: Paper.*Opponent's move: Rock").unwrap());
+    }
+}
+
+#[test]
+fn test_challenge_shows_a_random_seed(){
+    rps().args(["--interactive", "--challenge", "--no-color"])
+        .write_stdin("rock\npaper\n")
+        .assert()
+        .stderr(predicates::str::contains("(seed: "));
+    rps().args(["--interactive", "--challenge", "--seed", "0", "--no-color"])
+        .write_stdin("rock\npaper\n")
+        .assert()
+        .stderr("");
+}
+
+    /** ==== Hotkeys ==== **/
+
+#[test]
+fn test_hotkeys_fall_back_to_lines_without_a_terminal(){
+    rps().args(["--interactive", "--hotkeys", "--opponent", "scissors", "--no-color"])
+        .write_stdin("r\npaper\nquit\n")
+        .assert()
+        .code(1)
+        .stdout(predicates::str::ends_with("Final: You 1 - Opponent 1 (0 ties)\n"));
+}
+
+/**
+ * Commits to `pattern` and returns the commitment to publish and what to
+ * reveal later.
+ */
+fn committed(pattern : &str) -> (String, String) {
+    let output = rps().args([pattern, "--commit", "--format", "json"]).output().unwrap();
+    let json : serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    (json["commitment"].as_str().unwrap().to_string(), json["reveal"].as_str().unwrap().to_string())
+}
+
+    /** ==== Commit-reveal ==== **/
+
+#[test]
+fn test_commit_then_reveal(){
+    let ((first, first_reveal), (second, second_reveal)) = (committed("rock"), committed("scissors"));
+    rps().args(["--reveal", &first, &first_reveal, &second, &second_reveal])
+        .assert()
+        .code(0)
+        .stdout("Player 1's move: Rock. Player 2's move: Scissors. Player 1 wins!\n");
+}
+
+#[test]
+fn test_tampered_reveal_is_rejected(){
+    let (first, first_reveal) = committed("rock");
+    let (second, second_reveal) = committed("scissors");
+    let second_reveal = second_reveal.replacen("scissors", "paper", 1);
+    rps().args(["--reveal", &first, &first_reveal, &second, &second_reveal])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr("Player 2: The move doesn't match its commitment\n");
+}
+
+#[test]
+fn test_recommitting_after_the_other_reveal_is_rejected(){
+    let (first, first_reveal) = committed("rock");
+    let (second, _) = committed("scissors");
+    // Player 2 sees the rock and makes a fresh commitment to paper.
+    let (_, fresh_reveal) = committed("paper");
+    rps().args(["--reveal", &first, &first_reveal, &second, &fresh_reveal])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr("Player 2: The move doesn't match its commitment\n");
+}
+
+    /** ==== Banner ==== **/
+
+#[test]
+fn test_plain_banner_has_no_escapes(){
+    let output = rps().args(["scissors", "--seed", "25", "--banner", "--no-color"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("You:      Scissors"));
+    assert!(stdout.contains("Opponent: Paper"));
+    assert!(stdout.contains("WIN"));
+    assert!(!stdout.contains('\x1b'))
+}
+
+#[test]
+fn test_banner_leaves_json_alone(){
+    rps().args(["scissors", "--seed", "25", "--banner", "--format", "json"])
+        .assert()
+        .code(0)
+        .stdout("{\"opponent_move\":\"paper\",\"user_move\":\"scissors\",\"result\":\"UserWin\"}\n");
+}
+
+#[test]
+fn test_ascii_art_leaves_json_alone(){
+    rps().args(["rock", "--seed", "1", "--ascii", "--format", "json"])
+        .assert()
+        .code(2)
+        .stdout("{\"opponent_move\":\"paper\",\"user_move\":\"rock\",\"result\":\"OpponentWin\"}\n");
+}
+
+#[test]
+fn test_ascii_art_draws_both_hands(){
+    rps().args(["rock", "--seed", "1", "--ascii"])
+        .assert()
+        .code(2)
+        .stdout(predicates::str::contains("Your move: Rock\n    _______\n---'   ____)\n"))
+        .stdout(predicates::str::contains("Opponent's move: Paper\n"))
+        .stdout(predicates::str::ends_with("You lose!\n"));
+}
+
+    /** ==== Opponent file ==== **/
+
+#[test]
+fn test_opponent_file_drives_the_rounds(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("moves.txt");
+    std::fs::write(&path, "scissors\n\nrock\npaper\n").unwrap();
+    rps().args(["rock", "--rounds", "3", "--opponent-file", path.to_str().unwrap(), "--format", "csv"])
+        .assert()
+        .code(1)
+        .stdout(predicates::str::starts_with("rock,scissors,UserWin\nrock,rock,Tie\nrock,paper,OpponentWin\n"));
+}
+
+#[test]
+fn test_opponent_file_too_short_for_the_rounds(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("moves.txt");
+    std::fs::write(&path, "scissors\n").unwrap();
+    rps().args(["rock", "--rounds", "2", "--opponent-file", path.to_str().unwrap(), "--format", "csv"])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr(format!("{}: 1 move for a game of up to 2 rounds\n", path.display()));
+}
+
+#[test]
+fn test_opponent_file_running_out(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("moves.txt");
+    std::fs::write(&path, "scissors\n").unwrap();
+    rps().args(["--interactive", "--opponent-file", path.to_str().unwrap(), "--format", "csv"])
+        .write_stdin("rock\nrock\n")
+        .assert()
+        .code(3)
+        .stdout("rock,scissors,UserWin\n")
+        .stderr(format!("{}: ran out of moves in round 2\n", path.display()));
+}
+
+    /** ==== Sudden death ==== **/
+
+#[test]
+fn test_sudden_death_after_a_level_series(){
+    rps().args(["rock", "--seed", "1", "--rounds", "4", "--sudden-death", "--no-color"])
+        .assert()
+        .code(0)
+        .stdout(predicates::str::contains(
+            "Opponent's move: Rock. Tie\nLevel at 1-1: sudden death!\nOpponent's move: Rock. Tie\nOpponent's move: Scissors. You win!\nYou won the sudden death in round 2\n"
+        ));
+}
+
+    /** ==== Probabilities ==== **/
+
+#[test]
+fn test_probabilities_before_the_game(){
+    rps().args(["rock", "--seed", "25", "--probabilities", "--no-color"])
+        .assert()
+        .code(2)
+        .stdout("Win 33.3% / Tie 33.3% / Lose 33.3%\nOpponent's move: Paper. You lose!\n");
+}
+
+#[test]
+fn test_probabilities_follow_the_weights(){
+    rps().args(["rock", "--weights", "1,0,1", "--probabilities", "--no-color"])
+        .assert()
+        .stdout(predicates::str::starts_with("Win 50.0% / Tie 50.0% / Lose 0.0%\n"));
+}
+
+    /** ==== Shuffle bag ==== **/
+
+#[test]
+fn test_shuffle_bag_rejects_replayed_ties(){
+    // With seed 1 the last move of a bag is rock, which used to stop the
+    // game partway through.
+    rps().args(["rock", "--rounds", "9", "--ai", "shuffle-bag", "--no-ties", "--seed", "1"])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr("--ai shuffle-bag can't replay ties: the last move in each bag is fixed, so a re-roll may only ever tie\n");
+}
+
+#[test]
+fn test_shuffle_bag_across_rounds(){
+    let output = rps().args(["rock", "--rounds", "9", "--ai", "shuffle-bag", "--seed", "3", "--format", "csv"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let moves : Vec<&str> = stdout.lines().take(9).map(|line| line.split(',').nth(1).unwrap()).collect();
+    for bag in moves.chunks(3) {
+        let mut sorted = bag.to_vec();
+        sorted.sort();
+        assert_eq!(sorted, ["paper", "rock", "scissors"])
+    }
+}
+
+    /** ==== Input socket ==== **/
+
+#[test]
+#[cfg(unix)]
+fn test_input_socket_round_trip(){
+    use std::{io::{BufRead, BufReader, Write}, os::unix::net::UnixStream, thread, time::Duration};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rps.sock");
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("rock_paper_scissors"))
+        .args(["--input-socket", path.to_str().unwrap(), "--seed", "25"])
+        .env_remove("RPS_MOVE")
+        .spawn()
+        .unwrap();
+    let connect = || {
+        for _ in 0..100 {
+            if let Ok(stream) = UnixStream::connect(&path) {
+                return stream;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("the server never started listening");
+    };
+
+    // A client that hangs up without sending anything doesn't stop the server.
+    drop(connect());
+    let mut stream = connect();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    stream.write_all(b"scissors\n").unwrap();
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply).unwrap();
+    server.kill().unwrap();
+    server.wait().unwrap();
+    assert_eq!(reply, "Opponent's move: Paper. You win!\n")
+}
+
+#[test]
+#[cfg(unix)]
+fn test_input_socket_in_use_is_left_alone(){
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rps.sock");
+    let _listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    rps().args(["--input-socket", path.to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("another server is already using it"));
+    assert!(path.exists())
+}
+
+    /** ==== Seed range ==== **/
+
+#[test]
+fn test_seed_range_counts_outcomes(){
+    rps().args(["rock", "--seed-range", "0..3", "--verbose", "--no-color"])
+        .assert()
+        .code(1)
+        .stdout("Seed 0: you Rock vs Scissors — win\nSeed 1: you Rock vs Paper — loss\nSeed 2: you Rock vs Rock — tie\nSeeds 0..=2: 1 win, 1 tie, 1 loss\n");
+}
+
+    /** ==== Difficulty ==== **/
+
+#[test]
+fn test_easy_difficulty_plays_like_random(){
+    let random = rps().args(["rock", "--seed", "1", "--rounds", "5", "--ai", "random"]).output().unwrap();
+    rps().args(["rock", "--seed", "1", "--rounds", "5", "--difficulty", "easy"])
+        .assert()
+        .stdout(String::from_utf8(random.stdout).unwrap());
+}
+
+#[test]
+fn test_hard_difficulty_beats_a_repeated_move(){
+    rps().args(["rock", "--seed", "1", "--rounds", "20", "--difficulty", "hard", "--quiet"]).assert().code(2);
+}
+
+#[test]
+fn test_difficulty_and_ai_conflict(){
+    rps().args(["rock", "--difficulty", "hard", "--ai", "markov"])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("--difficulty"));
+}